@@ -0,0 +1,114 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(clap::ValueEnum, Parser, Clone, Default, Debug)]
+enum Mode {
+    // Rosbag file
+    #[default]
+    Rosbag,
+    // MCAP file
+    Mcap,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about)]
+struct Args {
+    /// Input rosbag path
+    #[clap(short, long)]
+    input: PathBuf,
+
+    /// Output UVT file path
+    #[clap(short, long, required_unless_present = "list_topics")]
+    output: Option<PathBuf>,
+
+    /// File mode
+    #[clap(short, long, default_value_t, value_enum)]
+    mode: Mode,
+
+    /// Map topic
+    #[clap(long, default_value = "/map")]
+    map_topic: String,
+
+    /// Trajectory topic
+    #[clap(long, default_value = "/odom")]
+    traj_topic: String,
+
+    /// Store the map coordinates in double precision, e.g. for UTM coordinates
+    #[clap(long)]
+    double_precision: bool,
+
+    /// List the topics of the rosbag with their message types, then exit
+    #[clap(long)]
+    list_topics: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if !args.input.exists() {
+        eprintln!("File does not exist: {}", args.input.display());
+        std::process::exit(1);
+    }
+
+    if args.list_topics {
+        let topics = match args.mode {
+            Mode::Rosbag => uvt::Uvt::list_rosbag_topics(&args.input),
+            Mode::Mcap => uvt::Uvt::list_mcap_topics(&args.input),
+        };
+        match topics {
+            Ok(topics) => {
+                for (topic, msg_type) in topics {
+                    println!("{} ({})", topic, msg_type);
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "Could not list the topics of {}: {}",
+                    args.input.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let Some(output) = args.output else {
+        unreachable!("clap requires --output without --list-topics");
+    };
+
+    let options = uvt::ReadOptions {
+        map_precision: match args.double_precision {
+            true => uvt::MapPrecision::Double,
+            false => uvt::MapPrecision::Single,
+        },
+        on_warning: Some(|message| eprintln!("Warning: {}", message)),
+        ..Default::default()
+    };
+    let (map_topic, traj_topic) = (&args.map_topic, &args.traj_topic);
+    let uv_traj = match args.mode {
+        Mode::Rosbag => {
+            uvt::Uvt::read_rosbag_with_options(&args.input, map_topic, traj_topic, &options)
+        }
+        Mode::Mcap => {
+            uvt::Uvt::read_mcap_with_options(&args.input, map_topic, traj_topic, &options)
+        }
+    };
+    let uv_traj = match uv_traj {
+        Ok(uv_traj) => uv_traj,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", args.input.display(), e);
+            eprintln!("Use --list-topics to list the topics of the rosbag");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = uv_traj.write_file(&output) {
+        eprintln!("Could not write {}: {}", output.display(), e);
+        std::process::exit(1);
+    }
+    println!(
+        "Wrote {} poses to {}",
+        uv_traj.num_poses(),
+        output.display()
+    );
+}
@@ -0,0 +1,124 @@
+use std::process::{Command, Output};
+
+use uvt::Point;
+use uvt::fixtures::{self, MessageWriter, temp_path, write_bag};
+use uvt::pose::{Pose, Time};
+
+/// Serializes a sensor_msgs/PointCloud2 with FLOAT32 `x`, `y` and `z` fields.
+fn pointcloud2(points: &[Point]) -> Vec<u8> {
+    fixtures::pointcloud2(MessageWriter::ros1(), &fixtures::xyz_pointcloud(points))
+}
+
+/// Serializes a nav_msgs/Odometry at position `(x, 0, 0)`, with zero covariances and twist.
+fn odometry(seq: u32, x: f64) -> Vec<u8> {
+    let stamp = Time {
+        sec: seq as i32 + 1,
+        nanosec: 0,
+    };
+    let pose = Pose::from_6dof((x, 0.0, 0.0, 0.0, 0.0, 0.0));
+    fixtures::odometry(
+        MessageWriter::ros1(),
+        seq,
+        stamp,
+        "odom",
+        "base_link",
+        &pose,
+    )
+}
+
+fn convert(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_uvt-convert"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_convert_rosbag() {
+    let bag_path = temp_path("mission.bag");
+    let uvt_path = temp_path("mission.uvt");
+    write_bag(
+        &bag_path,
+        &[
+            (
+                "/map",
+                "sensor_msgs/PointCloud2",
+                vec![pointcloud2(&[
+                    Point::new(1.0, 2.0, 3.0),
+                    Point::new(4.0, 5.0, 6.0),
+                ])],
+            ),
+            (
+                "/odom",
+                "nav_msgs/Odometry",
+                vec![odometry(0, 0.0), odometry(1, 1.0), odometry(2, 2.0)],
+            ),
+        ],
+    );
+    let (bag, uvt) = (bag_path.to_str().unwrap(), uvt_path.to_str().unwrap());
+
+    let output = convert(&["--input", bag, "--output", uvt, "--mode", "rosbag"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let converted = uvt::Uvt::read_file(&uvt_path).unwrap();
+    std::fs::remove_file(&uvt_path).unwrap();
+    assert_eq!(converted.num_poses(), 3);
+    assert_eq!(converted.trajectory_frame_id().unwrap(), "odom");
+    assert_eq!(
+        converted.trajectory[2].pose.position,
+        Point::new(2.0, 0.0, 0.0)
+    );
+
+    let output = convert(&["--input", bag, "--output", uvt, "--map-topic", "/mapp"]);
+    std::fs::remove_file(&bag_path).unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'/mapp'"));
+    assert!(!uvt_path.exists());
+}
+
+#[test]
+fn test_convert_missing_input() {
+    let missing = temp_path("missing.bag");
+    let uvt_path = temp_path("missing.uvt");
+    let output = convert(&[
+        "--input",
+        missing.to_str().unwrap(),
+        "--output",
+        uvt_path.to_str().unwrap(),
+    ]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File does not exist"));
+}
+
+#[test]
+fn test_list_topics() {
+    let bag_path = temp_path("topics.bag");
+    write_bag(
+        &bag_path,
+        &[
+            ("/odom", "nav_msgs/Odometry", vec![odometry(0, 0.0)]),
+            (
+                "/velodyne_points",
+                "sensor_msgs/PointCloud2",
+                vec![pointcloud2(&[Point::new(1.0, 2.0, 3.0)])],
+            ),
+        ],
+    );
+
+    let output = convert(&["--input", bag_path.to_str().unwrap(), "--list-topics"]);
+    std::fs::remove_file(&bag_path).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "/odom (nav_msgs/Odometry)\n/velodyne_points (sensor_msgs/PointCloud2)\n"
+    );
+}
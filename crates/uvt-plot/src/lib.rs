@@ -15,7 +15,7 @@
 //!
 //! ## Example
 //!
-//! ```rust
+//! ```no_run
 //! use uvt;
 //! use uvt_plot;
 //!
@@ -23,115 +23,826 @@
 //! let my_uvt = uvt::Uvt::read_file("example.uvt").unwrap();
 //!
 //! // Plot trajectory
-//! uvt_plot::plot_trajectory(my_uvt);
+//! uvt_plot::plot_trajectory(my_uvt).unwrap();
 //! ```
-use std::{path::PathBuf, str::FromStr};
+use std::{ops::Range, path::Path};
 
 use plotters::{
     chart::{self, ChartBuilder},
-    prelude::{BitMapBackend, Circle, IntoDrawingArea},
-    series::LineSeries,
-    style::{RED, WHITE},
+    coord::Shift,
+    drawing::DrawingAreaErrorKind,
+    element::{EmptyElement, PathElement, Pixel},
+    prelude::{BitMapBackend, Circle, DrawingArea, DrawingBackend, IntoDrawingArea, Rectangle},
+    style::{BLACK, Color, GREEN, RGBColor, WHITE},
 };
-use uvt;
+
+/// Errors raised while plotting a UVT.
+#[derive(Debug)]
+pub enum PlotError {
+    /// The plot could not be drawn, or the image could not be written.
+    Drawing(String),
+    /// The trajectory has no pose to plot: it is empty, or no position is finite.
+    NothingToPlot(String),
+    /// The map could not be read from the UVT.
+    Uvt(uvt::UvtError),
+}
+
+impl std::fmt::Display for PlotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlotError::Drawing(msg) => write!(f, "Drawing error: {}", msg),
+            PlotError::NothingToPlot(msg) => write!(f, "Nothing to plot: {}", msg),
+            PlotError::Uvt(e) => write!(f, "Invalid UVT: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PlotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlotError::Uvt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<uvt::UvtError> for PlotError {
+    fn from(e: uvt::UvtError) -> Self {
+        PlotError::Uvt(e)
+    }
+}
+
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for PlotError {
+    fn from(e: DrawingAreaErrorKind<E>) -> Self {
+        PlotError::Drawing(e.to_string())
+    }
+}
+
+/// Margin around the chart, in pixels
+const MARGIN: u32 = 20;
+/// Size of the label areas, left and bottom of the chart, in pixels
+const LABEL_AREA_SIZE: u32 = 40;
+/// Width of the colorbar area, right of the chart, in pixels
+const COLORBAR_AREA_SIZE: u32 = 100;
+/// Number of color steps of the colorbar
+const COLORBAR_STEPS: usize = 100;
+/// Padding around the trajectory, in map units
+const PADDING: f64 = 10.0;
+/// Length of the heading arrow, relative to the shortest range of the chart
+const ARROW_RATIO: f64 = 0.08;
+
+/// Quantity coloring the trajectory points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorBy {
+    /// Elevation (z) of the poses.
+    #[default]
+    Elevation,
+    /// Timestamp of the poses.
+    Time,
+}
+
+/// Coordinates forming the axes of the trajectory plot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlotView {
+    /// Top-down view, along x and y.
+    #[default]
+    TopXY,
+    /// Side view, along x and z.
+    SideXZ,
+    /// Side view, along y and z.
+    SideYZ,
+}
+
+impl PlotView {
+    /// Projects a point on the horizontal and vertical axes of the view.
+    fn project(self, pt: uvt::Point) -> (f64, f64) {
+        match self {
+            PlotView::TopXY => (pt.x, pt.y),
+            PlotView::SideXZ => (pt.x, pt.z),
+            PlotView::SideYZ => (pt.y, pt.z),
+        }
+    }
+
+    /// Labels of the horizontal and vertical axes of the view.
+    fn axis_labels(self) -> (&'static str, &'static str) {
+        match self {
+            PlotView::TopXY => ("x (m)", "y (m)"),
+            PlotView::SideXZ => ("x (m)", "z (m)"),
+            PlotView::SideYZ => ("y (m)", "z (m)"),
+        }
+    }
+}
+
+/// Options of the trajectory plot.
+#[derive(Debug, Clone)]
+pub struct PlotOptions {
+    /// Width of the image, in pixels. Defaults to 800.
+    pub width: u32,
+    /// Height of the image, in pixels. Defaults to 600.
+    pub height: u32,
+    /// Scales both axes equally, so that a meter spans as many pixels along x as along y.
+    /// Defaults to true.
+    pub equal_aspect: bool,
+    /// Quantity coloring the trajectory points. Defaults to the elevation.
+    pub color_by: ColorBy,
+    /// Coordinates forming the axes of the plot. Defaults to the top-down view.
+    pub view: PlotView,
+    /// Marks the first pose with a green circle and an arrow along its heading,
+    /// and the last pose with a black square. Defaults to true.
+    pub show_markers: bool,
+    /// Draws the map points in light gray under the trajectory, and includes the map
+    /// in the plot bounds. Defaults to false.
+    pub show_map: bool,
+    /// Maximum number of map points drawn, the map being subsampled above it.
+    /// Defaults to 100 000.
+    pub max_map_points: usize,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            equal_aspect: true,
+            color_by: ColorBy::Elevation,
+            view: PlotView::TopXY,
+            show_markers: true,
+            show_map: false,
+            max_map_points: 100_000,
+        }
+    }
+}
+
+/// Keeps evenly spaced points, at most `max_points` of them.
+fn subsample(points: Vec<uvt::Point>, max_points: usize) -> Vec<uvt::Point> {
+    if points.len() <= max_points {
+        return points;
+    }
+    let stride = points.len().div_ceil(max_points.max(1));
+    points.into_iter().step_by(stride).collect()
+}
+
+/// Computes the (min, max) bounds of the plotted data: the trajectory, and the map if shown.
+fn plot_bounds(uvt_file: &uvt::Uvt, options: &PlotOptions) -> Option<(uvt::Point, uvt::Point)> {
+    let traj_bounds = uvt_file.trajectory_bounds()?;
+    let map_bounds = options.show_map.then(|| uvt_file.map_bounds()).flatten();
+    Some(match map_bounds {
+        Some((map_min, map_max)) => {
+            let (min, max) = traj_bounds;
+            (
+                uvt::Point::new(
+                    min.x.min(map_min.x),
+                    min.y.min(map_min.y),
+                    min.z.min(map_min.z),
+                ),
+                uvt::Point::new(
+                    max.x.max(map_max.x),
+                    max.y.max(map_max.y),
+                    max.z.max(map_max.z),
+                ),
+            )
+        }
+        None => traj_bounds,
+    })
+}
+
+/// Computes the horizontal and vertical ranges of the chart, padded around the `(min, max)`
+/// trajectory bounds projected on the view.
+///
+/// With `equal_aspect`, the shorter range is widened around its center to match the aspect
+/// ratio of the plotting area.
+fn plot_ranges(
+    min: uvt::Point,
+    max: uvt::Point,
+    options: &PlotOptions,
+) -> (Range<f64>, Range<f64>) {
+    let (min, max) = (options.view.project(min), options.view.project(max));
+    let x_range = (min.0 - PADDING)..(max.0 + PADDING);
+    let y_range = (min.1 - PADDING)..(max.1 + PADDING);
+    if !options.equal_aspect {
+        return (x_range, y_range);
+    }
+
+    // Size of the plotting area, in pixels
+    let area_width = options
+        .width
+        .saturating_sub(2 * MARGIN + LABEL_AREA_SIZE + COLORBAR_AREA_SIZE)
+        .max(1) as f64;
+    let area_height = options
+        .height
+        .saturating_sub(2 * MARGIN + LABEL_AREA_SIZE)
+        .max(1) as f64;
+
+    // Map units per pixel, common to both axes
+    let scale = f64::max(
+        (x_range.end - x_range.start) / area_width,
+        (y_range.end - y_range.start) / area_height,
+    );
+    let centered = |range: Range<f64>, half_span: f64| {
+        let center = (range.start + range.end) / 2.0;
+        (center - half_span)..(center + half_span)
+    };
+    (
+        centered(x_range, scale * area_width / 2.0),
+        centered(y_range, scale * area_height / 2.0),
+    )
+}
+
+/// Returns sRGB polynomial approximation from Turbo color map, assuming `t` is normalized.
+/// Mirrors the colormap of `uvt-viz3d`.
+fn colormap_turbo_srgb(t: f32) -> RGBColor {
+    #![allow(clippy::excessive_precision)]
+    const R: [f32; 6] = [
+        0.13572138,
+        4.61539260,
+        -42.66032258,
+        132.13108234,
+        -152.94239396,
+        59.28637943,
+    ];
+    const G: [f32; 6] = [
+        0.09140261,
+        2.19418839,
+        4.84296658,
+        -14.18503333,
+        4.27729857,
+        2.82956604,
+    ];
+    const B: [f32; 6] = [
+        0.10667330,
+        12.64194608,
+        -60.58204836,
+        110.36276771,
+        -89.90310912,
+        27.34824973,
+    ];
+
+    debug_assert!((0.0..=1.0).contains(&t));
+
+    // Polynomial of degree 5 in t
+    let powers = [1.0, t, t * t, t.powi(3), t.powi(4), t.powi(5)];
+    let channel = |coefs: [f32; 6]| {
+        let v: f32 = coefs.iter().zip(powers).map(|(c, p)| c * p).sum();
+        (v * 255.0) as u8
+    };
+    RGBColor(channel(R), channel(G), channel(B))
+}
+
+/// Normalizes values over their min/max, a constant series and non-finite values
+/// being mapped to 0.5.
+fn normalize(values: &[f64]) -> Vec<f32> {
+    let (min, max) = value_range(values).unwrap_or((0.0, 0.0));
+    values
+        .iter()
+        .map(|&v| match max - min {
+            range if range > 0.0 && v.is_finite() => ((v - min) / range) as f32,
+            _ => 0.5,
+        })
+        .collect()
+}
+
+/// Computes the (min, max) range of the finite values, or `None` if there is none.
+fn value_range(values: &[f64]) -> Option<(f64, f64)> {
+    values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold(None, |range, &v| match range {
+            None => Some((v, v)),
+            Some((min, max)) => Some((f64::min(min, v), f64::max(max, v))),
+        })
+}
+
+/// Extracts the values of the trajectory coloring the plot.
+fn color_values<'a, I: IntoIterator<Item = &'a uvt::pose::PoseStamped>>(
+    trajectory: I,
+    color_by: ColorBy,
+) -> Vec<f64> {
+    trajectory
+        .into_iter()
+        .map(|pose| match color_by {
+            ColorBy::Elevation => pose.pose.position.z,
+            ColorBy::Time => pose.header.stamp.as_nanos() as f64 * 1e-9,
+        })
+        .collect()
+}
+
+/// Builds the polylines of an arrow of `length` along the heading (x axis) of `pose`,
+/// projected on the view. A heading orthogonal to the view, or a pose with non-finite
+/// values, has no arrow.
+fn heading_arrow(pose: &uvt::pose::Pose, view: PlotView, length: f64) -> Vec<Vec<(f64, f64)>> {
+    let m = pose.orientation.to_rotation_matrix();
+    let origin = view.project(pose.position);
+    let (dx, dy) = view.project(uvt::Point::new(m[0][0], m[1][0], m[2][0]));
+    let norm = dx.hypot(dy);
+    if norm.is_nan() || norm < 1e-6 {
+        return vec![];
+    }
+
+    let heading = dy.atan2(dx);
+    let point_at = |from: (f64, f64), angle: f64, len: f64| {
+        (from.0 + len * angle.cos(), from.1 + len * angle.sin())
+    };
+    let tip = point_at(origin, heading, length);
+    let head = |side: f64| {
+        point_at(
+            tip,
+            heading + side * 5.0 * std::f64::consts::FRAC_PI_6,
+            0.4 * length,
+        )
+    };
+    vec![vec![origin, tip], vec![head(-1.0), tip, head(1.0)]]
+}
+
+/// Draws a vertical colorbar of the Turbo colormap over `(min, max)`.
+fn draw_colorbar<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    (min, max): (f64, f64),
+    label: &str,
+) -> Result<(), PlotError> {
+    // A constant series still gets a colorbar
+    let (min, max) = if max > min {
+        (min, max)
+    } else {
+        (min - 0.5, max + 0.5)
+    };
+
+    let mut bar = ChartBuilder::on(area)
+        .margin(MARGIN)
+        .margin_bottom(MARGIN + LABEL_AREA_SIZE)
+        .margin_right(0)
+        .set_label_area_size(chart::LabelAreaPosition::Right, COLORBAR_AREA_SIZE / 2)
+        .build_cartesian_2d(0.0..1.0, min..max)?;
+    bar.configure_mesh()
+        .disable_mesh()
+        .disable_x_axis()
+        .y_desc(label)
+        .draw()?;
+
+    let step = (max - min) / COLORBAR_STEPS as f64;
+    bar.draw_series((0..COLORBAR_STEPS).map(|i| {
+        let t = (i as f32 + 0.5) / COLORBAR_STEPS as f32;
+        let (low, high) = (min + i as f64 * step, min + (i + 1) as f64 * step);
+        Rectangle::new([(0.0, low), (1.0, high)], colormap_turbo_srgb(t).filled())
+    }))?;
+    Ok(())
+}
 
 /// Plots the trajectory from a UVT file.
 ///
 /// This function generates a bird-eye view of the trajectory recorded in the UVT file
 /// and saves it as a PNG image (`traj.png`) in the current working directory.
+/// See [`plot_trajectory_to`] to choose the output file and the image size.
 ///
 /// # Arguments
 ///
 /// * `uvt_file` - A `uvt::Uvt` object containing the trajectory data.
 ///
+/// # Errors
+///
+/// Returns an error if the plot cannot be drawn or written, see [`plot_trajectory_to`].
+///
 /// # Example
 ///
-/// ```rust
+/// ```no_run
 /// use uvt;
 /// use uvt_plot;
 ///
 /// let my_uvt = uvt::Uvt::read_file("example.uvt").unwrap();
-/// uvt_plot::plot_trajectory(my_uvt);
+/// uvt_plot::plot_trajectory(my_uvt).unwrap();
 /// ```
 ///
 /// The resulting plot will be saved as `traj.png`.
-pub fn plot_trajectory(uvt_file: uvt::Uvt) {
-    let figpath: PathBuf = PathBuf::from_str("traj.png").unwrap();
+pub fn plot_trajectory(uvt_file: uvt::Uvt) -> Result<(), PlotError> {
+    plot_trajectory_to(&uvt_file, "traj.png", &PlotOptions::default())
+}
+
+/// Plots the trajectory from a UVT file into a PNG image.
+///
+/// Poses and map points with non-finite coordinates, e.g. from a corrupted recording,
+/// are skipped.
+///
+/// # Arguments
+///
+/// * `uvt_file` - A `uvt::Uvt` object containing the trajectory data.
+/// * `path` - The path of the PNG image.
+/// * `options` - The options of the plot, see [`PlotOptions`].
+///
+/// # Errors
+///
+/// Returns [`PlotError::NothingToPlot`] without writing the image if the trajectory is
+/// empty or has no finite position, and [`PlotError::Uvt`] if `options.show_map` is set
+/// but the map points cannot be read. Returns [`PlotError::Drawing`] if the plot cannot be
+/// drawn, or if the image cannot be written to `path`, e.g. because its directory does not exist.
+///
+/// # Example
+///
+/// ```no_run
+/// use uvt;
+/// use uvt_plot::{self, PlotOptions};
+///
+/// let my_uvt = uvt::Uvt::read_file("example.uvt").unwrap();
+/// let options = PlotOptions {
+///     width: 1920,
+///     height: 1080,
+///     ..Default::default()
+/// };
+/// uvt_plot::plot_trajectory_to(&my_uvt, "my_traj.png", &options).unwrap();
+/// ```
+pub fn plot_trajectory_to<P: AsRef<Path>>(
+    uvt_file: &uvt::Uvt,
+    path: P,
+    options: &PlotOptions,
+) -> Result<(), PlotError> {
+    let figpath = path.as_ref();
 
-    let positions: Vec<(f64, f64, f64)> = uvt_file
+    let poses: Vec<&uvt::pose::PoseStamped> = uvt_file
         .trajectory
         .iter()
-        .map(|pose| pose.pose.position)
-        .map(|pt| (pt.x, pt.y, pt.z))
+        .filter(|pose| pose.pose.position.is_finite())
+        .collect();
+    let positions: Vec<(f64, f64)> = poses
+        .iter()
+        .map(|pose| options.view.project(pose.pose.position))
         .collect();
 
-    let (xs, ys): (Vec<f64>, Vec<f64>) = positions.iter().map(|pt| (pt.0, pt.1)).unzip();
-    let zs: Vec<f64> = positions.iter().map(|pt| pt.2).collect();
+    let Some((min, max)) = plot_bounds(uvt_file, options) else {
+        let msg = match uvt_file.trajectory.is_empty() {
+            true => "the trajectory is empty",
+            false => "the trajectory has no finite position",
+        };
+        return Err(PlotError::NothingToPlot(msg.to_string()));
+    };
+    let (x_range, y_range) = plot_ranges(min, max, options);
+    // Read before creating the image, which is written even if the plot is interrupted
+    let map_points = match options.show_map {
+        true => subsample(uvt_file.map_points()?, options.max_map_points),
+        false => vec![],
+    };
 
-    let x_max = xs
-        .iter()
-        .max_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-    let x_min = xs
-        .iter()
-        .min_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-    let y_max = ys
-        .iter()
-        .max_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-    let y_min = ys
-        .iter()
-        .min_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-    let z_max = zs
-        .iter()
-        .max_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-    let z_min = zs
-        .iter()
-        .min_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-
-    let x_span = (x_min, x_max);
-    let y_span = (y_min, y_max);
-    let z_span = (z_min, z_max);
-
-    let fig = BitMapBackend::new(figpath.as_os_str(), (800, 600)).into_drawing_area();
-    fig.fill(&WHITE).unwrap();
-
-    let mut ctx = ChartBuilder::on(&fig)
-        .margin(20)
-        .set_label_area_size(chart::LabelAreaPosition::Left, 40)
-        .set_label_area_size(chart::LabelAreaPosition::Bottom, 40)
-        .build_cartesian_2d(
-            (x_span.0 - 10.0)..(x_span.1 + 10.0),
-            (y_span.0 - 10.0)..(y_span.1 + 10.0),
-        )
-        .unwrap();
-    ctx.configure_mesh().draw().unwrap();
+    let fig = BitMapBackend::new(figpath, (options.width, options.height)).into_drawing_area();
+    fig.fill(&WHITE)?;
+    let (chart_area, colorbar_area) =
+        fig.split_horizontally(options.width.saturating_sub(COLORBAR_AREA_SIZE));
+
+    let arrow_length =
+        ARROW_RATIO * f64::min(x_range.end - x_range.start, y_range.end - y_range.start);
+
+    let mut ctx = ChartBuilder::on(&chart_area)
+        .margin(MARGIN)
+        .set_label_area_size(chart::LabelAreaPosition::Left, LABEL_AREA_SIZE)
+        .set_label_area_size(chart::LabelAreaPosition::Bottom, LABEL_AREA_SIZE)
+        .build_cartesian_2d(x_range, y_range)?;
+    let (x_label, y_label) = options.view.axis_labels();
+    ctx.configure_mesh()
+        .x_desc(x_label)
+        .y_desc(y_label)
+        .draw()?;
 
-    ctx.draw_series(LineSeries::new(
-        (-100..100)
-            .map(|y| y as f64 / 100.0)
-            .map(|y| ((y * 10.0).sin(), y)),
-        &RED,
-    ))
-    .unwrap();
+    ctx.draw_series(
+        map_points
+            .into_iter()
+            .filter(uvt::Point::is_finite)
+            .map(|pt| Pixel::new(options.view.project(pt), RGBColor(200, 200, 200))),
+    )?;
 
+    let values = color_values(poses.iter().copied(), options.color_by);
     ctx.draw_series(
         positions
             .into_iter()
-            .map(|(x, y, _)| (x, y))
-            .map(|pt| Circle::new(pt, 1, &RED)),
-    )
-    .unwrap();
+            .zip(normalize(&values))
+            .map(|(pt, t)| Circle::new(pt, 1, colormap_turbo_srgb(t))),
+    )?;
+
+    if options.show_markers {
+        let (first, last) = (poses[0], poses[poses.len() - 1]);
+        ctx.draw_series(
+            heading_arrow(&first.pose, options.view, arrow_length)
+                .into_iter()
+                .map(|line| PathElement::new(line, GREEN.stroke_width(2))),
+        )?;
+        ctx.draw_series([Circle::new(
+            options.view.project(first.pose.position),
+            5,
+            GREEN.filled(),
+        )])?;
+        ctx.draw_series([EmptyElement::at(options.view.project(last.pose.position))
+            + Rectangle::new([(-4, -4), (4, 4)], BLACK.filled())])?;
+    }
+
+    let label = match options.color_by {
+        ColorBy::Elevation => "Elevation (m)",
+        ColorBy::Time => "Time (s)",
+    };
+    // The positions are finite, and so are their elevations and stamps
+    let range = value_range(&values).unwrap_or((0.0, 0.0));
+    draw_colorbar(&colorbar_area, range, label)?;
+    fig.present()?;
+
+    println!("Saved trajectory plot in {}", figpath.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn straight_line(n: u32) -> uvt::Uvt {
+        let trajectory = (0..n)
+            .map(|i| {
+                uvt::pose::PoseStamped::new(
+                    uvt::pose::Header {
+                        seq: i,
+                        stamp: uvt::pose::Time {
+                            sec: i as i32,
+                            nanosec: 0,
+                        },
+                        frame_id: String::from("map"),
+                    },
+                    uvt::pose::Pose::from_6dof((i as f64, 0.5 * i as f64, 0.0, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        uvt::Uvt::from_points(vec![], trajectory)
+    }
+
+    /// Reads the dimensions from the IHDR chunk of a PNG image.
+    fn png_size(path: &Path) -> (u32, u32) {
+        let content = std::fs::read(path).unwrap();
+        assert_eq!(&content[1..4], b"PNG");
+        let be_u32 = |b: &[u8]| u32::from_be_bytes(b.try_into().unwrap());
+        (be_u32(&content[16..20]), be_u32(&content[20..24]))
+    }
+
+    #[test]
+    fn test_plot_trajectory_to() {
+        let path = std::env::temp_dir().join(format!("uvt-plot-{}-traj.png", std::process::id()));
+        let options = PlotOptions {
+            width: 320,
+            height: 200,
+            ..Default::default()
+        };
+        plot_trajectory_to(&straight_line(10), &path, &options).unwrap();
+        let size = png_size(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(size, (320, 200));
+
+        // An empty trajectory writes nothing
+        let empty = std::env::temp_dir().join(format!("uvt-plot-{}-empty.png", std::process::id()));
+        let err = plot_trajectory_to(&straight_line(0), &empty, &options).unwrap_err();
+        assert!(matches!(err, PlotError::NothingToPlot(_)));
+        assert!(!empty.exists());
+    }
+
+    #[test]
+    fn test_plot_unwritable_output() {
+        let dir = std::env::temp_dir().join(format!("uvt-plot-{}-missing", std::process::id()));
+        let path = dir.join("traj.png");
+        let options = PlotOptions {
+            width: 320,
+            height: 200,
+            ..Default::default()
+        };
+
+        let err = plot_trajectory_to(&straight_line(10), &path, &options).unwrap_err();
+        assert!(matches!(err, PlotError::Drawing(_)));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_plot_unsupported_map() {
+        let path = std::env::temp_dir().join(format!("uvt-plot-{}-map.png", std::process::id()));
+        let mut uvt_file = straight_line(10);
+        uvt_file.map.data = vtkio::model::DataSet::inline(vtkio::model::ImageDataPiece {
+            extent: vtkio::model::Extent::Dims([1, 1, 1]),
+            data: vtkio::model::Attributes::new(),
+        });
+        let options = PlotOptions {
+            width: 320,
+            height: 200,
+            show_map: true,
+            ..Default::default()
+        };
+
+        let err = plot_trajectory_to(&uvt_file, &path, &options).unwrap_err();
+        assert!(matches!(err, PlotError::Uvt(uvt::UvtError::Vtk(_))));
+        assert!(!path.exists());
+
+        // The map is only read when shown
+        let options = PlotOptions {
+            show_map: false,
+            ..options
+        };
+        plot_trajectory_to(&uvt_file, &path, &options).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_plot_nan_pose() {
+        let path = std::env::temp_dir().join(format!("uvt-plot-{}-nan.png", std::process::id()));
+        let options = PlotOptions {
+            width: 320,
+            height: 200,
+            show_map: true,
+            ..Default::default()
+        };
+        let mut uvt_file = straight_line(10);
+        uvt_file.trajectory[4].pose.position.y = f64::NAN;
+        uvt_file.trajectory[0].pose.orientation.w = f64::NAN;
+
+        let (min, max) = plot_bounds(&uvt_file, &options).unwrap();
+        assert_eq!(min, uvt::Point::new(0.0, 0.0, 0.0));
+        assert_eq!(max, uvt::Point::new(9.0, 4.5, 0.0));
+        assert!(heading_arrow(&uvt_file.trajectory[0].pose, options.view, 1.0).is_empty());
+
+        plot_trajectory_to(&uvt_file, &path, &options).unwrap();
+        let size = png_size(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(size, (320, 200));
+
+        // Without any finite position, nothing is written
+        uvt_file
+            .trajectory
+            .iter_mut()
+            .for_each(|pose| pose.pose.position.x = f64::NAN);
+        assert!(plot_bounds(&uvt_file, &options).is_none());
+        let err = plot_trajectory_to(&uvt_file, &path, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Nothing to plot: the trajectory has no finite position"
+        );
+        assert!(!path.exists());
+
+        assert_eq!(value_range(&[f64::NAN, 2.0, -1.0]), Some((-1.0, 2.0)));
+        assert_eq!(value_range(&[f64::NAN]), None);
+        assert_eq!(normalize(&[0.0, f64::NAN, 2.0]), vec![0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_plot_ranges_equal_aspect() {
+        // L-shaped path: 100 m along x, then 20 m along y
+        let corner = uvt::Point::new(100.0, 0.0, 0.0);
+        let positions = (0..=100)
+            .map(|i| uvt::Point::new(i as f64, 0.0, 0.0))
+            .chain((1..=20).map(|i| corner + uvt::Point::new(0.0, i as f64, 0.0)));
+        let mut path = uvt::Uvt::from_points(vec![], vec![]);
+        positions.for_each(|pt| {
+            path.append_pose(
+                uvt::pose::Pose::from_6dof((pt.x, pt.y, pt.z, 0.0, 0.0, 0.0)),
+                uvt::pose::Time { sec: 0, nanosec: 0 },
+            )
+        });
+        let (min, max) = path.trajectory_bounds().unwrap();
+
+        let span = |range: &Range<f64>| range.end - range.start;
+        let center = |range: &Range<f64>| (range.start + range.end) / 2.0;
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        let square = PlotOptions {
+            width: 600 + COLORBAR_AREA_SIZE,
+            height: 600,
+            ..Default::default()
+        };
+        let (x_range, y_range) = plot_ranges(min, max, &square);
+        assert!(close(span(&x_range), 120.0) && close(center(&x_range), 50.0));
+        assert!(close(span(&y_range), span(&x_range)));
+        // The shorter dimension is centered
+        assert!(close(center(&y_range), 10.0));
+
+        // Same number of map units per pixel along both axes
+        let (x_range, y_range) = plot_ranges(min, max, &PlotOptions::default());
+        let area = |size: u32| (size - 2 * MARGIN - LABEL_AREA_SIZE) as f64;
+        assert!(close(
+            span(&x_range) / area(800 - COLORBAR_AREA_SIZE),
+            span(&y_range) / area(600)
+        ));
+
+        let stretched = PlotOptions {
+            equal_aspect: false,
+            ..square
+        };
+        assert_eq!(
+            plot_ranges(min, max, &stretched),
+            (-10.0..110.0, -10.0..30.0)
+        );
+    }
+
+    #[test]
+    fn test_point_colors() {
+        let trajectory = straight_line(5).trajectory;
+        let mut climbing = trajectory.clone();
+        climbing
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, pose)| pose.pose.position.z = i as f64);
+
+        let colors: Vec<RGBColor> = normalize(&color_values(&climbing, ColorBy::Elevation))
+            .into_iter()
+            .map(colormap_turbo_srgb)
+            .collect();
+        assert_ne!(colors[0], colors[4]);
+        assert_eq!(colors[0], colormap_turbo_srgb(0.0));
+        assert_eq!(colors[4], colormap_turbo_srgb(1.0));
+
+        // A flat trajectory takes the middle color, while its timestamps differ
+        assert_eq!(
+            normalize(&color_values(&trajectory, ColorBy::Elevation)),
+            vec![0.5; 5]
+        );
+        assert_eq!(
+            normalize(&color_values(&trajectory, ColorBy::Time)),
+            vec![0.0, 0.25, 0.5, 0.75, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_plot_side_view() {
+        let mut hill = straight_line(20);
+        hill.trajectory
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, pose)| pose.pose.position.z = (i as f64 / 5.0).sin() * 3.0);
+        let (min, max) = hill.trajectory_bounds().unwrap();
+
+        let options = PlotOptions {
+            width: 400,
+            height: 300,
+            equal_aspect: false,
+            view: PlotView::SideXZ,
+            ..Default::default()
+        };
+        let (x_range, y_range) = plot_ranges(min, max, &options);
+        assert_eq!(x_range, (min.x - PADDING)..(max.x + PADDING));
+        assert_eq!(y_range, (min.z - PADDING)..(max.z + PADDING));
+
+        let path = std::env::temp_dir().join(format!("uvt-plot-{}-xz.png", std::process::id()));
+        plot_trajectory_to(&hill, &path, &options).unwrap();
+        let size = png_size(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(size, (400, 300));
+    }
+
+    #[test]
+    fn test_heading_arrow() {
+        let pose =
+            uvt::pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, std::f64::consts::FRAC_PI_2));
+        let arrow = heading_arrow(&pose, PlotView::TopXY, 2.0);
+        assert_eq!(arrow.len(), 2);
+        let tip = arrow[0][1];
+        assert!((tip.0 - 1.0).abs() < 1e-9 && (tip.1 - 4.0).abs() < 1e-9);
+
+        // Heading along y, orthogonal to the XZ view
+        assert!(heading_arrow(&pose, PlotView::SideXZ, 2.0).is_empty());
+    }
+
+    #[test]
+    fn test_plot_single_pose() {
+        let path = std::env::temp_dir().join(format!("uvt-plot-{}-single.png", std::process::id()));
+        let options = PlotOptions {
+            width: 320,
+            height: 200,
+            ..Default::default()
+        };
+        plot_trajectory_to(&straight_line(1), &path, &options).unwrap();
+        let size = png_size(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(size, (320, 200));
+    }
+
+    #[test]
+    fn test_plot_bounds_with_map() {
+        let mut uvt_file = straight_line(5);
+        uvt_file.map = uvt::Uvt::from_points(
+            vec![
+                uvt::Point::new(-50.0, -20.0, -1.0),
+                uvt::Point::new(60.0, 30.0, 2.0),
+            ],
+            vec![],
+        )
+        .map;
+
+        let options = PlotOptions {
+            equal_aspect: false,
+            ..Default::default()
+        };
+        let (min, max) = plot_bounds(&uvt_file, &options).unwrap();
+        assert_eq!(plot_ranges(min, max, &options), (-10.0..14.0, -10.0..12.0));
+
+        let overlay = PlotOptions {
+            show_map: true,
+            ..options
+        };
+        let (min, max) = plot_bounds(&uvt_file, &overlay).unwrap();
+        assert_eq!(plot_ranges(min, max, &overlay), (-60.0..70.0, -30.0..40.0));
+    }
 
-    println!("Saved trajectory plot in {}", figpath.as_os_str().display());
+    #[test]
+    fn test_subsample() {
+        let points: Vec<uvt::Point> = (0..10)
+            .map(|i| uvt::Point::new(i as f64, 0.0, 0.0))
+            .collect();
+        assert_eq!(subsample(points.clone(), 20).len(), 10);
+        let kept = subsample(points, 4);
+        assert_eq!(kept.len(), 4);
+        assert_eq!(kept[1], uvt::Point::new(3.0, 0.0, 0.0));
+    }
 }
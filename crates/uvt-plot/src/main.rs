@@ -1,17 +1,35 @@
 use clap::Parser;
 use std::path::PathBuf;
 
-use uvt_plot;
-
 #[derive(clap::ValueEnum, Parser, Clone, Default, Debug)]
 enum Mode {
     // UVT file
     #[default]
-    UVT,
+    Uvt,
     // Rosbag file
     Rosbag,
     // MCAP file
-    MCAP,
+    Mcap,
+}
+
+#[derive(clap::ValueEnum, Parser, Clone, Default, Debug)]
+enum ColorBy {
+    // Elevation of the poses
+    #[default]
+    Elevation,
+    // Timestamp of the poses
+    Time,
+}
+
+#[derive(clap::ValueEnum, Parser, Clone, Default, Debug)]
+enum View {
+    // Top-down view, along x and y
+    #[default]
+    Xy,
+    // Side view, along x and z
+    Xz,
+    // Side view, along y and z
+    Yz,
 }
 
 #[derive(Parser, Debug)]
@@ -32,6 +50,38 @@ struct Args {
     /// Trajectory topic
     #[clap(long, default_value = "/odom")]
     traj_topic: String,
+
+    /// Output PNG image path
+    #[clap(short, long, default_value = "traj.png")]
+    output: PathBuf,
+
+    /// Image width, in pixels
+    #[clap(long, default_value_t = 800)]
+    width: u32,
+
+    /// Image height, in pixels
+    #[clap(long, default_value_t = 600)]
+    height: u32,
+
+    /// Quantity coloring the trajectory
+    #[clap(long, default_value_t, value_enum)]
+    color_by: ColorBy,
+
+    /// Coordinates forming the axes of the plot
+    #[clap(long, default_value_t, value_enum)]
+    view: View,
+
+    /// Hide the start and end markers of the trajectory
+    #[clap(long)]
+    hide_markers: bool,
+
+    /// Draw the map points under the trajectory
+    #[clap(long)]
+    show_map: bool,
+
+    /// List the topics of the rosbag with their message types, then exit
+    #[clap(long)]
+    list_topics: bool,
 }
 
 fn main() {
@@ -42,12 +92,76 @@ fn main() {
         std::process::exit(1);
     }
 
-    let uv_traj = match args.mode {
-        Mode::UVT => uvt::Uvt::read_file(args.input_file),
-        Mode::Rosbag => uvt::Uvt::read_rosbag(args.input_file, &args.map_topic, &args.traj_topic),
-        Mode::MCAP => uvt::Uvt::read_mcap(args.input_file, &args.map_topic, &args.traj_topic),
+    if args.list_topics {
+        let topics = match args.mode {
+            Mode::Uvt => {
+                eprintln!("--list-topics requires the rosbag or mcap mode");
+                std::process::exit(1);
+            }
+            Mode::Rosbag => uvt::Uvt::list_rosbag_topics(&args.input_file),
+            Mode::Mcap => uvt::Uvt::list_mcap_topics(&args.input_file),
+        };
+        match topics {
+            Ok(topics) => {
+                for (topic, msg_type) in topics {
+                    println!("{} ({})", topic, msg_type);
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "Could not list the topics of {}: {}",
+                    args.input_file.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+        return;
     }
-    .unwrap();
 
-    uvt_plot::plot_trajectory(uv_traj);
+    let file_kind = match args.mode {
+        Mode::Uvt => "UVT",
+        Mode::Rosbag => "rosbag",
+        Mode::Mcap => "MCAP",
+    };
+    if let Ok(absolute_path) = std::path::absolute(&args.input_file) {
+        println!("Reading {} file in {}", file_kind, absolute_path.display());
+    }
+
+    let uv_traj = match args.mode {
+        Mode::Uvt => uvt::Uvt::read_file(&args.input_file),
+        Mode::Rosbag => uvt::Uvt::read_rosbag(&args.input_file, &args.map_topic, &args.traj_topic),
+        Mode::Mcap => uvt::Uvt::read_mcap(&args.input_file, &args.map_topic, &args.traj_topic),
+    };
+    let uv_traj = match uv_traj {
+        Ok(uv_traj) => uv_traj,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", args.input_file.display(), e);
+            if !matches!(args.mode, Mode::Uvt) {
+                eprintln!("Use --list-topics to list the topics of the rosbag");
+            }
+            std::process::exit(1);
+        }
+    };
+
+    let options = uvt_plot::PlotOptions {
+        width: args.width,
+        height: args.height,
+        color_by: match args.color_by {
+            ColorBy::Elevation => uvt_plot::ColorBy::Elevation,
+            ColorBy::Time => uvt_plot::ColorBy::Time,
+        },
+        view: match args.view {
+            View::Xy => uvt_plot::PlotView::TopXY,
+            View::Xz => uvt_plot::PlotView::SideXZ,
+            View::Yz => uvt_plot::PlotView::SideYZ,
+        },
+        show_markers: !args.hide_markers,
+        show_map: args.show_map,
+        ..Default::default()
+    };
+    if let Err(e) = uvt_plot::plot_trajectory_to(&uv_traj, &args.output, &options) {
+        eprintln!("Could not plot {}: {}", args.output.display(), e);
+        std::process::exit(1);
+    }
 }
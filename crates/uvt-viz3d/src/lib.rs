@@ -14,7 +14,7 @@
 //!
 //! ## Example
 //!
-//! ```rust
+//! ```no_run
 //! use uvt;
 //! use uvt_viz3d;
 //!
@@ -22,137 +22,246 @@
 //! let my_uvt = uvt::Uvt::read_file("example.uvt").unwrap();
 //!
 //! // Visualize UVT with rerun
-//! uvt_viz3d::show_uvt(my_uvt);
+//! uvt_viz3d::show_uvt(&my_uvt).unwrap();
 //! ```
 use rerun::external::glam;
-use vtkio::model::{DataSet, Piece};
 
-use uvt;
-use vtkio::IOBuffer;
+/// Errors raised while visualizing a UVT.
+#[derive(Debug)]
+pub enum VizError {
+    /// The map points could not be read from the UVT: they are not inline PolyData
+    /// points, or they lack the color field.
+    Uvt(uvt::UvtError),
+    /// The rerun viewer could not be spawned, or the data could not be logged.
+    Rerun(String),
+}
+
+impl std::fmt::Display for VizError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VizError::Uvt(e) => write!(f, "Unsupported map: {}", e),
+            VizError::Rerun(msg) => write!(f, "Rerun error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for VizError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VizError::Uvt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<uvt::UvtError> for VizError {
+    fn from(e: uvt::UvtError) -> Self {
+        VizError::Uvt(e)
+    }
+}
+
+/// Length of the coordinate axes drawn at each pose, in map units
+const AXES_LENGTH: f64 = 0.5;
+
+/// Options of the 3D view.
+#[derive(Debug, Clone)]
+pub struct VizOptions {
+    /// Scalar field coloring the map: a coordinate (`x`, `y`, `z`) or a point attribute
+    /// of the map, such as `intensity`. Defaults to `z`.
+    pub color_field: String,
+    /// Draws the x, y and z axes (in red, green and blue) of each pose of the trajectory.
+    /// Disabled by default, since it clutters dense trajectories.
+    pub show_axes: bool,
+    /// Colors the map with the colors of its points, such as the unpacked `rgb` field of a
+    /// colored point cloud, instead of `color_field`. Enabled by default; maps without
+    /// colors always use `color_field`.
+    pub use_map_colors: bool,
+}
+
+impl Default for VizOptions {
+    fn default() -> Self {
+        Self {
+            color_field: String::from("z"),
+            show_axes: false,
+            use_map_colors: true,
+        }
+    }
+}
+
+/// Builds the line strip connecting the consecutive positions of a trajectory,
+/// skipping the positions with non-finite coordinates.
+fn trajectory_strip(trajectory: &[uvt::pose::PoseStamped]) -> Vec<[f32; 3]> {
+    trajectory
+        .iter()
+        .filter(|pose| pose.pose.position.is_finite())
+        .map(|pose| pose.pose.position.into())
+        .collect()
+}
+
+/// Builds the segments of the x, y and z axes of each pose, along with their colors.
+/// Poses with non-finite values have no axes.
+fn pose_axes(
+    trajectory: &[uvt::pose::PoseStamped],
+    length: f64,
+) -> (Vec<[[f32; 3]; 2]>, Vec<[u8; 4]>) {
+    const AXES_COLORS: [[u8; 4]; 3] = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+
+    trajectory
+        .iter()
+        .filter(|pose| pose.pose.position.is_finite() && pose.pose.orientation.norm().is_finite())
+        .flat_map(|pose| {
+            let frame: glam::Affine3A = pose.pose.into();
+            (0..3).map(move |i| {
+                let tip = frame.transform_point3(glam::Vec3::AXES[i] * length as f32);
+                let segment: [[f32; 3]; 2] = [frame.translation.into(), tip.into()];
+                (segment, AXES_COLORS[i])
+            })
+        })
+        .unzip()
+}
+
+/// Maps the values of a scalar field on the Turbo colormap, normalized over their min/max.
+///
+/// A constant field, as well as non-finite values, take the middle color.
+fn field_colors(values: &[f64]) -> Vec<[u8; 4]> {
+    let (min, max) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+    values
+        .iter()
+        .map(|&v| match max - min {
+            range if range > 0.0 && v.is_finite() => ((v - min) / range) as f32,
+            _ => 0.5,
+        })
+        .map(colormap_turbo_srgb)
+        .collect()
+}
+
+/// Gives the map points with their colors: the map colors if it has some and
+/// `options.use_map_colors` is set, or else `options.color_field` on the Turbo colormap.
+fn colored_points(
+    uvt_file: &uvt::Uvt,
+    options: &VizOptions,
+) -> Result<(Vec<uvt::Point>, Vec<[u8; 4]>), VizError> {
+    if options.use_map_colors
+        && let Some(colors) = uvt_file.map_colors()
+    {
+        return Ok((uvt_file.map_points()?, colors));
+    }
+    let (points, values): (Vec<uvt::Point>, Vec<f64>) = uvt_file
+        .map_points_with(&options.color_field)?
+        .into_iter()
+        .unzip();
+    Ok((points, field_colors(&values)))
+}
 
 /// Visualizes the content of a UVT file in 3D using rerun.
 ///
-/// This function displays the LiDAR map and trajectory data from the UVT file in a 3D viewer.
+/// This function displays the LiDAR map and the trajectory path from the UVT file in a 3D viewer,
+/// with the map colored by its point colors if it has some, or else by elevation. See [`show_uvt_with_options`] to color it by another field.
 ///
 /// # Arguments
 ///
 /// * `uvt_file` - A `uvt::Uvt` object containing the map and trajectory data.
 ///
+/// # Errors
+///
+/// Returns a [`VizError`] if the map is not made of inline PolyData points,
+/// or if the rerun viewer cannot be used.
+///
 /// # Example
 ///
-/// ```rust
+/// ```no_run
 /// use uvt;
 /// use uvt_viz3d;
 ///
 /// let my_uvt = uvt::Uvt::read_file("example.uvt").unwrap();
-/// uvt_viz3d::show_uvt(my_uvt);
+/// uvt_viz3d::show_uvt(&my_uvt).unwrap();
 /// ```
-pub fn show_uvt(uvt_file: uvt::Uvt) {
-    let map = uvt_file.map;
-    let point_cloud = map.data.clone();
-
-    let pieces = match point_cloud {
-        DataSet::PolyData { pieces, .. } => pieces,
-        _ => {
-            panic!("Wrong vtk data type");
-        }
-    };
-
-    let points: Vec<uvt::Point> = pieces
-        .iter()
-        .map(|inline_piece| match inline_piece {
-            Piece::Inline(piece) => {
-                let piece_buf_points = piece.points.clone();
-                let buf_values = match piece_buf_points {
-                    IOBuffer::F64(buf_points) => buf_points,
-                    IOBuffer::F32(buf_points) => buf_points.iter().map(|&v| v as f64).collect(),
-                    _ => panic!("Unimplemented IO Buffer"),
-                };
-
-                let n_points = piece.num_points();
-                let piece_points: Vec<uvt::Point> = (0..n_points)
-                    .into_iter()
-                    .map(|i| (3 * i + 0, 3 * i + 1, 3 * i + 2))
-                    .map(|(idx_x, idx_y, idx_z)| {
-                        uvt::Point::new(buf_values[idx_x], buf_values[idx_y], buf_values[idx_z])
-                    })
-                    .collect();
-                piece_points
-            }
-            _ => panic!("Unknown piece type"),
-        })
-        .flatten()
-        .collect();
-
-    // Limits of Z
-    let zs: Vec<f64> = points.iter().map(|&pt| pt.z).collect();
-    let z_min = zs
-        .iter()
-        .min_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-    let z_max = zs
-        .iter()
-        .max_by(|&a, &b| a.partial_cmp(b).unwrap())
-        .unwrap()
-        .clone();
-
-    // Colors
-    let colors: Vec<[u8; 4]> = points
-        .iter()
-        .map(|pt| colormap_turbo_srgb(((pt.z - z_min) / (z_max - z_min)) as f32))
-        .collect();
+pub fn show_uvt(uvt_file: &uvt::Uvt) -> Result<(), VizError> {
+    show_uvt_with_options(uvt_file, &VizOptions::default())
+}
 
+/// Visualizes the content of a UVT file in 3D using rerun, with custom options.
+///
+/// Nothing is displayed if the map is empty, in which case a warning is logged with rerun.
+///
+/// # Arguments
+///
+/// * `uvt_file` - A `uvt::Uvt` object containing the map and trajectory data.
+/// * `options` - The options of the view, see [`VizOptions`].
+///
+/// # Errors
+///
+/// Returns a [`VizError`] if the map is not made of inline PolyData points, if it is colored
+/// by a missing `options.color_field` point attribute, or if the rerun viewer cannot be used.
+///
+/// # Example
+///
+/// ```no_run
+/// use uvt;
+/// use uvt_viz3d::{self, VizOptions};
+///
+/// let my_uvt = uvt::Uvt::read_file("example.uvt").unwrap();
+/// let options = VizOptions {
+///     color_field: String::from("intensity"),
+///     show_axes: true,
+///     use_map_colors: false,
+/// };
+/// uvt_viz3d::show_uvt_with_options(&my_uvt, &options).unwrap();
+/// ```
+pub fn show_uvt_with_options(uvt_file: &uvt::Uvt, options: &VizOptions) -> Result<(), VizError> {
     // Init rerun
     rerun::external::re_log::setup_logging();
 
+    let (points, colors) = colored_points(uvt_file, options)?;
+    if points.is_empty() {
+        rerun::external::re_log::warn!("The map is empty, nothing to show");
+        return Ok(());
+    }
+
     let rec = rerun::RecordingStreamBuilder::new("point-cloud-viewer")
         .spawn()
-        .unwrap();
+        .map_err(|e| VizError::Rerun(e.to_string()))?;
     rec.set_duration_secs("stable-time", 0f64);
 
     // Log map
     rec.log_static(
         "/map",
-        &rerun::Points3D::new(
-            points
-                .iter()
-                .map(|&pt| {
-                    let coords: [f32; 3] = pt.into();
-                    let vec_pt: glam::Vec3 = coords.into();
-                    vec_pt
-                })
-                .into_iter(),
-        )
+        &rerun::Points3D::new(points.iter().map(|&pt| {
+            let coords: [f32; 3] = pt.into();
+            let vec_pt: glam::Vec3 = coords.into();
+            vec_pt
+        }))
         .with_colors(colors)
         .with_radii([0.08]),
     )
-    .unwrap();
+    .map_err(|e| VizError::Rerun(e.to_string()))?;
 
     // Log trajectory
-    let n_points = uvt_file.trajectory.len();
-    let red: Vec<[u8; 4]> = (0..n_points)
-        .into_iter()
-        .map(|_| [255, 255, 255, 255])
-        .collect();
-
     rec.log_static(
         "/trajectory",
-        &rerun::Points3D::new(
-            uvt_file
-                .trajectory
-                .iter()
-                .map(|pt| {
-                    let coords: [f32; 3] = pt.pose.position.into();
-                    let vec_pt: glam::Vec3 = coords.into();
-                    vec_pt
-                })
-                .into_iter(),
-        )
-        .with_colors(red)
-        .with_radii([0.25]),
+        &rerun::LineStrips3D::new([trajectory_strip(&uvt_file.trajectory)])
+            .with_colors([[255, 255, 255, 255]])
+            .with_radii([0.1]),
     )
-    .unwrap();
+    .map_err(|e| VizError::Rerun(e.to_string()))?;
+
+    if options.show_axes {
+        let (segments, colors) = pose_axes(&uvt_file.trajectory, AXES_LENGTH);
+        rec.log_static(
+            "/trajectory/axes",
+            &rerun::LineStrips3D::new(segments)
+                .with_colors(colors)
+                .with_radii([0.02]),
+        )
+        .map_err(|e| VizError::Rerun(e.to_string()))?;
+    }
+
+    Ok(())
 }
 
 // Returns sRGB polynomial approximation from Turbo color map, assuming `t` is normalized. Copied from rerun DNA demo.
@@ -180,3 +289,143 @@ fn colormap_turbo_srgb(t: f32) -> [u8; 4] {
         255,
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vtkio::model::{DataSet, Piece};
+
+    #[test]
+    fn test_show_uvt_unsupported_map() {
+        let map = vtkio::Vtk {
+            version: vtkio::model::Version { major: 3, minor: 0 },
+            byte_order: vtkio::model::ByteOrder::BigEndian,
+            title: String::from("Test map"),
+            file_path: None,
+            data: DataSet::inline(vtkio::model::ImageDataPiece {
+                extent: vtkio::model::Extent::Dims([1, 1, 1]),
+                data: vtkio::model::Attributes::new(),
+            }),
+        };
+        let uvt_file = uvt::Uvt::new(map, vec![]);
+
+        let err = show_uvt(&uvt_file).err().unwrap();
+        assert!(matches!(err, VizError::Uvt(_)));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn test_field_colors() {
+        let points = vec![
+            uvt::Point::new(0.0, 0.0, 1.0),
+            uvt::Point::new(1.0, 0.0, 1.0),
+            uvt::Point::new(2.0, 0.0, 1.0),
+        ];
+        let mut uvt_file = uvt::Uvt::from_points(points, vec![]);
+        if let DataSet::PolyData { pieces, .. } = &mut uvt_file.map.data
+            && let Piece::Inline(piece) = &mut pieces[0]
+        {
+            piece.data.point.push(
+                vtkio::model::Attribute::scalars("intensity", 1)
+                    .with_data(vec![5.0_f32, 50.0, 100.0]),
+            );
+        }
+
+        let values = |field: &str| -> Vec<f64> {
+            let points_with = uvt_file.map_points_with(field).unwrap();
+            points_with.into_iter().map(|(_, v)| v).collect()
+        };
+        let colors = field_colors(&values("intensity"));
+        assert_eq!(colors.len(), 3);
+        assert_ne!(colors[0], colors[1]);
+        assert_ne!(colors[1], colors[2]);
+        assert_eq!(colors[0], colormap_turbo_srgb(0.0));
+        assert_eq!(colors[2], colormap_turbo_srgb(1.0));
+
+        // A flat map takes the middle color
+        let colors = field_colors(&values("z"));
+        assert!(colors.iter().all(|&c| c == colormap_turbo_srgb(0.5)));
+
+        // An empty map returns early, without spawning the viewer
+        let empty = uvt::Uvt::from_points(vec![], vec![]);
+        assert!(show_uvt(&empty).is_ok());
+    }
+
+    #[test]
+    fn test_colored_points() {
+        let points = vec![
+            uvt::Point::new(0.0, 0.0, 0.0),
+            uvt::Point::new(1.0, 0.0, 1.0),
+        ];
+        let mut uvt_file = uvt::Uvt::from_points(points.clone(), vec![]);
+        let options = VizOptions::default();
+
+        // Without map colors, the color field is used
+        let (_, colors) = colored_points(&uvt_file, &options).unwrap();
+        assert_eq!(
+            colors,
+            vec![colormap_turbo_srgb(0.0), colormap_turbo_srgb(1.0)]
+        );
+
+        if let DataSet::PolyData { pieces, .. } = &mut uvt_file.map.data
+            && let Piece::Inline(piece) = &mut pieces[0]
+        {
+            piece.data.point.push(
+                vtkio::model::Attribute::color_scalars("rgba", 4)
+                    .with_data(vec![255_u8, 0, 0, 255, 0, 0, 255, 128]),
+            );
+        }
+        let (colored, colors) = colored_points(&uvt_file, &options).unwrap();
+        assert_eq!(colored, points);
+        assert_eq!(colors, vec![[255, 0, 0, 255], [0, 0, 255, 128]]);
+
+        let options = VizOptions {
+            use_map_colors: false,
+            ..Default::default()
+        };
+        let (_, colors) = colored_points(&uvt_file, &options).unwrap();
+        assert_eq!(colors[0], colormap_turbo_srgb(0.0));
+    }
+
+    #[test]
+    fn test_trajectory_lines() {
+        let trajectory: Vec<uvt::pose::PoseStamped> = (0..4)
+            .map(|i| {
+                uvt::pose::PoseStamped::new(
+                    uvt::pose::Header {
+                        seq: i,
+                        stamp: uvt::pose::Time {
+                            sec: i as i32,
+                            nanosec: 0,
+                        },
+                        frame_id: String::from("map"),
+                    },
+                    uvt::pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect();
+
+        // One strip through the 4 positions, i.e. 3 segments
+        let strip = trajectory_strip(&trajectory);
+        assert_eq!(strip.len(), 4);
+        assert_eq!(strip.windows(2).count(), 3);
+        assert_eq!(strip[3], [3.0, 0.0, 0.0]);
+
+        // Three axes per pose
+        let (segments, colors) = pose_axes(&trajectory, 0.5);
+        assert_eq!(segments.len(), 4 * 3);
+        assert_eq!(colors.len(), 4 * 3);
+        assert_eq!(segments[3], [[1.0, 0.0, 0.0], [1.5, 0.0, 0.0]]);
+        assert_eq!(segments[4], [[1.0, 0.0, 0.0], [1.0, 0.5, 0.0]]);
+        assert_eq!(colors[5], [0, 0, 255, 255]);
+
+        // A NaN pose is skipped
+        let mut with_nan = trajectory.clone();
+        with_nan[2].pose.position.z = f64::NAN;
+        assert_eq!(trajectory_strip(&with_nan).len(), 3);
+        assert_eq!(pose_axes(&with_nan, 0.5).0.len(), 3 * 3);
+        let colors = field_colors(&[0.0, f64::NAN, 1.0]);
+        assert_eq!(colors[1], colormap_turbo_srgb(0.5));
+        assert_eq!(colors[2], colormap_turbo_srgb(1.0));
+    }
+}
@@ -1,17 +1,15 @@
 use clap::Parser;
 use std::path::PathBuf;
 
-use uvt_viz3d;
-
 #[derive(clap::ValueEnum, Parser, Clone, Default, Debug)]
 enum Mode {
     // UVT file
     #[default]
-    UVT,
+    Uvt,
     // Rosbag file
     Rosbag,
     // MCAP file
-    MCAP,
+    Mcap,
 }
 
 #[derive(Parser, Debug)]
@@ -32,6 +30,22 @@ struct Args {
     /// Trajectory topic
     #[clap(long, default_value = "/odom")]
     traj_topic: String,
+
+    /// Map field used to color the points (x, y, z or a point data such as intensity)
+    #[clap(long, default_value = "z")]
+    color_field: String,
+
+    /// Color the map with --color-field even if its points have colors
+    #[clap(long)]
+    ignore_map_colors: bool,
+
+    /// Draw the coordinate axes of each pose of the trajectory
+    #[clap(long)]
+    show_axes: bool,
+
+    /// List the topics of the rosbag with their message types, then exit
+    #[clap(long)]
+    list_topics: bool,
 }
 
 fn main() {
@@ -42,12 +56,65 @@ fn main() {
         std::process::exit(1);
     }
 
-    let uv_traj = match args.mode {
-        Mode::UVT => uvt::Uvt::read_file(args.input_file),
-        Mode::Rosbag => uvt::Uvt::read_rosbag(args.input_file, &args.map_topic, &args.traj_topic),
-        Mode::MCAP => uvt::Uvt::read_mcap(args.input_file, &args.map_topic, &args.traj_topic),
+    if args.list_topics {
+        let topics = match args.mode {
+            Mode::Uvt => {
+                eprintln!("--list-topics requires the rosbag or mcap mode");
+                std::process::exit(1);
+            }
+            Mode::Rosbag => uvt::Uvt::list_rosbag_topics(&args.input_file),
+            Mode::Mcap => uvt::Uvt::list_mcap_topics(&args.input_file),
+        };
+        match topics {
+            Ok(topics) => {
+                for (topic, msg_type) in topics {
+                    println!("{} ({})", topic, msg_type);
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "Could not list the topics of {}: {}",
+                    args.input_file.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+        return;
     }
-    .unwrap();
 
-    uvt_viz3d::show_uvt(uv_traj);
+    let file_kind = match args.mode {
+        Mode::Uvt => "UVT",
+        Mode::Rosbag => "rosbag",
+        Mode::Mcap => "MCAP",
+    };
+    if let Ok(absolute_path) = std::path::absolute(&args.input_file) {
+        println!("Reading {} file in {}", file_kind, absolute_path.display());
+    }
+
+    let uv_traj = match args.mode {
+        Mode::Uvt => uvt::Uvt::read_file(&args.input_file),
+        Mode::Rosbag => uvt::Uvt::read_rosbag(&args.input_file, &args.map_topic, &args.traj_topic),
+        Mode::Mcap => uvt::Uvt::read_mcap(&args.input_file, &args.map_topic, &args.traj_topic),
+    };
+    let uv_traj = match uv_traj {
+        Ok(uv_traj) => uv_traj,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", args.input_file.display(), e);
+            if !matches!(args.mode, Mode::Uvt) {
+                eprintln!("Use --list-topics to list the topics of the rosbag");
+            }
+            std::process::exit(1);
+        }
+    };
+
+    let options = uvt_viz3d::VizOptions {
+        color_field: args.color_field,
+        show_axes: args.show_axes,
+        use_map_colors: !args.ignore_map_colors,
+    };
+    if let Err(e) = uvt_viz3d::show_uvt_with_options(&uv_traj, &options) {
+        eprintln!("Could not show the UVT: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,157 @@
+//! Benchmarks of the decoding paths of the crate, over synthetic data.
+//!
+//! Run with `cargo bench -p uvt --features test-support`.
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use uvt::Uvt;
+use uvt::fixtures::{self, MessageWriter};
+use uvt::pose::{Header, Pose, Time};
+use uvt::{DataType, PointCloud2, PointField};
+
+const CLOUD_SIZES: [usize; 2] = [10_000, 100_000];
+
+/// Builds a cloud of `n` points with packed FLOAT32 `x`, `y`, `z` and `intensity` fields.
+fn synthetic_cloud(n: usize) -> PointCloud2 {
+    let fields = ["x", "y", "z", "intensity"]
+        .iter()
+        .enumerate()
+        .map(|(i, name)| PointField {
+            name: name.to_string(),
+            offset: 4 * i as u32,
+            datatype: DataType::FLOAT32,
+            count: 1,
+        })
+        .collect();
+    let data = (0..n)
+        .flat_map(|i| {
+            let t = i as f32 * 0.01;
+            [t.cos() * t, t.sin() * t, 0.1 * t, (i % 256) as f32]
+        })
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+
+    PointCloud2 {
+        header: Header {
+            seq: 0,
+            stamp: Time { sec: 0, nanosec: 0 },
+            frame_id: String::from("map"),
+        },
+        height: 1,
+        width: n as u32,
+        fields,
+        is_bigendian: false,
+        point_step: 16,
+        row_step: 16 * n as u32,
+        data,
+        is_dense: true,
+    }
+}
+
+fn odometry_msg(x: f64) -> Vec<u8> {
+    fixtures::odometry(
+        MessageWriter::cdr(),
+        0,
+        Time { sec: 0, nanosec: 0 },
+        "odom",
+        "base_link",
+        &Pose::from_6dof((x, 0.0, 0.0, 0.0, 0.0, 0.0)),
+    )
+}
+
+/// Writes an MCAP recording with one `/map` cloud of `n_points` and a 1000-pose `/odom` trajectory.
+fn synthetic_mcap(n_points: usize) -> PathBuf {
+    let path = fixtures::temp_path(&format!("bench-{}.mcap", n_points));
+    fixtures::write_mcap(
+        &path,
+        &[
+            (
+                "/map",
+                "sensor_msgs/msg/PointCloud2",
+                vec![synthetic_cloud(n_points).to_cdr_bytes()],
+            ),
+            (
+                "/odom",
+                "nav_msgs/msg/Odometry",
+                (0..1000).map(|i| odometry_msg(i as f64)).collect(),
+            ),
+        ],
+    );
+    path
+}
+
+fn bench_points(c: &mut Criterion) {
+    let mut group = c.benchmark_group("PointCloud2::points");
+    for n in CLOUD_SIZES {
+        let cloud = synthetic_cloud(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &cloud, |b, cloud| {
+            b.iter(|| black_box(cloud).points().unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_read_mcap(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Uvt::read_mcap");
+    group.sample_size(10);
+    for n in CLOUD_SIZES {
+        let path = synthetic_mcap(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &path, |b, path| {
+            b.iter(|| Uvt::read_mcap(path, "/map", "/odom").unwrap())
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+    group.finish();
+}
+
+fn bench_read_file(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Uvt::read_file");
+    group.sample_size(10);
+    for n in CLOUD_SIZES {
+        let mcap_path = synthetic_mcap(n);
+        let uvt_path = mcap_path.with_extension("uvt");
+        Uvt::read_mcap(&mcap_path, "/map", "/odom")
+            .unwrap()
+            .write_file(&uvt_path)
+            .unwrap();
+        std::fs::remove_file(mcap_path).unwrap();
+
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &uvt_path, |b, path| {
+            b.iter(|| Uvt::read_file(path).unwrap())
+        });
+        std::fs::remove_file(uvt_path).unwrap();
+    }
+    group.finish();
+}
+
+fn bench_shared_clone(c: &mut Criterion) {
+    let path = synthetic_mcap(100_000);
+    let uvt = Uvt::read_mcap(&path, "/map", "/odom").unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let mut group = c.benchmark_group("clone");
+    group.bench_function("deep copy", |b| {
+        b.iter(|| {
+            (
+                black_box(&uvt.map).clone(),
+                black_box(&uvt.trajectory).clone(),
+            )
+        })
+    });
+    let shared = uvt.shared();
+    group.bench_function("SharedUvt", |b| b.iter(|| black_box(&shared).clone()));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_points,
+    bench_read_mcap,
+    bench_read_file,
+    bench_shared_clone
+);
+criterion_main!(benches);
@@ -1,53 +1,117 @@
 use std::io::{Error, ErrorKind};
 
-use crate::deserialization::{BufferReader, MessageDataBuffer};
-use crate::pointcloud::{PointCloud2Deserializer, PointField};
+use crate::deserialization::{BufferReader, Endian, MessageDataBuffer};
+use crate::pointcloud::{LegacyPointCloudDeserializer, PointCloud2Deserializer, PointField};
 use crate::pose;
+use crate::tf::{StampedTransform, TransformDeserializer};
 use crate::trajectory::TrajectoryDeserializer;
 
+/// Deserializer of the messages recorded in ROS bags.
+///
+/// Bag messages are serialized in the ROS 1 layout, without alignment. Messages of ROS 2
+/// pipelines stored in bags keep their CDR layout, whose header is read with
+/// [`BagDeserializer::read_header_cdr`].
+///
+/// # Example
+/// ```
+/// use uvt::BagDeserializer;
+///
+/// // Encapsulation header, stamp, then the "map" frame id with its null terminator
+/// let mut data = vec![0x00, 0x01, 0x00, 0x00];
+/// data.extend(12_i32.to_le_bytes());
+/// data.extend(34_u32.to_le_bytes());
+/// data.extend(4_u32.to_le_bytes());
+/// data.extend(b"map\0");
+///
+/// let header = BagDeserializer::new(data).read_header_cdr().unwrap();
+/// assert_eq!(header.frame_id, "map");
+/// ```
 pub struct BagDeserializer {
     buf: MessageDataBuffer,
 }
 
 impl BagDeserializer {
+    /// Creates a deserializer for a message recorded in a ROS bag.
     pub fn new(data: Vec<u8>) -> Self {
         Self {
             buf: MessageDataBuffer::new(data),
         }
     }
+
+    /// Reads a ROS 2 std_msgs/Header serialized as little-endian CDR.
+    ///
+    /// Unlike the ROS 1 layout of bag messages, the message starts with a 4-byte
+    /// encapsulation header, the header has no `seq` field, the `frame_id` is
+    /// null-terminated and fields are aligned to their size, relative to the end of
+    /// the encapsulation header. The returned header has a `seq` of 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is truncated, or if it is big-endian CDR.
+    pub fn read_header_cdr(&mut self) -> Result<pose::Header, std::io::Error> {
+        let encapsulation = self.buf.slice(4).ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                "Not enough bytes to read the CDR encapsulation header",
+            )
+        })?;
+        if encapsulation[1] != 0x01 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Only little-endian CDR is supported by the bag deserializer",
+            ));
+        }
+
+        let stamp = pose::Time {
+            sec: self.buf.read_i32_le()?,
+            nanosec: self.buf.read_u32_le()?,
+        };
+        let frame_id = self.buf.read_lp_string()?;
+        // Align to the next field
+        self.buf.align_cdr(4);
+
+        Ok(pose::Header {
+            seq: 0,
+            stamp,
+            frame_id: frame_id.trim_end_matches('\0').to_string(),
+        })
+    }
 }
 
 impl BufferReader for BagDeserializer {
-    fn read_u32_le(&mut self) -> Result<u32, std::io::Error> {
-        self.buf.read_u32_le()
+    fn read_u32(&mut self, endian: Endian) -> Result<u32, std::io::Error> {
+        self.buf.read_u32(endian)
     }
 
-    fn read_f64_le(&mut self) -> Result<f64, std::io::Error> {
-        self.buf.read_f64_le()
+    fn read_i32(&mut self, endian: Endian) -> Result<i32, std::io::Error> {
+        self.buf.read_i32(endian)
     }
 
-    fn read_byte(&mut self) -> Result<u8, std::io::Error> {
-        self.buf.read_byte()
+    fn read_f32(&mut self, endian: Endian) -> Result<f32, std::io::Error> {
+        self.buf.read_f32(endian)
     }
 
-    fn slice(&mut self, length: usize) -> Option<&[u8]> {
-        self.buf.slice(length)
+    fn read_f64(&mut self, endian: Endian) -> Result<f64, std::io::Error> {
+        self.buf.read_f64(endian)
     }
 
-    fn read_lp_string(&mut self) -> Result<String, std::io::Error> {
-        self.buf.read_lp_string()
+    fn read_byte(&mut self) -> Result<u8, std::io::Error> {
+        self.buf.read_byte()
     }
 
-    fn read_null_terminated_string(&mut self) -> Result<String, std::io::Error> {
-        self.buf.read_null_terminated_string()
+    fn read_lp_string(&mut self) -> Result<String, std::io::Error> {
+        self.buf.read_lp_string()
     }
 
+    /// Reads a std_msgs/Header in the ROS 1 layout: `seq`, `stamp` and a length-prefixed
+    /// `frame_id`, without padding or null terminator. Use
+    /// [`BagDeserializer::read_header_cdr`] for ROS 2 messages serialized as CDR.
     fn read_header(&mut self) -> Result<pose::Header, std::io::Error> {
         Ok(pose::Header {
-            seq: self.buf.read_u32_le()?.clone(),
+            seq: self.buf.read_u32_le()?,
             stamp: pose::Time {
-                sec: self.buf.read_i32_le()?.clone(),
-                nanosec: self.buf.read_u32_le()?.clone(),
+                sec: self.buf.read_i32_le()?,
+                nanosec: self.buf.read_u32_le()?,
             },
             frame_id: self.read_lp_string()?,
         })
@@ -59,19 +123,13 @@ impl PointCloud2Deserializer for BagDeserializer {
         Ok(PointField {
             name: self.read_lp_string()?,
             offset: self.buf.read_u32_le()?,
-            datatype: self.buf.read_byte()?.into(),
+            datatype: self.buf.read_byte()?.try_into()?,
             count: self.buf.read_u32_le()?,
         })
     }
 
     fn read_point_fields(&mut self) -> Result<Vec<PointField>, std::io::Error> {
-        let n_fields = self.buf.read_u32_le()?;
-        let fields = (0..n_fields)
-            .into_iter()
-            .map(|_| self.read_point_field())
-            .into_iter()
-            .collect();
-        fields
+        self.read_lp_array(|d| d.read_point_field())
     }
 
     fn read_data(&mut self) -> Result<Vec<u8>, std::io::Error> {
@@ -92,6 +150,26 @@ impl PointCloud2Deserializer for BagDeserializer {
     }
 }
 
+impl LegacyPointCloudDeserializer for BagDeserializer {
+    fn read_points32(&mut self) -> Result<Vec<pose::Point>, std::io::Error> {
+        self.read_lp_array(|d| {
+            Ok(pose::Point {
+                x: d.buf.read_f32_le()? as f64,
+                y: d.buf.read_f32_le()? as f64,
+                z: d.buf.read_f32_le()? as f64,
+            })
+        })
+    }
+
+    fn read_channels(&mut self) -> Result<Vec<(String, Vec<f32>)>, std::io::Error> {
+        self.read_lp_array(|d| {
+            let name = d.read_lp_string()?;
+            let values = d.read_lp_array(|d| d.buf.read_f32_le())?;
+            Ok((name, values))
+        })
+    }
+}
+
 impl TrajectoryDeserializer for BagDeserializer {
     fn read_position(&mut self) -> Result<pose::Point, std::io::Error> {
         Ok(pose::Point {
@@ -113,11 +191,7 @@ impl TrajectoryDeserializer for BagDeserializer {
     /// 6 x 6 covariance matrix = 36 covariance values
     /// https://docs.ros.org/en/noetic/api/geometry_msgs/html/msg/PoseWithCovarianceStamped.html
     fn read_covariance(&mut self) -> Result<Vec<f64>, std::io::Error> {
-        (0..36)
-            .into_iter()
-            .map(|_| self.buf.read_f64_le())
-            .into_iter()
-            .collect()
+        self.read_array(36, |d| d.buf.read_f64_le())
     }
     fn read_vector(&mut self) -> Result<pose::Vector3, std::io::Error> {
         Ok(pose::Vector3::new(
@@ -126,4 +200,91 @@ impl TrajectoryDeserializer for BagDeserializer {
             self.buf.read_f64_le()?,
         ))
     }
+
+    fn read_pose(&mut self) -> Result<pose::Pose, std::io::Error> {
+        Ok(pose::Pose {
+            position: self.read_position()?,
+            orientation: self.read_orientation()?,
+        })
+    }
+
+    fn read_poses(&mut self) -> Result<Vec<pose::Pose>, std::io::Error> {
+        self.read_lp_array(|d| d.read_pose())
+    }
+
+    fn read_stamped_poses(&mut self) -> Result<Vec<pose::PoseStamped>, std::io::Error> {
+        self.read_lp_array(|d| {
+            Ok(pose::PoseStamped {
+                header: d.read_header()?,
+                pose: d.read_pose()?,
+            })
+        })
+    }
+}
+
+impl TransformDeserializer for BagDeserializer {
+    fn read_transform_stamped(&mut self) -> Result<StampedTransform, std::io::Error> {
+        let header = self.read_header()?;
+        let child_frame_id = self.read_lp_string()?;
+        let transform = pose::Pose {
+            position: self.read_position()?,
+            orientation: self.read_orientation()?,
+        };
+        Ok((header.stamp, header.frame_id, child_frame_id, transform))
+    }
+
+    fn read_transforms(&mut self) -> Result<Vec<StampedTransform>, std::io::Error> {
+        self.read_lp_array(|d| d.read_transform_stamped())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::MessageWriter;
+
+    fn stamp() -> pose::Time {
+        pose::Time {
+            sec: 12,
+            nanosec: 34,
+        }
+    }
+
+    #[test]
+    fn test_read_header_ros1() {
+        let msg = MessageWriter::ros1()
+            .header(7, stamp(), "odom")
+            .u32(42)
+            .finish();
+        let mut d = BagDeserializer::new(msg);
+
+        let header = d.read_header().unwrap();
+        assert_eq!(header.seq, 7);
+        assert_eq!(header.stamp, stamp());
+        assert_eq!(header.frame_id, "odom");
+        assert_eq!(d.buf.read_u32_le().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_read_header_cdr() {
+        // "map" and its null terminator end on a 4-byte boundary,
+        // "base_link" needs padding before the next field
+        for frame_id in ["map", "base_link"] {
+            let msg = MessageWriter::cdr()
+                .header(7, stamp(), frame_id)
+                .u32(42)
+                .finish();
+            let mut d = BagDeserializer::new(msg);
+
+            let header = d.read_header_cdr().unwrap();
+            assert_eq!(header.seq, 0);
+            assert_eq!(header.stamp, stamp());
+            assert_eq!(header.frame_id, frame_id);
+            assert_eq!(d.buf.read_u32_le().unwrap(), 42);
+        }
+
+        let msg = MessageWriter::cdr_be().header(7, stamp(), "map").finish();
+        let err = BagDeserializer::new(msg).read_header_cdr().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
 }
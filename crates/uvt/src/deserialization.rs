@@ -1,7 +1,29 @@
 use crate::pose;
-use std::fs::{File, create_dir_all};
-use std::io::{self, Error, ErrorKind, Write};
-use std::path::Path;
+use std::io::{self, Error, ErrorKind};
+
+/// Implements the readers of primitive number types: little-endian, big-endian,
+/// and with a byte order chosen at runtime.
+macro_rules! number_readers {
+    ($($t:ident: $le:ident, $be:ident, $read:ident;)*) => {$(
+        #[doc = concat!("Read a little-endian ", stringify!($t), " from the buffer")]
+        pub fn $le(&mut self) -> Result<$t, io::Error> {
+            self.read_bytes(stringify!($t)).map($t::from_le_bytes)
+        }
+
+        #[doc = concat!("Read a big-endian ", stringify!($t), " from the buffer")]
+        pub fn $be(&mut self) -> Result<$t, io::Error> {
+            self.read_bytes(stringify!($t)).map($t::from_be_bytes)
+        }
+
+        #[doc = concat!("Read a ", stringify!($t), " with the byte order `endian` from the buffer")]
+        pub fn $read(&mut self, endian: Endian) -> Result<$t, io::Error> {
+            match endian {
+                Endian::Little => self.$le(),
+                Endian::Big => self.$be(),
+            }
+        }
+    )*};
+}
 
 #[derive(Debug, Clone, PartialEq)]
 /// A simple buffer reader for deserializing message data.
@@ -18,151 +40,66 @@ impl MessageDataBuffer {
         Self { data, position: 0 }
     }
 
-    /// Get total length of buffer
-    pub fn len(&self) -> usize {
-        self.data.len()
-    }
-
-    /// Get number of remaining bytes in buffer
-    pub fn n_remaining(&self) -> usize {
-        self.len() - self.position
-    }
-
     /// Get current position of cursor
     pub fn offset(&self) -> usize {
         self.position
     }
 
-    /// Dump data to file
-    pub fn dump_to_file(&self, path: &str) -> io::Result<()> {
-        let path = Path::new(&path);
-
-        // Ensure the parent directory exists
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-
-        let mut file = File::create(path)?;
-        let bytes = &self.data;
-
-        for (i, chunk) in bytes.chunks(16).enumerate() {
-            // Hex section
-            write!(file, "{:08x}:", i * 16)?;
-            for b in chunk {
-                write!(file, " {:02x}", b)?;
-            }
-
-            // Pad to align ASCII section
-            for _ in chunk.len()..16 {
-                write!(file, "   ")?; // 3 spaces
-            }
-
-            // ASCII section
-            write!(file, "  |")?;
-            for b in chunk {
-                let c = *b;
-                let printable = if (0x20..=0x7E).contains(&c) {
-                    c as char
-                } else {
-                    '.'
-                };
-                write!(file, "{}", printable)?;
-            }
-            writeln!(file, "|")?;
+    /// Move the cursor to position `position`
+    pub fn set_offset(&mut self, position: usize) -> Result<(), io::Error> {
+        if position > self.data.len() {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "Cannot move the cursor past the end of the buffer",
+            ));
         }
-
+        self.position = position;
         Ok(())
     }
 
-    /// Read the byte at position `position`
-    pub fn seek(&self, position: usize) -> Option<u8> {
-        if position > self.data.len() {
-            None
-        } else {
-            Some(self.data[position])
+    /// Skip padding bytes so that the next read starts at a multiple of `alignment`.
+    /// CDR alignment is relative to the end of the 4-byte encapsulation header.
+    pub fn align_cdr(&mut self, alignment: usize) {
+        let offset = self.position.saturating_sub(4);
+        let padding = (alignment - (offset % alignment)) % alignment;
+        if padding > 0 {
+            let _ = self.slice(padding);
         }
     }
 
+    /// Retrieve the next `n` bytes without moving the cursor
+    pub fn peek(&self, n: usize) -> Option<&[u8]> {
+        self.data.get(self.position..self.position.checked_add(n)?)
+    }
+
     /// Retrieve a slice of length `length` from the buffer
     pub fn slice(&mut self, length: usize) -> Option<&[u8]> {
-        if (self.position + length) > self.data.len() {
-            return None;
-        }
-        let bytes: &[u8] = self.data[self.position..self.position + length]
-            .try_into()
-            .ok()?;
+        let start = self.position;
+        self.peek(length)?;
         self.position += length;
-        Some(bytes)
-    }
-
-    /// Read a u32 from the buffer
-    pub fn read_u32_le(&mut self) -> Result<u32, io::Error> {
-        let bytes = self
-            .slice(4)
-            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Not enough bytes to read u32"))?;
-
-        let bytes_arr: [u8; 4] = bytes
-            .try_into()
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "Failed to convert bytes to u32"))?;
-
-        Ok(u32::from_le_bytes(bytes_arr))
-    }
-    /// Read a u16 from the buffer
-    pub fn read_u16_le(&mut self) -> Result<u16, io::Error> {
-        let bytes = self.slice(2).ok_or_else(|| {
-            Error::new(ErrorKind::UnexpectedEof, "Not enough bytes to read a u16")
-        })?;
-        let bytes_arr: [u8; 2] = bytes
-            .try_into()
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "Failed to convert bytes to u16"))?;
-
-        Ok(u16::from_le_bytes(bytes_arr))
+        Some(&self.data[start..self.position])
     }
-    /// Read a i32 from the buffer
-    pub fn read_i32_le(&mut self) -> Result<i32, io::Error> {
-        let bytes = self.slice(4).ok_or_else(|| {
-            Error::new(ErrorKind::UnexpectedEof, "Not enough bytes to read a i32")
-        })?;
-        let bytes_arr: [u8; 4] = bytes
-            .try_into()
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "Failed to convert bytes to i32"))?;
 
-        Ok(i32::from_le_bytes(bytes_arr))
-    }
-    /// Read a i16 from the buffer
-    pub fn read_i16_le(&mut self) -> Result<i16, io::Error> {
-        let bytes = self.slice(2).ok_or_else(|| {
-            Error::new(ErrorKind::UnexpectedEof, "Not enough bytes to read a i16")
+    /// Read the next `N` bytes from the buffer, `name` being the type of the value read
+    fn read_bytes<const N: usize>(&mut self, name: &str) -> Result<[u8; N], io::Error> {
+        let bytes = self.slice(N).ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("Not enough bytes to read a {}", name),
+            )
         })?;
-        let bytes_arr: [u8; 2] = bytes
-            .try_into()
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "Failed to convert bytes to i16"))?;
-
-        Ok(i16::from_le_bytes(bytes_arr))
+        let mut bytes_arr = [0; N];
+        bytes_arr.copy_from_slice(bytes);
+        Ok(bytes_arr)
     }
-    /// Read a f64 from the buffer
-    pub fn read_f64_le(&mut self) -> Result<f64, io::Error> {
-        let bytes = self.slice(8).ok_or_else(|| {
-            Error::new(ErrorKind::UnexpectedEof, "Not enough bytes to read a f64")
-        })?;
-
-        let bytes_arr: [u8; 8] = bytes
-            .try_into()
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "Failed to convert bytes to f64"))?;
 
-        Ok(f64::from_le_bytes(bytes_arr))
-    }
-    /// Read a f32 from the buffer
-    pub fn read_f32_le(&mut self) -> Result<f32, io::Error> {
-        let bytes = self.slice(4).ok_or_else(|| {
-            Error::new(ErrorKind::UnexpectedEof, "Not enough bytes to read a f32")
-        })?;
-
-        let bytes_arr: [u8; 4] = bytes
-            .try_into()
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "Failed to convert bytes to f32"))?;
-
-        Ok(f32::from_le_bytes(bytes_arr))
+    number_readers! {
+        u16: read_u16_le, read_u16_be, read_u16;
+        i16: read_i16_le, read_i16_be, read_i16;
+        u32: read_u32_le, read_u32_be, read_u32;
+        i32: read_i32_le, read_i32_be, read_i32;
+        f32: read_f32_le, read_f32_be, read_f32;
+        f64: read_f64_le, read_f64_be, read_f64;
     }
 
     /// Read a byte from the buffer
@@ -176,6 +113,11 @@ impl MessageDataBuffer {
     /// Read a length-prefixed UTF-8 string from the buffer (4-byte LE length + bytes)
     pub fn read_lp_string(&mut self) -> Result<String, io::Error> {
         let strlen = self.read_u32_le()? as usize;
+        self.read_string(strlen)
+    }
+
+    /// Read a UTF-8 string of `strlen` bytes from the buffer
+    pub fn read_string(&mut self, strlen: usize) -> Result<String, io::Error> {
         let bytes = self.slice(strlen).ok_or_else(|| {
             Error::new(
                 ErrorKind::UnexpectedEof,
@@ -187,50 +129,248 @@ impl MessageDataBuffer {
 
         Ok(s.to_owned())
     }
+}
 
-    pub fn read_null_terminated_string(&mut self) -> Result<String, io::Error> {
-        let _strlen = self.read_u32_le()? as usize;
-        let mut bytes = Vec::new();
-        while let b = self.read_byte()? {
-            if b == 0 {
-                break;
-            }
-            bytes.push(b);
+/// Helpers to inspect a buffer while writing a deserializer and its tests
+#[cfg(test)]
+impl MessageDataBuffer {
+    /// Get total length of buffer
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Get number of remaining bytes in buffer
+    pub fn n_remaining(&self) -> usize {
+        self.len() - self.position
+    }
+
+    /// Move the cursor `n` bytes back
+    pub fn rewind(&mut self, n: usize) -> Result<(), io::Error> {
+        self.position = self.position.checked_sub(n).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Cannot move the cursor before the start of the buffer",
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Read the byte at position `position`
+    pub fn seek(&self, position: usize) -> Option<u8> {
+        if position >= self.data.len() {
+            None
+        } else {
+            Some(self.data[position])
         }
-        println!("{:?}", bytes);
-        let s = String::from_utf8(bytes).map_err(|_| {
+    }
+
+    /// Retrieve the bytes after the cursor without moving it
+    pub fn remaining(&self) -> &[u8] {
+        &self.data[self.position..]
+    }
+
+    /// Read a UTF-8 string terminated by a null byte, consuming the terminator
+    pub fn read_null_terminated_string(&mut self) -> Result<String, io::Error> {
+        let remaining = &self.data[self.position..];
+        let strlen = remaining.iter().position(|&b| b == 0).ok_or_else(|| {
             Error::new(
-                ErrorKind::InvalidData,
-                "Bytes cannot be converted to string",
+                ErrorKind::UnexpectedEof,
+                "No null terminator found before the end of the buffer",
             )
         })?;
+        let s = self.read_string(strlen)?;
+        self.position += 1;
         Ok(s)
     }
 }
 
-/// Trait for reading from a byte buffer.
+/// Byte order of the values stored in a buffer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// Trait for reading the fields of a serialized message, implemented by the deserializers
+/// of the supported recording formats.
+///
+/// Implementors only provide the readers taking a byte order, the decoders pass
+/// [`BufferReader::endian`] to them to follow the byte order of the message.
 ///# Methods
-///* `read_u32_le` - Reads a little-endian u32 from the buffer.
-///* `read_f64_le` - Reads a little-endian f64 from the buffer.
+///* `endian` - Byte order of the message, little-endian unless the deserializer knows better.
+///* `read_u32` - Reads a u32 from the buffer with the given byte order.
+///* `read_i32` - Reads a i32 from the buffer with the given byte order.
+///* `read_f32` - Reads a f32 from the buffer with the given byte order.
+///* `read_f64` - Reads a f64 from the buffer with the given byte order.
 ///* `read_byte` - Reads a single byte from the buffer.
 ///* `read_byte_aligned` - Reads a single byte from the buffer, aligning to the next specified alignment.
-///* `slice` - Retrieves a slice of bytes from the buffer.
 ///* `read_lp_string` - Reads a length-prefixed string from the buffer.
-//* `read_lp_string_aligned` - Reads a length-prefixed string from the buffer, aligning to the next specified alignment.
-///* `read_null_terminated_string` - Reads a null-terminated string from the buffer.
+///* `read_lp_string_aligned` - Reads a length-prefixed string from the buffer, aligning to the next specified alignment.
 ///* `read_header` - Reads a standard message header from the buffer
+///* `read_array` - Reads `n` consecutive elements with the given element reader.
+///* `read_lp_array` - Reads a u32 element count, then that many elements with the given element reader.
 pub trait BufferReader {
-    fn read_u32_le(&mut self) -> Result<u32, std::io::Error>;
-    fn read_f64_le(&mut self) -> Result<f64, std::io::Error>;
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
+    fn read_u32(&mut self, endian: Endian) -> Result<u32, std::io::Error>;
+    fn read_i32(&mut self, endian: Endian) -> Result<i32, std::io::Error>;
+    fn read_f32(&mut self, endian: Endian) -> Result<f32, std::io::Error>;
+    fn read_f64(&mut self, endian: Endian) -> Result<f64, std::io::Error>;
     fn read_byte(&mut self) -> Result<u8, std::io::Error>;
-    fn read_byte_aligned(&mut self, next_alignment: usize) -> Result<u8, std::io::Error> {
+    fn read_byte_aligned(&mut self, _next_alignment: usize) -> Result<u8, std::io::Error> {
         self.read_byte()
     }
-    fn slice(&mut self, length: usize) -> Option<&[u8]>;
     fn read_lp_string(&mut self) -> Result<String, std::io::Error>;
-    fn read_lp_string_aligned(&mut self, next_alignment: usize) -> Result<String, std::io::Error> {
+    fn read_lp_string_aligned(&mut self, _next_alignment: usize) -> Result<String, std::io::Error> {
         self.read_lp_string()
     }
-    fn read_null_terminated_string(&mut self) -> Result<String, std::io::Error>;
     fn read_header(&mut self) -> Result<pose::Header, std::io::Error>;
+    fn read_array<T>(
+        &mut self,
+        n: usize,
+        mut f: impl FnMut(&mut Self) -> Result<T, std::io::Error>,
+    ) -> Result<Vec<T>, std::io::Error>
+    where
+        Self: Sized,
+    {
+        (0..n).map(|_| f(self)).collect()
+    }
+    fn read_lp_array<T>(
+        &mut self,
+        f: impl FnMut(&mut Self) -> Result<T, std::io::Error>,
+    ) -> Result<Vec<T>, std::io::Error>
+    where
+        Self: Sized,
+    {
+        let n = self.read_u32(self.endian())? as usize;
+        self.read_array(n, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bag::BagDeserializer;
+    use crate::mcap::McapDeserializer;
+
+    #[test]
+    fn test_seek_bounds() {
+        let buf = MessageDataBuffer::new(vec![1, 2, 3]);
+
+        assert_eq!(buf.seek(0), Some(1));
+        assert_eq!(buf.seek(buf.len() - 1), Some(3));
+        assert_eq!(buf.seek(buf.len()), None);
+        assert_eq!(buf.seek(buf.len() + 1), None);
+    }
+
+    #[test]
+    fn test_read_null_terminated_string() {
+        let mut buf = MessageDataBuffer::new(b"\0map\0rest".to_vec());
+
+        assert_eq!(buf.read_null_terminated_string().unwrap(), "");
+        assert_eq!(buf.read_null_terminated_string().unwrap(), "map");
+        assert_eq!(buf.n_remaining(), 4);
+
+        let err = buf.read_null_terminated_string().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(buf.n_remaining(), 4);
+    }
+
+    #[test]
+    fn test_peek_and_rewind() {
+        let mut buf = MessageDataBuffer::new(vec![1, 2, 3, 4, 5]);
+        buf.slice(1).unwrap();
+
+        assert_eq!(buf.peek(2), Some(&[2, 3][..]));
+        assert_eq!(buf.peek(4), Some(&[2, 3, 4, 5][..]));
+        assert_eq!(buf.peek(5), None);
+        assert_eq!(buf.offset(), 1);
+        assert_eq!(buf.remaining(), &[2, 3, 4, 5]);
+
+        buf.slice(3).unwrap();
+        assert_eq!(buf.remaining(), &[5]);
+        buf.rewind(3).unwrap();
+        assert_eq!(buf.offset(), 1);
+        assert_eq!(buf.slice(2), Some(&[2, 3][..]));
+
+        let err = buf.rewind(4).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(buf.offset(), 3);
+    }
+
+    #[test]
+    fn test_read_both_byte_orders() {
+        let bytes = [0x3f, 0xf8, 0, 0, 0, 0, 0, 0];
+
+        let mut buf = MessageDataBuffer::new(bytes.to_vec());
+        assert_eq!(buf.read_f64_be().unwrap(), 1.5);
+        let mut buf = MessageDataBuffer::new(bytes.iter().rev().copied().collect());
+        assert_eq!(buf.read_f64_le().unwrap(), 1.5);
+
+        let mut buf = MessageDataBuffer::new(vec![0, 0, 1, 2, 2, 1, 0, 0]);
+        assert_eq!(buf.read_u32_be().unwrap(), 258);
+        assert_eq!(buf.read_u32_le().unwrap(), 258);
+
+        let mut buf = MessageDataBuffer::new(vec![0xff, 0xfe, 0xfe, 0xff]);
+        assert_eq!(buf.read_i16_be().unwrap(), -2);
+        assert_eq!(buf.read_i16_le().unwrap(), -2);
+
+        let mut buf = MessageDataBuffer::new(vec![0x3f, 0xc0, 0, 0, 0, 0, 0xc0, 0x3f]);
+        assert_eq!(buf.read_f32_be().unwrap(), 1.5);
+        assert_eq!(buf.read_f32_le().unwrap(), 1.5);
+    }
+
+    #[test]
+    fn test_buffer_reader_trait_object() {
+        let payload = [
+            &1.5f64.to_le_bytes()[..],
+            &(-2.5f32).to_le_bytes(),
+            &7u32.to_le_bytes(),
+            &(-7i32).to_le_bytes(),
+        ]
+        .concat();
+        let cdr = [&[0x00, 0x01, 0x00, 0x00][..], &payload].concat();
+
+        let mut mcap = McapDeserializer::new(cdr);
+        // Skip the encapsulation header
+        (0..4).for_each(|_| {
+            mcap.read_byte().unwrap();
+        });
+        let readers: Vec<Box<dyn BufferReader>> = vec![
+            Box::new(BagDeserializer::new(payload.clone())),
+            Box::new(mcap),
+        ];
+
+        for mut reader in readers {
+            let endian = reader.endian();
+            assert_eq!(reader.read_f64(endian).unwrap(), 1.5);
+            assert_eq!(reader.read_f32(endian).unwrap(), -2.5);
+            assert_eq!(reader.read_u32(endian).unwrap(), 7);
+            assert_eq!(reader.read_i32(endian).unwrap(), -7);
+            assert!(reader.read_byte().is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_lp_array() {
+        let values = [1.5, -2.0, 3.25];
+        let mut data = (values.len() as u32).to_le_bytes().to_vec();
+        values
+            .iter()
+            .for_each(|v: &f64| data.extend_from_slice(&v.to_le_bytes()));
+        data.push(0xff);
+
+        let mut d = BagDeserializer::new(data);
+        assert_eq!(
+            d.read_lp_array(|d| d.read_f64(Endian::Little)).unwrap(),
+            values
+        );
+        assert_eq!(d.read_byte().unwrap(), 0xff);
+
+        let mut d = BagDeserializer::new(vec![3, 0, 0, 0, 0, 0, 0, 0]);
+        let err = d.read_lp_array(|d| d.read_f64(Endian::Little)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
 }
@@ -0,0 +1,100 @@
+//! This module provides the error type of the crate.
+use std::fmt;
+use std::io;
+
+/// Errors raised while reading, writing or manipulating UVT data.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum UvtError {
+    /// An I/O error, or a malformed message in a recording.
+    Io(io::Error),
+    /// The VTK map does not have the expected structure.
+    Vtk(String),
+    /// A topic has no messages in a recording, along with the topics of the recording.
+    MissingTopic {
+        topic: String,
+        available: Vec<String>,
+    },
+    /// A line of the trajectory section of a UVT file is malformed.
+    /// Lines are numbered from 1, starting after the delimiter.
+    Parse { line: usize, msg: String },
+    /// The file does not follow the UVT format.
+    Format(String),
+    /// The trajectory cannot be used for the requested operation, e.g. it is empty.
+    Trajectory(String),
+}
+
+impl fmt::Display for UvtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UvtError::Io(e) => write!(f, "I/O error: {}", e),
+            UvtError::Vtk(msg) => write!(f, "Invalid VTK map: {}", msg),
+            UvtError::MissingTopic { topic, available } => write!(
+                f,
+                "Topic '{}' has no messages (available: {})",
+                topic,
+                available.join(", ")
+            ),
+            UvtError::Parse { line, msg } => write!(f, "Line {}: {}", line, msg),
+            UvtError::Format(msg) => write!(f, "Invalid UVT file: {}", msg),
+            UvtError::Trajectory(msg) => write!(f, "Invalid trajectory: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for UvtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UvtError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for UvtError {
+    fn from(e: io::Error) -> Self {
+        UvtError::Io(e)
+    }
+}
+
+impl From<UvtError> for io::Error {
+    fn from(e: UvtError) -> Self {
+        match e {
+            UvtError::Io(e) => e,
+            e @ UvtError::MissingTopic { .. } => {
+                io::Error::new(io::ErrorKind::NotFound, e.to_string())
+            }
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_io_error() {
+        let e = io::Error::from(UvtError::MissingTopic {
+            topic: String::from("/map"),
+            available: vec![String::from("/odom"), String::from("/tf")],
+        });
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            e.to_string(),
+            "Topic '/map' has no messages (available: /odom, /tf)"
+        );
+
+        let e = io::Error::from(UvtError::Parse {
+            line: 3,
+            msg: String::from("expected 6 or 8 values"),
+        });
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), "Line 3: expected 6 or 8 values");
+
+        let e = io::Error::from(UvtError::from(io::Error::from(
+            io::ErrorKind::PermissionDenied,
+        )));
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
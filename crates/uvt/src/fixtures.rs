@@ -0,0 +1,380 @@
+//! Synthetic messages and recordings shared by the tests.
+//!
+//! The other crates of the workspace use them through the `test-support` feature.
+use crate::deserialization::Endian;
+use crate::pointcloud::{CdrWriter, DataType, PointCloud2, PointField};
+use crate::pose;
+
+/// Builds a little-endian PointCloud2 with FLOAT32 `x`, `y` and `z` fields.
+pub fn xyz_pointcloud(points: &[pose::Point]) -> PointCloud2 {
+    let fields = ["x", "y", "z"]
+        .iter()
+        .enumerate()
+        .map(|(i, name)| PointField {
+            name: name.to_string(),
+            offset: 4 * i as u32,
+            datatype: DataType::FLOAT32,
+            count: 1,
+        })
+        .collect();
+    let data: Vec<u8> = points
+        .iter()
+        .flat_map(|&pt| Into::<[f32; 3]>::into(pt))
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+
+    PointCloud2 {
+        header: pose::Header {
+            seq: 0,
+            stamp: pose::Time { sec: 0, nanosec: 0 },
+            frame_id: String::from("map"),
+        },
+        height: 1,
+        width: points.len() as u32,
+        fields,
+        is_bigendian: false,
+        point_step: 12,
+        row_step: 12 * points.len() as u32,
+        data,
+        is_dense: true,
+    }
+}
+
+/// Serializes ROS messages, either in the ROS 1 layout or in CDR.
+pub struct MessageWriter {
+    layout: Layout,
+}
+
+/// Serialization of the values of a [`MessageWriter`].
+enum Layout {
+    /// Little-endian values, without alignment nor null terminators.
+    Ros1(Vec<u8>),
+    Cdr(CdrWriter),
+}
+
+#[allow(dead_code)]
+impl MessageWriter {
+    /// Starts a message in the ROS 1 layout (no alignment, no null terminators).
+    pub fn ros1() -> Self {
+        Self {
+            layout: Layout::Ros1(Vec::new()),
+        }
+    }
+
+    /// Starts a little-endian CDR message, including its encapsulation header.
+    pub fn cdr() -> Self {
+        Self {
+            layout: Layout::Cdr(CdrWriter::new(Endian::Little)),
+        }
+    }
+
+    /// Starts a big-endian CDR message, including its encapsulation header.
+    pub fn cdr_be() -> Self {
+        Self {
+            layout: Layout::Cdr(CdrWriter::new(Endian::Big)),
+        }
+    }
+
+    /// Writes a value with `ros1` in the ROS 1 layout, or with `cdr` in CDR.
+    fn write(
+        &mut self,
+        ros1: impl FnOnce(&mut Vec<u8>),
+        cdr: impl FnOnce(&mut CdrWriter) -> &mut CdrWriter,
+    ) -> &mut Self {
+        match &mut self.layout {
+            Layout::Ros1(buf) => ros1(buf),
+            Layout::Cdr(w) => {
+                cdr(w);
+            }
+        }
+        self
+    }
+
+    pub fn u8(&mut self, v: u8) -> &mut Self {
+        self.write(|buf| buf.push(v), |w| w.u8(v))
+    }
+
+    pub fn u32(&mut self, v: u32) -> &mut Self {
+        self.write(|buf| buf.extend(v.to_le_bytes()), |w| w.u32(v))
+    }
+
+    pub fn i32(&mut self, v: i32) -> &mut Self {
+        self.write(|buf| buf.extend(v.to_le_bytes()), |w| w.i32(v))
+    }
+
+    pub fn f32(&mut self, v: f32) -> &mut Self {
+        self.write(|buf| buf.extend(v.to_le_bytes()), |w| w.f32(v))
+    }
+
+    /// Writes consecutive f32 values, like the coordinates of a point.
+    pub fn f32s(&mut self, values: &[f32]) -> &mut Self {
+        values.iter().for_each(|&v| {
+            self.f32(v);
+        });
+        self
+    }
+
+    pub fn f64(&mut self, v: f64) -> &mut Self {
+        self.write(|buf| buf.extend(v.to_le_bytes()), |w| w.f64(v))
+    }
+
+    pub fn string(&mut self, s: &str) -> &mut Self {
+        self.write(
+            |buf| {
+                buf.extend((s.len() as u32).to_le_bytes());
+                buf.extend(s.as_bytes());
+            },
+            |w| w.string(s),
+        )
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.write(
+            |buf| {
+                buf.extend((bytes.len() as u32).to_le_bytes());
+                buf.extend(bytes);
+            },
+            |w| w.bytes(bytes),
+        )
+    }
+
+    /// Writes a std_msgs/Header. The sequence number only exists in ROS 1.
+    pub fn header(&mut self, seq: u32, stamp: pose::Time, frame_id: &str) -> &mut Self {
+        if let Layout::Ros1(_) = self.layout {
+            self.u32(seq);
+        }
+        self.i32(stamp.sec).u32(stamp.nanosec).string(frame_id)
+    }
+
+    /// Writes a geometry_msgs/Pose.
+    pub fn pose(&mut self, pose: &pose::Pose) -> &mut Self {
+        let (p, q) = (pose.position, pose.orientation);
+        [p.x, p.y, p.z, q.x, q.y, q.z, q.w]
+            .into_iter()
+            .for_each(|v| {
+                self.f64(v);
+            });
+        self
+    }
+
+    pub fn finish(&mut self) -> Vec<u8> {
+        match &mut self.layout {
+            Layout::Ros1(buf) => std::mem::take(buf),
+            Layout::Cdr(w) => w.finish(),
+        }
+    }
+}
+
+/// Serializes a nav_msgs/Odometry message with zero covariances and twist.
+pub fn odometry(
+    mut w: MessageWriter,
+    seq: u32,
+    stamp: pose::Time,
+    frame_id: &str,
+    child_frame_id: &str,
+    pose: &pose::Pose,
+) -> Vec<u8> {
+    w.header(seq, stamp, frame_id)
+        .string(child_frame_id)
+        .pose(pose);
+    (0..36 + 6 + 36).for_each(|_| {
+        w.f64(0.0);
+    });
+    w.finish()
+}
+
+/// Serializes a sensor_msgs/PointCloud2 message.
+pub fn pointcloud2(mut w: MessageWriter, cloud: &PointCloud2) -> Vec<u8> {
+    let header = &cloud.header;
+    w.header(header.seq, header.stamp, &header.frame_id)
+        .u32(cloud.height)
+        .u32(cloud.width)
+        .u32(cloud.fields.len() as u32);
+    cloud.fields.iter().for_each(|field| {
+        w.string(&field.name)
+            .u32(field.offset)
+            .u8(field.datatype.clone() as u8)
+            .u32(field.count);
+    });
+    w.u8(cloud.is_bigendian as u8)
+        .u32(cloud.point_step)
+        .u32(cloud.row_step)
+        .bytes(&cloud.data)
+        .u8(cloud.is_dense as u8);
+    w.finish()
+}
+
+/// Returns a path in the temporary directory, unique to this process.
+pub fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("uvt-{}-{}", std::process::id(), name))
+}
+
+/// Writes an MCAP file with CDR messages on the given `(topic, schema name, messages)` channels.
+pub fn write_mcap(path: &std::path::Path, channels: &[(&str, &str, Vec<Vec<u8>>)]) {
+    write_mcap_compressed(path, channels, None);
+}
+
+/// Writes an MCAP file like [`write_mcap`], with chunks compressed by `compression`.
+pub fn write_mcap_compressed(
+    path: &std::path::Path,
+    channels: &[(&str, &str, Vec<Vec<u8>>)],
+    compression: Option<mcap::Compression>,
+) {
+    let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    let mut writer = mcap::WriteOptions::new()
+        .compression(compression)
+        .create(file)
+        .unwrap();
+
+    let mut sequence = 0;
+    for (topic, schema_name, messages) in channels {
+        let schema_id = writer.add_schema(schema_name, "ros2msg", &[]).unwrap();
+        let channel_id = writer
+            .add_channel(schema_id, topic, "cdr", &Default::default())
+            .unwrap();
+        for msg in messages {
+            sequence += 1;
+            let header = mcap::records::MessageHeader {
+                channel_id,
+                sequence,
+                log_time: sequence as u64,
+                publish_time: sequence as u64,
+            };
+            writer.write_to_known_channel(&header, msg).unwrap();
+        }
+    }
+    writer.finish().unwrap();
+}
+
+/// Compresses the data of a bag chunk.
+fn compress(data: &[u8], compression: &str) -> Vec<u8> {
+    use std::io::Write;
+
+    match compression {
+        "bz2" => {
+            let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        "lz4" => {
+            let mut encoder = lz4::EncoderBuilder::new().build(Vec::new()).unwrap();
+            encoder.write_all(data).unwrap();
+            let (compressed, result) = encoder.finish();
+            result.unwrap();
+            compressed
+        }
+        _ => data.to_vec(),
+    }
+}
+
+/// Serializes a ROS bag record from its header fields and data.
+fn bag_record(fields: &[(&str, &[u8])], data: &[u8]) -> Vec<u8> {
+    let header: Vec<u8> = fields
+        .iter()
+        .flat_map(|(name, value)| {
+            let len = (name.len() + 1 + value.len()) as u32;
+            len.to_le_bytes()
+                .into_iter()
+                .chain(name.bytes())
+                .chain([b'='])
+                .chain(value.iter().copied())
+        })
+        .collect();
+    let mut record = Vec::new();
+    record.extend((header.len() as u32).to_le_bytes());
+    record.extend(header);
+    record.extend((data.len() as u32).to_le_bytes());
+    record.extend(data);
+    record
+}
+
+/// Writes a ROS bag (format 2.0) with the given `(topic, type, messages)` connections,
+/// all stored in a single uncompressed chunk.
+pub fn write_bag(path: &std::path::Path, connections: &[(&str, &str, Vec<Vec<u8>>)]) {
+    write_bag_compressed(path, connections, "none");
+}
+
+/// Writes a ROS bag like [`write_bag`], with its chunk compressed as `bz2` or `lz4`.
+///
+/// Any other `compression` is written in the chunk header, with the data left uncompressed.
+pub fn write_bag_compressed(
+    path: &std::path::Path,
+    connections: &[(&str, &str, Vec<Vec<u8>>)],
+    compression: &str,
+) {
+    let connection_records: Vec<Vec<u8>> = connections
+        .iter()
+        .enumerate()
+        .map(|(id, (topic, datatype, _))| {
+            let conn_header = bag_record(
+                &[
+                    ("topic", topic.as_bytes()),
+                    ("type", datatype.as_bytes()),
+                    ("md5sum", &[b'0'; 32]),
+                    ("message_definition", b""),
+                ],
+                &[],
+            );
+            // The connection header is the data of the record, without its own data length
+            let conn_header = &conn_header[4..conn_header.len() - 4];
+            bag_record(
+                &[
+                    ("op", &[0x07]),
+                    ("conn", &(id as u32).to_le_bytes()),
+                    ("topic", topic.as_bytes()),
+                ],
+                conn_header,
+            )
+        })
+        .collect();
+
+    let mut chunk_data: Vec<u8> = connection_records.concat();
+    let mut stamp = 0_u32;
+    for (id, (_, _, messages)) in connections.iter().enumerate() {
+        for msg in messages {
+            stamp += 1;
+            let time: Vec<u8> = stamp.to_le_bytes().into_iter().chain([0; 4]).collect();
+            chunk_data.extend(bag_record(
+                &[
+                    ("op", &[0x02]),
+                    ("conn", &(id as u32).to_le_bytes()),
+                    ("time", &time),
+                ],
+                msg,
+            ));
+        }
+    }
+    let chunk = bag_record(
+        &[
+            ("op", &[0x05]),
+            ("compression", compression.as_bytes()),
+            ("size", &(chunk_data.len() as u32).to_le_bytes()),
+        ],
+        &compress(&chunk_data, compression),
+    );
+
+    const VERSION: &[u8] = b"#ROSBAG V2.0\n";
+    let header_len = bag_record(
+        &[
+            ("op", &[0x03]),
+            ("index_pos", &0_u64.to_le_bytes()),
+            ("conn_count", &0_u32.to_le_bytes()),
+            ("chunk_count", &0_u32.to_le_bytes()),
+        ],
+        &[],
+    )
+    .len();
+    let index_pos = (VERSION.len() + header_len + chunk.len()) as u64;
+    let header = bag_record(
+        &[
+            ("op", &[0x03]),
+            ("index_pos", &index_pos.to_le_bytes()),
+            ("conn_count", &(connections.len() as u32).to_le_bytes()),
+            ("chunk_count", &1_u32.to_le_bytes()),
+        ],
+        &[],
+    );
+
+    let bag: Vec<u8> = [VERSION.to_vec(), header, chunk, connection_records.concat()].concat();
+    std::fs::write(path, bag).unwrap();
+}
@@ -9,6 +9,7 @@
 //! ## Features
 //! - Read/write `.uvt` files
 //! - Extract map and trajectory data from `.bag` and `.mcap` logs
+//! - Extract TF transforms from `.bag` and `.mcap` logs
 //!
 //! ## Example
 //! ```no_run
@@ -21,27 +22,814 @@
 //! uvt.write_file("output.uvt").unwrap();
 //! ```
 use rayon::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{Error, ErrorKind};
 use std::path;
+use std::sync::Arc;
 use std::{fs, time::Duration};
 
 extern crate mcap as mcap_crate;
 
 use rosbag::{ChunkRecord, IndexRecord, MessageRecord, RosBag};
-use tqdm::Iter;
 use vtkio::Vtk;
+use vtkio::model::{Attribute, DataArray, DataSet, Piece};
 
 mod bag;
 mod deserialization;
+mod error;
+#[cfg(any(test, feature = "test-support"))]
+#[doc(hidden)]
+pub mod fixtures;
+mod map_file;
 mod mcap;
 mod pointcloud;
 pub mod pose;
+mod tf;
 mod trajectory;
+pub use bag::BagDeserializer;
+pub use error::UvtError;
+pub use pointcloud::{DataType, PointCloud2, PointField, VoxelGrid, VoxelKey, voxel_downsample};
 pub use pose::Point;
+pub use trajectory::{JumpHandling, TrajectoryType, remove_jumps};
 
 use memmap2::Mmap;
 
-const TRAJ_DELIM: &str = "#############################";
+/// Line separating the map section from the trajectory section in a UVT file.
+///
+/// Everything before it is the map (an inline legacy VTK map or a `map_ref` line),
+/// everything after it is the trajectory.
+pub const TRAJ_DELIMITER: &str = "#############################";
+
+/// Key of the header line referencing an external map file, in place of an inline VTK map
+const MAP_REF_KEY: &str = "map_ref";
+
+/// Name of the LiDAR scan ring field, in pointclouds and in the VTK map point data
+const RING_FIELD: &str = "ring";
+
+/// Name of the intensity point data of the VTK map
+const INTENSITY_FIELD: &str = "intensity";
+
+/// Name of the RGBA color scalars of the VTK map
+const COLOR_FIELD: &str = "rgba";
+
+/// Topics on which the TF tree is published
+const TF_TOPICS: [&str; 2] = ["/tf_static", "/tf"];
+
+/// Message types of legacy `sensor_msgs/PointCloud` maps, in ROS 1 and ROS 2
+const LEGACY_POINTCLOUD_TYPES: [&str; 2] = ["sensor_msgs/PointCloud", "sensor_msgs/msg/PointCloud"];
+
+/// Checks whether a map topic type is a legacy `sensor_msgs/PointCloud`.
+fn is_legacy_pointcloud(datatype: Option<&str>) -> bool {
+    datatype.is_some_and(|t| LEGACY_POINTCLOUD_TYPES.contains(&t))
+}
+
+/// Parses a map message, of the legacy `sensor_msgs/PointCloud` type if `legacy` is set.
+///
+/// Only the points within the spatial filter of `options`, if any, are kept in the cloud,
+/// so that the points outside of it are dropped as soon as the message is decoded.
+fn parse_map<D>(d: D, legacy: bool, options: &ReadOptions) -> Result<pointcloud::PointCloud2, Error>
+where
+    D: pointcloud::PointCloud2Deserializer + pointcloud::LegacyPointCloudDeserializer,
+{
+    let mut cloud = if legacy {
+        pointcloud::parse_pointcloud_legacy(d)?
+    } else {
+        pointcloud::parse_pointcloud(d)?
+    };
+    if let Some((min, max)) = &options.spatial_filter {
+        cloud.retain_within(min, max)?;
+    }
+    Ok(cloud)
+}
+
+/// Options controlling the extraction of UVT data from rosbags.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// Only keep the map points inside this axis-aligned box, given as (min corner, max corner).
+    /// The points outside of the box are dropped as each map message is decoded, so the memory
+    /// used by the decoded map clouds is bounded by the kept points, not by the map size.
+    pub spatial_filter: Option<(Point, Point)>,
+    /// Fail instead of warning when one of the map and trajectory topics has no messages
+    /// while the other one does, which usually means the topic name has a typo.
+    pub strict_topics: bool,
+    /// Message layout of the trajectory topic.
+    pub trajectory_type: TrajectoryType,
+    /// Merge every map message into a voxel grid of this leaf size, instead of only
+    /// keeping the last one. The merged map has at most one point per voxel.
+    /// Reading fails with an `InvalidInput` error if the leaf size is not positive and finite.
+    pub merge_leaf_size: Option<f64>,
+    /// Concatenate the points of every map message, instead of only keeping the last one.
+    /// Exact duplicate points are only kept once. Ignored if `merge_leaf_size` is set.
+    pub accumulate_maps: bool,
+    /// Precision of the map point coordinates, single precision by default.
+    pub map_precision: MapPrecision,
+    /// Called with the message of each warning raised while reading, e.g. when one of the
+    /// map and trajectory topics has no messages, or when invalid orientations are replaced
+    /// by the identity. Warnings are discarded when unset.
+    pub on_warning: Option<fn(&str)>,
+}
+
+impl ReadOptions {
+    /// Reports a warning to [`ReadOptions::on_warning`], if set.
+    fn warn(&self, message: &str) {
+        if let Some(on_warning) = self.on_warning {
+            on_warning(message);
+        }
+    }
+}
+
+/// Precision of the coordinates of the VTK map points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapPrecision {
+    /// Single precision (`f32`) coordinates, half the size of double precision ones.
+    /// Coordinates are rounded to about 7 significant digits, e.g. to 0.5 m for
+    /// UTM northings of millions of meters.
+    #[default]
+    Single,
+    /// Double precision (`f64`) coordinates, as in the recorded pointclouds.
+    Double,
+}
+
+impl MapPrecision {
+    /// Gives the precision of a map point buffer: double for `f64` values, single otherwise.
+    fn of(buffer: &vtkio::IOBuffer) -> Self {
+        match buffer {
+            vtkio::IOBuffer::F64(_) => MapPrecision::Double,
+            _ => MapPrecision::Single,
+        }
+    }
+}
+
+/// Phase of the extraction of UVT data from a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressPhase {
+    /// Decoding the map messages.
+    ReadingMap,
+    /// Decoding the trajectory messages.
+    ReadingTrajectory,
+    /// Concatenating the map messages, with [`ReadOptions::accumulate_maps`].
+    AccumulatingMap,
+    /// Merging the map messages into a voxel grid, with [`ReadOptions::merge_leaf_size`].
+    MergingMap,
+}
+
+impl std::fmt::Display for ProgressPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let desc = match self {
+            ProgressPhase::ReadingMap => "Reading map msgs",
+            ProgressPhase::ReadingTrajectory => "Reading trajectory msgs",
+            ProgressPhase::AccumulatingMap => "Accumulating map msgs",
+            ProgressPhase::MergingMap => "Merging map msgs",
+        };
+        write!(f, "{}", desc)
+    }
+}
+
+/// Progress of the extraction of UVT data, reported as each message is processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressEvent {
+    /// The current phase of the extraction.
+    pub phase: ProgressPhase,
+    /// The number of messages of the phase processed so far, including the current one.
+    pub current: usize,
+    /// The number of messages of the phase.
+    pub total: usize,
+}
+
+/// Reports progress events with tqdm progress bars, one per phase.
+fn tqdm_progress() -> impl FnMut(ProgressEvent) {
+    let mut bar: Option<(ProgressPhase, tqdm::Tqdm<std::ops::Range<usize>>)> = None;
+    move |event| {
+        if bar.as_ref().is_none_or(|(phase, _)| *phase != event.phase) {
+            let desc = Some(event.phase.to_string());
+            bar = Some((event.phase, tqdm::tqdm(0..event.total).desc(desc)));
+        }
+        if let Some((_, bar)) = bar.as_mut() {
+            bar.next();
+        }
+    }
+}
+
+/// Iterates over `items`, reporting a progress event of `phase` for each item.
+fn report_progress<'a, T>(
+    items: &'a [T],
+    phase: ProgressPhase,
+    progress: &'a mut dyn FnMut(ProgressEvent),
+) -> impl Iterator<Item = &'a T> {
+    let total = items.len();
+    items.iter().enumerate().map(move |(i, item)| {
+        progress(ProgressEvent {
+            phase,
+            current: i + 1,
+            total,
+        });
+        item
+    })
+}
+
+/// Number of messages parsed in parallel between two progress reports.
+const PARSE_BATCH_SIZE: usize = 1024;
+
+/// Parses `msgs` in parallel, keeping the message order in the output.
+/// Messages are parsed by batches, and a progress event of `phase` is reported for each
+/// message of a batch once the whole batch is parsed.
+/// Parsing stops at the first batch containing a message that cannot be parsed.
+fn parse_messages<T: Send>(
+    msgs: &[Vec<u8>],
+    phase: ProgressPhase,
+    progress: &mut dyn FnMut(ProgressEvent),
+    parse: impl Fn(&[u8]) -> Result<Vec<T>, Error> + Sync,
+) -> Result<Vec<T>, Error> {
+    let total = msgs.len();
+    let mut parsed = Vec::with_capacity(total);
+    for (i, batch) in msgs.chunks(PARSE_BATCH_SIZE).enumerate() {
+        let batch_parsed: Vec<Vec<T>> = batch
+            .par_iter()
+            .map(|msg| parse(msg))
+            .collect::<Result<_, _>>()?;
+        parsed.extend(batch_parsed.into_iter().flatten());
+
+        let start = i * PARSE_BATCH_SIZE;
+        (start + 1..=start + batch.len()).for_each(|current| {
+            progress(ProgressEvent {
+                phase,
+                current,
+                total,
+            })
+        });
+    }
+    Ok(parsed)
+}
+
+/// Encoding of the legacy VTK map of a UVT file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapEncoding {
+    /// Human-readable ASCII values.
+    #[default]
+    Ascii,
+    /// Big-endian binary values, more compact and faster to parse.
+    Binary,
+}
+
+/// Options controlling how UVT files are written.
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Encoding of the inline VTK map. The trajectory is always written as text.
+    pub map_encoding: MapEncoding,
+    /// Number of decimals of the trajectory values, 6 by default.
+    /// With `None`, values are written with full precision.
+    pub decimals: Option<u32>,
+    /// Write the file even if the map has no points, instead of failing.
+    pub allow_empty_map: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            map_encoding: MapEncoding::default(),
+            decimals: Some(6),
+            allow_empty_map: false,
+        }
+    }
+}
+
+/// Area over which [`Uvt::map_density_over`] spreads the map points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DensityArea {
+    /// The XY bounding box of the map points.
+    #[default]
+    MapBoundingBox,
+    /// The XY convex hull of the trajectory positions, i.e. the area covered by the vehicle.
+    TrajectoryHull,
+}
+
+/// Computes the area of the convex hull of 2D points, with Andrew's monotone chain.
+fn convex_hull_area(mut points: Vec<(f64, f64)>) -> f64 {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+    if points.len() < 3 {
+        return 0.0;
+    }
+
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<(f64, f64)> = Vec::with_capacity(2 * points.len());
+    // Lower hull, then upper hull
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for pt in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], pt) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(pt);
+        }
+        // The last point is the first one of the next pass
+        hull.pop();
+    }
+
+    // Shoelace formula
+    let n = hull.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (hull[i], hull[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        .abs()
+        / 2.0
+}
+
+/// Computes the axis-aligned bounding box of points, as its (min, max) corners.
+/// Points with a non-finite coordinate are skipped. Returns `None` if there is no other point.
+fn bounds<I: IntoIterator<Item = Point>>(points: I) -> Option<(Point, Point)> {
+    points
+        .into_iter()
+        .filter(Point::is_finite)
+        .fold(None, |acc, pt| {
+            let (min, max) = acc.unwrap_or((pt, pt));
+            Some((
+                Point::new(min.x.min(pt.x), min.y.min(pt.y), min.z.min(pt.z)),
+                Point::new(max.x.max(pt.x), max.y.max(pt.y), max.z.max(pt.z)),
+            ))
+        })
+}
+
+/// Computes the mean of points. Returns `None` if there is no point.
+fn centroid<I: IntoIterator<Item = Point>>(points: I) -> Option<Point> {
+    let (sum, n) = points
+        .into_iter()
+        .fold((Point::new(0.0, 0.0, 0.0), 0), |(sum, n), pt| {
+            (sum + pt, n + 1)
+        });
+    (n > 0).then(|| sum * (1.0 / n as f64))
+}
+
+/// Splits the content of a UVT file into its map section and its trajectory section.
+///
+/// The map section is returned as is, up to the delimiter, while the trajectory section is trimmed.
+/// Only a delimiter standing on its own line counts, so that a VTK title or comment containing
+/// the delimiter string does not split the file, and a file with several such lines is rejected.
+fn split_uvt(content: &[u8]) -> Result<(&[u8], &str), UvtError> {
+    let is_line_start = |i: usize| i == 0 || content[i - 1] == b'\n';
+    let is_line_end = |i: usize| matches!(&content[i..], [] | [b'\n', ..] | [b'\r', b'\n', ..]);
+    let mut delimiters = content
+        .windows(TRAJ_DELIMITER.len())
+        .enumerate()
+        .filter(|&(i, w)| {
+            w == TRAJ_DELIMITER.as_bytes()
+                && is_line_start(i)
+                && is_line_end(i + TRAJ_DELIMITER.len())
+        })
+        .map(|(i, _)| i);
+    let delimiter = delimiters.next().ok_or(UvtError::Format(String::from(
+        "Could not find Trajectory delimiter in UVT file",
+    )))?;
+    if delimiters.next().is_some() {
+        return Err(UvtError::Format(String::from(
+            "Found several Trajectory delimiter lines in UVT file",
+        )));
+    }
+    let traj_str = str::from_utf8(&content[delimiter + TRAJ_DELIMITER.len()..])
+        .map_err(|_| UvtError::Format(String::from("Trajectory data is not valid UTF-8")))?;
+    Ok((&content[..delimiter], traj_str.trim()))
+}
+
+/// Parses the map section of a UVT file, either an inline legacy VTK map, in ASCII or binary,
+/// or a `map_ref` reference to an external map file, relative to `base_dir`.
+fn parse_map_section(map_section: &[u8], base_dir: Option<&path::Path>) -> Result<Vtk, UvtError> {
+    // Trailing bytes are kept, as they may be part of binary data
+    let vtk_bytes = map_section.trim_ascii_start();
+    match str::from_utf8(vtk_bytes)
+        .ok()
+        .and_then(|vtk_str| vtk_str.split_once(":"))
+        .filter(|(key, _)| key.trim() == MAP_REF_KEY)
+    {
+        Some((_, map_ref)) => {
+            let map_path = base_dir.unwrap_or(path::Path::new("")).join(map_ref.trim());
+            Vtk::import(&map_path).map_err(|e| {
+                UvtError::Vtk(format!("Failed to read map {}: {}", map_path.display(), e))
+            })
+        }
+        None => Vtk::parse_legacy_be(vtk_bytes)
+            .map_err(|e| UvtError::Vtk(format!("Failed to parse vtk: {}", e))),
+    }
+}
+
+/// Parses the trajectory section of a UVT file: a `frame_id : <value>` line followed
+/// by one `x,y,z,roll,pitch,yaw` line per pose.
+fn parse_trajectory_section(traj_str: &str) -> Result<Vec<pose::PoseStamped>, UvtError> {
+    let invalid = |line: usize, msg: String| UvtError::Parse { line, msg };
+
+    let frame_line = traj_str.lines().next().ok_or_else(|| {
+        UvtError::Format(String::from("Missing frame_id line after the delimiter"))
+    })?;
+    let frame_id = frame_line
+        .split_once(":")
+        .ok_or_else(|| {
+            invalid(
+                1,
+                format!("expected 'frame_id : <value>', got '{}'", frame_line),
+            )
+        })?
+        .1
+        .trim();
+
+    traj_str
+        .lines()
+        .skip(1)
+        .enumerate()
+        .map(|(i, line)| {
+            let columns: Vec<&str> = line.split(",").map(str::trim).collect();
+            if columns.len() != 6 && columns.len() != 8 {
+                return Err(invalid(
+                    i + 2,
+                    format!("expected 6 or 8 values, got {} - '{}'", columns.len(), line),
+                ));
+            }
+            let parse_error = || invalid(i + 2, format!("failed to parse values in '{}'", line));
+            let values: Vec<f64> = columns[..6]
+                .iter()
+                .map(|n| n.parse::<f64>().map_err(|_| parse_error()))
+                .collect::<Result<Vec<f64>, UvtError>>()?;
+
+            // Legacy files have no stamps
+            let stamp = match columns[6..] {
+                [sec, nanosec] => pose::Time {
+                    sec: sec.parse().map_err(|_| parse_error())?,
+                    nanosec: nanosec.parse().map_err(|_| parse_error())?,
+                },
+                _ => Duration::from_secs(0).into(),
+            };
+            let header = pose::Header {
+                frame_id: frame_id.to_string(),
+                seq: (i + 2) as u32,
+                stamp,
+            };
+
+            Ok(pose::PoseStamped::new(
+                header,
+                pose::Pose::from_6dof((
+                    values[0], values[1], values[2], // X, Y, Z
+                    values[3], values[4], values[5], // Roll, Pitch, Yaw
+                )),
+            ))
+        })
+        .collect()
+}
+
+/// Parses a trajectory CSV file, as written by [`Uvt::write_trajectory_csv`], with poses
+/// expressed in `frame_id`.
+///
+/// Columns are matched by the names of the header row. The `x`, `y`, `z`, `qx`, `qy`, `qz`
+/// and `qw` columns are required, while the `seq`, `sec` and `nanosec` columns are optional.
+/// Orientations that cannot be normalized are replaced by the identity, which is reported
+/// as a warning to `options`.
+fn parse_trajectory_csv(
+    csv: &str,
+    frame_id: &str,
+    options: &ReadOptions,
+) -> Result<Vec<pose::PoseStamped>, UvtError> {
+    let invalid = |line: usize, msg: String| UvtError::Parse { line, msg };
+
+    let header_line = csv
+        .lines()
+        .next()
+        .ok_or_else(|| UvtError::Format(String::from("Missing CSV header row")))?;
+    let names: Vec<&str> = header_line.split(",").map(str::trim).collect();
+    let column = |name: &str| names.iter().position(|&n| n == name);
+    let pose_columns = ["x", "y", "z", "qx", "qy", "qz", "qw"]
+        .into_iter()
+        .map(|name| column(name).ok_or_else(|| invalid(1, format!("missing '{}' column", name))))
+        .collect::<Result<Vec<usize>, UvtError>>()?;
+    let (seq_column, sec_column, nanosec_column) =
+        (column("seq"), column("sec"), column("nanosec"));
+
+    let mut poses = csv
+        .lines()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let columns: Vec<&str> = line.split(",").map(str::trim).collect();
+            if columns.len() != names.len() {
+                return Err(invalid(
+                    i + 1,
+                    format!(
+                        "expected {} values, got {} - '{}'",
+                        names.len(),
+                        columns.len(),
+                        line
+                    ),
+                ));
+            }
+            let parse_error = || invalid(i + 1, format!("failed to parse values in '{}'", line));
+            let values: Vec<f64> = pose_columns
+                .iter()
+                .map(|&c| columns[c].parse::<f64>().map_err(|_| parse_error()))
+                .collect::<Result<Vec<f64>, UvtError>>()?;
+            let seq = match seq_column {
+                Some(c) => columns[c].parse().map_err(|_| parse_error())?,
+                None => (i - 1) as u32,
+            };
+            let stamp = pose::Time {
+                sec: match sec_column {
+                    Some(c) => columns[c].parse().map_err(|_| parse_error())?,
+                    None => 0,
+                },
+                nanosec: match nanosec_column {
+                    Some(c) => columns[c].parse().map_err(|_| parse_error())?,
+                    None => 0,
+                },
+            };
+            let header = pose::Header {
+                seq,
+                stamp,
+                frame_id: frame_id.to_string(),
+            };
+
+            Ok(pose::PoseStamped::from_hpo(
+                header,
+                Point::new(values[0], values[1], values[2]),
+                pose::Quaternion::new(values[3], values[4], values[5], values[6]),
+            ))
+        })
+        .collect::<Result<Vec<_>, UvtError>>()?;
+    let replaced = trajectory::check_orientations(&mut poses);
+    if let Some(warning) = replaced_orientations_warning(replaced) {
+        options.warn(&warning);
+    }
+    Ok(poses)
+}
+
+/// Describes the orientations of a read trajectory replaced by the identity, if any.
+fn replaced_orientations_warning(replaced: usize) -> Option<String> {
+    (replaced > 0).then(|| {
+        format!(
+            "{} invalid orientation quaternions replaced by the identity",
+            replaced
+        )
+    })
+}
+
+/// Wraps the points of a standalone map file into a VTK map, keeping its `intensity` field,
+/// if any, as the `intensity` point data of the map.
+/// Points with a non-finite coordinate are dropped.
+fn cloud_map(cloud: &pointcloud::PointCloud2, title: String) -> Result<Vtk, UvtError> {
+    let (coords, points): (Vec<Point>, Vec<_>) = cloud
+        .points()?
+        .into_iter()
+        .map(|pt| (Point::new(pt["x"], pt["y"], pt["z"]), pt))
+        .filter(|(coords, _)| coords.is_finite())
+        .unzip();
+
+    let mut attributes = vtkio::model::Attributes::new();
+    if cloud.fields.iter().any(|f| f.name == INTENSITY_FIELD) {
+        let intensities: Vec<f32> = points.iter().map(|pt| pt[INTENSITY_FIELD] as f32).collect();
+        attributes
+            .point
+            .push(Attribute::scalars(INTENSITY_FIELD, 1).with_data(intensities));
+    }
+    Ok(points_vtk(&coords, attributes, title, MapPrecision::Single))
+}
+
+/// Retrieves the frame shared by every pose of a trajectory.
+/// Returns a [`UvtError::Trajectory`] error if the trajectory is empty or mixes several frames.
+fn trajectory_frame_id(trajectory: &[pose::PoseStamped]) -> Result<&str, UvtError> {
+    let frame_id = trajectory
+        .first()
+        .ok_or(UvtError::Trajectory(String::from("Missing poses")))?
+        .header
+        .frame_id
+        .as_str();
+
+    if trajectory.iter().any(|p| p.header.frame_id != frame_id) {
+        let mut frames: Vec<&str> = trajectory
+            .iter()
+            .map(|p| p.header.frame_id.as_str())
+            .collect();
+        frames.sort();
+        frames.dedup();
+        return Err(UvtError::Trajectory(format!(
+            "Poses are expressed in several frames: {}",
+            frames.join(", ")
+        )));
+    }
+    Ok(frame_id)
+}
+
+/// Writes the map section, the delimiter and the trajectory data to a UVT file.
+/// Trajectory values are rounded to `decimals` decimals, if any.
+fn write_uvt<P: AsRef<path::Path>>(
+    path: P,
+    map_section: &[u8],
+    trajectory: &[pose::PoseStamped],
+    decimals: Option<u32>,
+) -> Result<(), UvtError> {
+    let export_path = path::absolute(path)?;
+
+    let frame_str = format!("frame_id : {}", trajectory_frame_id(trajectory)?);
+
+    let round = |v: f64| decimals.map_or(v, |n| pose::round(v, n));
+    let traj_poses: Vec<String> = trajectory
+        .iter()
+        .map(|pose| {
+            let dofs = pose.pose.to_6dof();
+            let x = round(dofs.0);
+            let y = round(dofs.1);
+            let z = round(dofs.2);
+            let roll = round(dofs.3);
+            let pitch = round(dofs.4);
+            let yaw = round(dofs.5);
+
+            let (sec, nanosec) = (pose.header.stamp.sec, pose.header.stamp.nanosec);
+
+            format!("{x},{y},{z},{roll},{pitch},{yaw},{sec},{nanosec}")
+        })
+        .collect();
+    let traj_str = traj_poses.join("\n");
+    let mut uvt_bytes = map_section.to_vec();
+    uvt_bytes.extend(
+        [TRAJ_DELIMITER, &frame_str, &traj_str]
+            .join("\n")
+            .into_bytes(),
+    );
+
+    fs::write(export_path, uvt_bytes)?;
+
+    Ok(())
+}
+
+/// Points of a map pointcloud, along with their scan ring and their color,
+/// when the cloud has a `ring` field and a packed color field.
+#[derive(Default)]
+struct MapPoints {
+    points: Vec<Point>,
+    rings: Option<Vec<u16>>,
+    colors: Option<Vec<[u8; 4]>>,
+}
+
+/// Wraps points into a VTK map made of a single inline PolyData piece,
+/// with coordinates of the given precision.
+fn points_vtk(
+    points: &[Point],
+    attributes: vtkio::model::Attributes,
+    title: String,
+    precision: MapPrecision,
+) -> Vtk {
+    let pts = match precision {
+        MapPrecision::Single => vtkio::IOBuffer::F32(
+            points
+                .par_iter()
+                .map(|&pt| Into::<[f32; 3]>::into(pt))
+                .flatten()
+                .collect(),
+        ),
+        MapPrecision::Double => vtkio::IOBuffer::F64(
+            points
+                .par_iter()
+                .flat_map(|pt| [pt.x, pt.y, pt.z])
+                .collect(),
+        ),
+    };
+    let data = vtkio::model::DataSet::inline(vtkio::model::PolyDataPiece {
+        points: pts,
+        verts: None,
+        lines: None,
+        polys: None,
+        strips: None,
+        data: attributes,
+    });
+
+    Vtk {
+        version: vtkio::model::Version { major: 3, minor: 0 },
+        byte_order: vtkio::model::ByteOrder::BigEndian,
+        title,
+        file_path: None,
+        data,
+    }
+}
+
+/// Checks that `min` and `max` span a valid axis-aligned box.
+fn check_box(min: &Point, max: &Point) -> Result<(), UvtError> {
+    if min.x > max.x || min.y > max.y || min.z > max.z {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Inverted bounding box: min {:?} exceeds max {:?}", min, max),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Gives the file name of `path`, or the whole path if it has none, e.g. when it ends in `..`.
+/// Names that are not valid UTF-8 are converted lossily.
+fn file_name(path: &path::Path) -> std::borrow::Cow<'_, str> {
+    path.file_name()
+        .map_or_else(|| path.to_string_lossy(), |f| f.to_string_lossy())
+}
+
+/// Checks the options of a read, before any message is decoded.
+fn check_read_options(options: &ReadOptions) -> Result<(), UvtError> {
+    if let Some(leaf_size) = options.merge_leaf_size {
+        pointcloud::VoxelGrid::new(leaf_size)?;
+    }
+    Ok(())
+}
+
+/// Checks that `buffer` holds one tuple of `num_comp` values per flag of `keep`.
+fn can_retain_tuples(buffer: &vtkio::IOBuffer, num_comp: usize, keep: &[bool]) -> bool {
+    !matches!(buffer, vtkio::IOBuffer::Bit(_)) && buffer.len() == num_comp * keep.len()
+}
+
+/// Keeps the tuples of `num_comp` values of `buffer` whose flag in `keep` is set.
+fn retain_tuples(buffer: &mut vtkio::IOBuffer, num_comp: usize, keep: &[bool]) {
+    use vtkio::IOBuffer;
+    vtkio::match_buf!(buffer, values => {
+        *values = values
+            .chunks_exact(num_comp)
+            .zip(keep)
+            .filter(|&(_, &k)| k)
+            .flat_map(|(tuple, _)| tuple.iter().copied())
+            .collect();
+    })
+}
+
+/// Checks that the map and trajectory topics are either both empty or both populated.
+///
+/// # Arguments
+///
+/// * `map` - The map topic and its number of messages.
+/// * `traj` - The trajectory topic and its number of messages.
+/// * `available_topics` - Lists the topics of the recording, only called on a mismatch.
+/// * `options` - Whether a mismatch is an error or a warning.
+///
+/// # Returns
+///
+/// The warning message, if any, when `options.strict_topics` is disabled. It is left to
+/// the caller to report it.
+fn check_topic_counts<F: FnOnce() -> Vec<String>>(
+    map: (&str, usize),
+    traj: (&str, usize),
+    available_topics: F,
+    options: &ReadOptions,
+) -> Result<Option<String>, UvtError> {
+    let empty_topic = match (map, traj) {
+        ((topic, 0), (_, n)) | ((_, n), (topic, 0)) if n > 0 => topic,
+        _ => return Ok(None),
+    };
+
+    let available = available_topics();
+    if options.strict_topics {
+        return Err(UvtError::MissingTopic {
+            topic: empty_topic.to_string(),
+            available,
+        });
+    }
+    let message = format!(
+        "Topic '{}' has no messages, while the other topic does. Is the topic name misspelled? (available: {})",
+        empty_topic,
+        available.join(", ")
+    );
+    Ok(Some(message))
+}
+
+/// Converts an error raised while reading the chunks of a ROS bag into an I/O error.
+///
+/// The bag reader rejects the header of chunks compressed with an unknown format.
+fn bag_chunk_error(e: rosbag::Error) -> Error {
+    match e {
+        rosbag::Error::InvalidHeader => Error::new(
+            ErrorKind::Unsupported,
+            "Invalid bag chunk header, the chunk may use an unsupported compression (supported: none, bz2, lz4)",
+        ),
+        rosbag::Error::Bzip2DecompressionError(_) | rosbag::Error::Lz4DecompressionError(_) => {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Failed to decompress bag chunk: {}", e),
+            )
+        }
+        e => Error::new(ErrorKind::InvalidData, e),
+    }
+}
+
+/// Converts an error of the MCAP reader into an I/O error.
+///
+/// Chunks compressed with a format the reader was not built with are reported as `Unsupported`.
+fn mcap_error(e: mcap_crate::McapError) -> Error {
+    match e {
+        mcap_crate::McapError::UnsupportedCompression(compression) => Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "MCAP chunks are compressed with '{}', which is not supported (supported: lz4, zstd)",
+                compression
+            ),
+        ),
+        e => Error::new(ErrorKind::InvalidData, e),
+    }
+}
 
 /// A UVT (_Uncrewed Vehicle Trajectory_)
 ///
@@ -56,9 +844,48 @@ pub struct Uvt {
 }
 
 impl Uvt {
+    /// Creates a UVT from a VTK map and a trajectory.
+    ///
+    /// # Arguments
+    ///
+    /// * `map` - The environment map.
+    /// * `trajectory` - The vehicle's trajectory.
+    pub fn new(map: vtkio::Vtk, trajectory: Vec<pose::PoseStamped>) -> Self {
+        Self { map, trajectory }
+    }
+
+    /// Creates a UVT from map points and a trajectory.
+    ///
+    /// The points are wrapped into a single inline PolyData piece, like the maps extracted from rosbags.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The points of the environment map.
+    /// * `trajectory` - The vehicle's trajectory.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{Point, Uvt};
+    ///
+    /// let points = vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 2.0, 0.5)];
+    /// let uvt = Uvt::from_points(points, vec![]);
+    /// ```
+    pub fn from_points(points: Vec<pose::Point>, trajectory: Vec<pose::PoseStamped>) -> Self {
+        let map = points_vtk(
+            &points,
+            vtkio::model::Attributes::new(),
+            String::from("UVT file generated from points"),
+            MapPrecision::Single,
+        );
+        Self::new(map, trajectory)
+    }
+
     /// Read a UVT file from disk.
     /// A UVT file contains both a VTK map and a trajectory.
     ///
+    /// Instead of an inline VTK map, the file can start with a `map_ref : <path>` line
+    /// referencing an external map file (`.vtk`, `.vtp`, ...), see [`Uvt::write_file_with_map_ref`].
+    /// A relative reference is relative to the directory of the UVT file.
     ///
     /// # Arguments
     ///
@@ -75,276 +902,383 @@ impl Uvt {
     /// Returns an error if:
     /// - The file cannot be read
     /// - The VTK or trajectory data is malformed
-    /// = The UVT file does not follow the UVT format
+    /// - The UVT file does not follow the UVT format
     ///
     /// # Example
     /// ```no_run
+    /// use uvt::Uvt;
+    ///
     /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
     /// ```
-    pub fn read_file<P: AsRef<path::Path>>(path: P) -> Result<Self, Error> {
+    pub fn read_file<P: AsRef<path::Path>>(path: P) -> Result<Self, UvtError> {
         let fpath = path.as_ref();
-        let content = fs::read_to_string(fpath)?;
-
-        println!(
-            "Reading uvt file in {}",
-            path::absolute(fpath).unwrap().display()
-        );
-
-        let delimiter = content.find(TRAJ_DELIM).ok_or(Error::new(
-            ErrorKind::InvalidData,
-            "Could not find Trajectory delimiter in UVT file",
-        ))?;
-        let vtk_str = content[..delimiter].trim();
-        let traj_str = content[delimiter + TRAJ_DELIM.len()..].trim();
-
-        let vtk_file =
-            Vtk::parse_legacy_be(vtk_str.as_bytes()).expect(&format!("Failed to parse vtk"));
-
-        let frame_id = traj_str
-            .lines()
-            .next()
-            .unwrap()
-            .split_once(":")
-            .expect("Expected frame_id line following 'frame_id : <value>'")
-            .1
-            .trim();
+        let content = fs::read(fpath)?;
 
-        let trajectory: Vec<pose::PoseStamped> = traj_str
-            .lines()
-            .skip(1)
-            .enumerate()
-            .map(|(i, line)| {
-                let values: Vec<f64> = line
-                    .split(",")
-                    .map(|n| {
-                        n.trim().parse::<f64>().unwrap_or_else(|_| {
-                            panic!("Failed to parse floats in line {}: '{}'", i + 2, line)
-                        })
-                    })
-                    .collect::<Vec<f64>>();
-                if values.len() != 6 {
-                    panic!(
-                        "Line {}: expected 6 values, got {} - '{}'",
-                        i + 2,
-                        values.len(),
-                        line
-                    );
-                }
-
-                // TODO: Get more info, with time
-                let header = pose::Header {
-                    frame_id: frame_id.to_string(),
-                    seq: (i + 2) as u32,
-                    stamp: Duration::from_secs(0).into(),
-                };
-
-                pose::PoseStamped::new(
-                    header,
-                    pose::Pose::from_6dof((
-                        values[0], values[1], values[2], // X, Y, Z
-                        values[3], values[4], values[5], // Roll, Pitch, Yaw
-                    )),
-                )
-            })
-            .collect();
+        let (map_section, traj_str) = split_uvt(&content)?;
+        let vtk_file = parse_map_section(map_section, fpath.parent())?;
+        let trajectory = parse_trajectory_section(traj_str)?;
 
         Ok(Self {
             map: vtk_file,
-            trajectory: trajectory,
+            trajectory,
         })
     }
 
-    /// Retrieves messages for a given topic from a ROS bag.
+    /// Read a UVT file from disk, without parsing its map.
     ///
-    /// This internal method extracts messages that match a specified topic.
+    /// The map section is kept as is, so it can be written back byte for byte
+    /// with [`LazyUvt::write_file`] and only parsed when needed with [`LazyUvt::map_parsed`].
     ///
     /// # Arguments
     ///
-    /// * `bag` - A reference to a `RosBag` instance.
-    /// * `topic` - The name of the topic for which to retrieve messages.
+    /// * `path` - A path to the UVT file.
     ///
-    /// # Returns
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
     ///
-    /// A vector of message data as byte vectors.
-    fn retrieve_topic_messages<'a>(bag: &'a RosBag, topic: &str) -> Vec<Vec<u8>> {
-        let connections: Vec<_> = bag
-            .index_records()
-            .filter_map(Result::ok)
-            .filter_map(|record| match record {
-                IndexRecord::Connection(conn) => Some(conn),
-                _ => None,
-            })
-            .collect();
-
-        let topic_conns: Vec<_> = connections
-            .iter()
-            .filter(|conn| conn.topic == topic)
-            .collect();
-
-        let conn_id = topic_conns[0].id;
+    /// let lazy = Uvt::read_file_lazy("my_file.uvt").unwrap();
+    /// println!("{} poses", lazy.trajectory.len());
+    /// lazy.write_file("copy.uvt").unwrap();
+    /// ```
+    pub fn read_file_lazy<P: AsRef<path::Path>>(path: P) -> Result<LazyUvt, UvtError> {
+        let fpath = path.as_ref();
+        let content = fs::read(fpath)?;
 
-        let topic_msgs: Vec<Vec<u8>> = bag
-            .chunk_records()
-            .filter_map(Result::ok)
-            .filter_map(|record| match record {
-                ChunkRecord::Chunk(chunk) => Some(chunk),
-                _ => None,
-            })
-            .map(|chunk| {
-                chunk
-                    .messages()
-                    .filter_map(Result::ok)
-                    .filter_map(|msg| match msg {
-                        MessageRecord::MessageData(msg_data) => Some(msg_data.clone()),
-                        _ => None,
-                    })
-                    .filter(|msg| msg.conn_id == conn_id)
-                    .map(|msg| msg.data.to_vec())
-                    .collect::<Vec<Vec<u8>>>()
-            })
-            .flatten()
-            .collect();
-        topic_msgs
+        let (map_section, traj_str) = split_uvt(&content)?;
+        Ok(LazyUvt {
+            raw_map: map_section.to_vec(),
+            trajectory: parse_trajectory_section(traj_str)?,
+            base_dir: fpath.parent().map(path::Path::to_path_buf),
+        })
     }
 
-    /// Reads a ROS bag file and extracts UVT data.
+    /// Reads a map from a standalone legacy VTK file, in ASCII or binary, into a UVT
+    /// without trajectory.
     ///
-    /// The method reads messages for the map and trajectory topics, parses pointcloud data,
-    /// and constructs a VTK map using the last pointcloud.
+    /// See [`Uvt::with_trajectory_from_csv`] to add a trajectory to the map.
     ///
     /// # Arguments
     ///
-    /// * `path` - A path to the ROS bag file.
-    /// * `map_topic` - The topic name for map messages.
-    /// * `traj_topic` - The topic name for trajectory messages.
+    /// * `path` - A path to the VTK file.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// A `Uvt` instance containing the map and trajectory.
+    /// Returns [`UvtError::Vtk`] if the file is not a legacy VTK file,
+    /// or [`UvtError::Io`] if it cannot be read.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_map_vtk("map.vtk").unwrap();
+    /// ```
+    pub fn read_map_vtk<P: AsRef<path::Path>>(path: P) -> Result<Self, UvtError> {
+        let content = fs::read(path)?;
+        let map = Vtk::parse_legacy_be(content.as_slice())
+            .map_err(|e| UvtError::Vtk(format!("Failed to parse vtk: {}", e)))?;
+        Ok(Self::new(map, vec![]))
+    }
+
+    /// Reads a map from a standalone PLY file into a UVT without trajectory.
+    ///
+    /// The `ascii`, `binary_little_endian` and `binary_big_endian` formats are supported.
+    /// The first comment of the header, if any, is used as the title of the map, and an
+    /// `intensity` vertex property is kept as the `intensity` point data of the map.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the PLY file.
     ///
     /// # Errors
     ///
-    /// Returns an error if the ROS bag file cannot be read or parsed.
+    /// Returns an `InvalidData` I/O error if the file is malformed,
+    /// or if its vertices have no `x`, `y` or `z` property.
     ///
     /// # Example
     /// ```no_run
-    /// let uvt = Uvt::read_rosbag("my_file.bag", "/map", "/odom").unwrap();
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_map_ply("map.ply").unwrap();
     /// ```
-    pub fn read_rosbag<P: AsRef<path::Path>>(
-        path: P,
-        map_topic: &str,
-        traj_topic: &str,
-    ) -> Result<Self, Error> {
-        let absolute_path = path::absolute(&path).unwrap();
-
-        println!("Reading rosbag file in {}", absolute_path.clone().display());
-
-        let fname = absolute_path.file_name().unwrap().to_str().unwrap();
-
-        let bag = RosBag::new(path)?;
-
-        let map_msgs = Self::retrieve_topic_messages(&bag, map_topic);
-        let traj_msgs = Self::retrieve_topic_messages(&bag, traj_topic);
+    pub fn read_map_ply<P: AsRef<path::Path>>(path: P) -> Result<Self, UvtError> {
+        let content = fs::read(path.as_ref())?;
+        let (cloud, comment) = map_file::parse_ply(&content)?;
+        let title = comment
+            .unwrap_or_else(|| format!("UVT file generated from {}", path.as_ref().display()));
+        Ok(Self::new(cloud_map(&cloud, title)?, vec![]))
+    }
 
-        // Collect maps and trajectory
-        let maps: Vec<pointcloud::PointCloud2> = map_msgs
-            .iter()
-            .tqdm()
-            .desc(Some("Reading map msgs"))
-            .map(|msg| {
-                pointcloud::parse_pointcloud::<bag::BagDeserializer>(bag::BagDeserializer::new(
-                    msg.to_vec(),
-                ))
-                .unwrap()
-            })
-            .collect();
-        let trajectory: Vec<pose::PoseStamped> = traj_msgs
-            .iter()
-            .tqdm()
-            .desc(Some("Reading trajectory msgs"))
-            .map(|msg| {
-                trajectory::parse_trajectory::<bag::BagDeserializer>(bag::BagDeserializer::new(
-                    msg.to_vec(),
-                ))
-                .unwrap()
+    /// Reads a map from a standalone PCD file into a UVT without trajectory.
+    ///
+    /// The `ascii` and `binary` data formats are supported. When the file has an
+    /// `intensity` field, it is kept as the `intensity` point data of the map.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the PCD file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` I/O error if the file is malformed, or if it has no `x`, `y`
+    /// or `z` field, and an `Unsupported` I/O error for `binary_compressed` data.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_map_pcd("map.pcd").unwrap();
+    /// ```
+    pub fn read_map_pcd<P: AsRef<path::Path>>(path: P) -> Result<Self, UvtError> {
+        let content = fs::read(path.as_ref())?;
+        let cloud = map_file::parse_pcd(&content)?;
+        let map = cloud_map(
+            &cloud,
+            format!("UVT file generated from {}", path.as_ref().display()),
+        )?;
+        Ok(Self::new(map, vec![]))
+    }
+
+    /// Replaces the trajectory with the one of a CSV file, as written by
+    /// [`Uvt::write_trajectory_csv`], with poses expressed in `frame_id`.
+    ///
+    /// Columns are matched by the names of the header row. The `x`, `y`, `z`, `qx`, `qy`,
+    /// `qz` and `qw` columns are required. Without `seq` column, poses are numbered from 0,
+    /// and without `sec` and `nanosec` columns, they are not stamped.
+    /// Orientations that cannot be normalized are replaced by the identity, see
+    /// [`Uvt::with_trajectory_from_csv_with_options`] to be warned about them.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the CSV file.
+    /// * `frame_id` - The frame in which the poses are expressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`UvtError::Parse`] error if a row of the file is malformed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_map_vtk("map.vtk")
+    ///     .unwrap()
+    ///     .with_trajectory_from_csv("trajectory.csv", "map")
+    ///     .unwrap();
+    /// uvt.write_file("mission.uvt").unwrap();
+    /// ```
+    pub fn with_trajectory_from_csv<P: AsRef<path::Path>>(
+        self,
+        path: P,
+        frame_id: &str,
+    ) -> Result<Self, UvtError> {
+        self.with_trajectory_from_csv_with_options(path, frame_id, &ReadOptions::default())
+    }
+
+    /// Replaces the trajectory with the one of a CSV file, with reading options.
+    ///
+    /// See [`Uvt::with_trajectory_from_csv`]. Orientations that cannot be normalized are
+    /// replaced by the identity, and reported to [`ReadOptions::on_warning`]. The other
+    /// options only apply to rosbags.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the CSV file.
+    /// * `frame_id` - The frame in which the poses are expressed.
+    /// * `options` - Options controlling the reading.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{ReadOptions, Uvt};
+    ///
+    /// let options = ReadOptions {
+    ///     on_warning: Some(|message| eprintln!("Warning: {}", message)),
+    ///     ..Default::default()
+    /// };
+    /// let uvt = Uvt::read_map_vtk("map.vtk")
+    ///     .unwrap()
+    ///     .with_trajectory_from_csv_with_options("trajectory.csv", "map", &options)
+    ///     .unwrap();
+    /// ```
+    pub fn with_trajectory_from_csv_with_options<P: AsRef<path::Path>>(
+        mut self,
+        path: P,
+        frame_id: &str,
+        options: &ReadOptions,
+    ) -> Result<Self, UvtError> {
+        let csv = fs::read_to_string(path)?;
+        self.trajectory = parse_trajectory_csv(&csv, frame_id, options)?;
+        Ok(self)
+    }
+
+    /// Retrieves messages for a given topic from a ROS bag.
+    ///
+    /// This internal method extracts messages that match a specified topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `bag` - A reference to a `RosBag` instance.
+    /// * `topic` - The name of the topic for which to retrieve messages.
+    ///
+    /// # Returns
+    ///
+    /// A vector of message data as byte vectors, or a [`UvtError::MissingTopic`] error
+    /// if the bag has no connection on `topic`.
+    fn retrieve_topic_messages(bag: &RosBag, topic: &str) -> Result<Vec<Vec<u8>>, UvtError> {
+        let mut topic_msgs = Vec::new();
+        Self::for_each_topic_message(bag, &[topic], |_, data| {
+            topic_msgs.push(data.to_vec());
+            Ok(())
+        })?;
+        Ok(topic_msgs)
+    }
+
+    /// Calls `f` on each message recorded on one of `topics`, in bag order.
+    ///
+    /// Only one chunk of the bag is decompressed at a time, and the messages are not kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `bag` - A reference to a `RosBag` instance.
+    /// * `topics` - The names of the topics for which to process messages.
+    /// * `f` - A callback, called with the index of the message topic in `topics` and the message data.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`UvtError::MissingTopic`] error if the bag has no connection on one of
+    /// `topics`, or the first error returned by `f`.
+    fn for_each_topic_message(
+        bag: &RosBag,
+        topics: &[&str],
+        mut f: impl FnMut(usize, &[u8]) -> Result<(), UvtError>,
+    ) -> Result<(), UvtError> {
+        let connections: Vec<_> = bag
+            .index_records()
+            .filter_map(Result::ok)
+            .filter_map(|record| match record {
+                IndexRecord::Connection(conn) => Some(conn),
+                _ => None,
             })
             .collect();
 
-        // Retrieve points from pointclouds
-        let pointclouds: Vec<Vec<pose::Point>> =
-            maps.par_iter().map(|m| m.to_owned().into()).collect();
-        println!("Retrieved points from pointclouds");
+        // A topic can be advertised by several connections, e.g. when a publisher restarts
+        let mut conn_topics: HashMap<u32, usize> = HashMap::new();
+        for (i, topic) in topics.iter().enumerate() {
+            let conn_ids: Vec<u32> = connections
+                .iter()
+                .filter(|conn| conn.topic == *topic)
+                .map(|conn| conn.id)
+                .collect();
 
-        // Use last pointcloud as the map
-        let last_pcloud = pointclouds[pointclouds.len() - 1].clone();
-        let pts: Vec<f32> = last_pcloud
-            .par_iter()
-            .map(|&pt| Into::<[f32; 3]>::into(pt))
-            .flatten()
-            .collect::<Vec<f32>>()
-            .try_into()
-            .unwrap();
-        let data = vtkio::model::DataSet::inline(vtkio::model::PolyDataPiece {
-            points: vtkio::IOBuffer::F32(pts),
-            verts: None,
-            lines: None,
-            polys: None,
-            strips: None,
-            data: vtkio::model::Attributes::new(),
-        });
+            if conn_ids.is_empty() {
+                return Err(UvtError::MissingTopic {
+                    topic: topic.to_string(),
+                    available: Self::retrieve_topics(bag),
+                });
+            }
+            conn_topics.extend(conn_ids.into_iter().map(|id| (id, i)));
+        }
 
-        let map_vtk = Vtk {
-            version: vtkio::model::Version { major: 3, minor: 0 },
-            byte_order: vtkio::model::ByteOrder::BigEndian,
-            title: String::from(format!("UVT file generated from {}", fname)),
-            file_path: None,
-            data: data,
-        };
+        // Chunks are decompressed while being read, a failure must not silently drop messages
+        for record in bag.chunk_records() {
+            let chunk = match record.map_err(bag_chunk_error)? {
+                ChunkRecord::Chunk(chunk) => chunk,
+                _ => continue,
+            };
+            for msg in chunk.messages() {
+                if let MessageRecord::MessageData(msg_data) = msg.map_err(bag_chunk_error)?
+                    && let Some(&i) = conn_topics.get(&msg_data.conn_id)
+                {
+                    f(i, msg_data.data)?;
+                }
+            }
+        }
+        Ok(())
+    }
 
-        Ok(Self {
-            map: map_vtk,
-            trajectory: trajectory,
-        })
+    /// Retrieves the names of all topics of a ROS bag.
+    fn retrieve_topics(bag: &RosBag) -> Vec<String> {
+        let mut topics: Vec<String> = bag
+            .index_records()
+            .filter_map(Result::ok)
+            .filter_map(|record| match record {
+                IndexRecord::Connection(conn) => Some(conn.topic.to_string()),
+                _ => None,
+            })
+            .collect();
+        topics.sort();
+        topics.dedup();
+        topics
     }
 
-    /// Retrieves messages for a given topic from an MCAP file.
+    /// Retrieves the message type of a given topic from a ROS bag.
     ///
-    /// This internal method reads an MCAP memory-mapped file and extracts the messages
-    /// matching the specified topic.
+    /// # Arguments
+    ///
+    /// * `bag` - A reference to a `RosBag` instance.
+    /// * `topic` - The name of the topic.
+    ///
+    /// # Returns
+    ///
+    /// The message type (e.g. `sensor_msgs/PointCloud2`), or `None` if the topic is absent.
+    fn retrieve_topic_type(bag: &RosBag, topic: &str) -> Option<String> {
+        bag.index_records()
+            .filter_map(Result::ok)
+            .find_map(|record| match record {
+                IndexRecord::Connection(conn) if conn.topic == topic => Some(conn.tp.to_string()),
+                _ => None,
+            })
+    }
+
+    /// Lists the topics of a ROS bag file along with their message types.
     ///
     /// # Arguments
     ///
-    /// * `mcap_map` - A memory-mapped representation of an MCAP file.
-    /// * `topic` - The name of the topic for which to retrieve messages.
+    /// * `path` - A path to the ROS bag file.
     ///
     /// # Returns
     ///
-    /// A vector of message data as byte vectors.
-    fn retrieve_mcap_topic_messages<'a>(mcap_map: &Mmap, topic: &str) -> Vec<Vec<u8>> {
-        let messages = mcap_crate::MessageStream::new(&mcap_map).unwrap();
-        let topic_msgs = messages
-            .into_iter()
-            .filter_map(|stream_msg| {
-                let msg = stream_msg.unwrap();
-                let msg_topic = msg.channel.topic.as_str();
-                if msg_topic == topic {
-                    Some(msg.data.to_vec())
-                } else {
-                    None
+    /// The `(topic, type)` pairs of the bag connections, sorted by topic name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ROS bag file cannot be read.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// for (topic, msg_type) in Uvt::list_rosbag_topics("my_file.bag").unwrap() {
+    ///     println!("{} ({})", topic, msg_type);
+    /// }
+    /// ```
+    pub fn list_rosbag_topics<P: AsRef<path::Path>>(
+        path: P,
+    ) -> Result<Vec<(String, String)>, UvtError> {
+        let bag = RosBag::new(path)?;
+        let mut topics: Vec<(String, String)> = bag
+            .index_records()
+            .filter_map(Result::ok)
+            .filter_map(|record| match record {
+                IndexRecord::Connection(conn) => {
+                    Some((conn.topic.to_string(), conn.tp.to_string()))
                 }
+                _ => None,
             })
             .collect();
-        topic_msgs
+        topics.sort();
+        topics.dedup();
+        Ok(topics)
     }
 
-    /// Reads an MCAP file and extracts UVT data.
+    /// Reads a ROS bag file and extracts UVT data.
     ///
     /// The method reads messages for the map and trajectory topics, parses pointcloud data,
     /// and constructs a VTK map using the last pointcloud.
     ///
     /// # Arguments
     ///
-    /// * `path` - A path to the MCAP file.
+    /// * `path` - A path to the ROS bag file.
     /// * `map_topic` - The topic name for map messages.
     /// * `traj_topic` - The topic name for trajectory messages.
     ///
@@ -354,146 +1288,4276 @@ impl Uvt {
     ///
     /// # Errors
     ///
-    /// Returns an error if the MCAP file cannot be read or parsed.
+    /// Returns an error if the ROS bag file cannot be read or parsed.
     ///
     /// # Example
     /// ```no_run
-    /// let uvt = Uvt::read_mcap("my_file.mcap", "/map", "/odom").unwrap();
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_rosbag("my_file.bag", "/map", "/odom").unwrap();
     /// ```
-    pub fn read_mcap<P: AsRef<path::Path>>(
+    pub fn read_rosbag<P: AsRef<path::Path>>(
         path: P,
         map_topic: &str,
         traj_topic: &str,
-    ) -> Result<Self, Error> {
-        let absolute_path = path::absolute(&path).unwrap();
-        println!("Reading MCAP file in {}", absolute_path.clone().display());
+    ) -> Result<Self, UvtError> {
+        Self::read_rosbag_with_options(path, map_topic, traj_topic, &ReadOptions::default())
+    }
 
-        let fname = absolute_path.file_name().unwrap().to_str().unwrap();
+    /// Reads a ROS bag file and extracts UVT data, with extraction options.
+    ///
+    /// See [`Uvt::read_rosbag`] and [`ReadOptions`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the ROS bag file.
+    /// * `map_topic` - The topic name for map messages.
+    /// * `traj_topic` - The topic name for trajectory messages.
+    /// * `options` - Options controlling the extraction.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{Point, ReadOptions, Uvt};
+    ///
+    /// let options = ReadOptions {
+    ///     spatial_filter: Some((Point::new(-50.0, -50.0, -5.0), Point::new(50.0, 50.0, 5.0))),
+    ///     ..Default::default()
+    /// };
+    /// let uvt = Uvt::read_rosbag_with_options("my_file.bag", "/map", "/odom", &options).unwrap();
+    /// ```
+    pub fn read_rosbag_with_options<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+        options: &ReadOptions,
+    ) -> Result<Self, UvtError> {
+        Self::read_rosbag_impl(path, map_topic, traj_topic, options, &mut tqdm_progress())
+    }
 
-        let fd = fs::File::open(path.as_ref()).expect("Couldn't open MCAP file");
-        let mapped = unsafe { Mmap::map(&fd) }?;
-        println!("MCAP file opened !");
+    /// Reads a ROS bag file and extracts UVT data, reporting the progress to a callback
+    /// instead of printing it.
+    ///
+    /// See [`Uvt::read_rosbag`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the ROS bag file.
+    /// * `map_topic` - The topic name for map messages.
+    /// * `traj_topic` - The topic name for trajectory messages.
+    /// * `progress` - A callback, called with a [`ProgressEvent`] for each processed message.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_rosbag_with_progress("my_file.bag", "/map", "/odom", |event| {
+    ///     eprintln!("{}: {}/{}", event.phase, event.current, event.total);
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn read_rosbag_with_progress<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+        mut progress: impl FnMut(ProgressEvent),
+    ) -> Result<Self, UvtError> {
+        let options = ReadOptions::default();
+        Self::read_rosbag_impl(path, map_topic, traj_topic, &options, &mut progress)
+    }
 
-        let map_msgs = Self::retrieve_mcap_topic_messages(&mapped, map_topic);
-        let traj_msgs = Self::retrieve_mcap_topic_messages(&mapped, traj_topic);
+    fn read_rosbag_impl<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+        options: &ReadOptions,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Self, UvtError> {
+        check_read_options(options)?;
+        let absolute_path = path::absolute(&path)?;
+        let fname = file_name(&absolute_path);
 
-        // Collect maps and trajectory
-        let maps: Vec<pointcloud::PointCloud2> = map_msgs
-            .iter()
-            .tqdm()
-            .desc(Some("Reading map msgs"))
-            .map(|msg| {
-                pointcloud::parse_pointcloud::<mcap::McapDeserializer>(mcap::McapDeserializer::new(
-                    msg.to_vec(),
-                ))
-                .unwrap()
-            })
-            .collect();
-        let trajectory: Vec<pose::PoseStamped> = traj_msgs
-            .iter()
-            .tqdm()
-            .desc(Some("Reading trajectory msgs"))
-            .map(|msg| {
-                trajectory::parse_trajectory::<mcap::McapDeserializer>(mcap::McapDeserializer::new(
-                    msg.to_vec(),
-                ))
-                .unwrap()
-            })
-            .collect();
+        let bag = RosBag::new(path)?;
 
-        // Retrieve points from pointclouds
-        let pointclouds: Vec<Vec<pose::Point>> =
-            maps.par_iter().map(|m| m.to_owned().into()).collect();
-        println!("Retrieved points from pointclouds");
+        let map_msgs = Self::retrieve_topic_messages(&bag, map_topic)?;
+        let traj_msgs = Self::retrieve_topic_messages(&bag, traj_topic)?;
+        if let Some(warning) = check_topic_counts(
+            (map_topic, map_msgs.len()),
+            (traj_topic, traj_msgs.len()),
+            || Self::retrieve_topics(&bag),
+            options,
+        )? {
+            options.warn(&warning);
+        }
+        let map_type = Self::retrieve_topic_type(&bag, map_topic);
 
-        // Use last pointcloud as the map
-        let last_pcloud = pointclouds[pointclouds.len() - 1].clone();
-        let pts: Vec<f32> = last_pcloud
-            .par_iter()
-            .map(|&pt| Into::<[f32; 3]>::into(pt))
-            .flatten()
-            .collect::<Vec<f32>>()
-            .try_into()
-            .unwrap();
-        let data = vtkio::model::DataSet::inline(vtkio::model::PolyDataPiece {
-            points: vtkio::IOBuffer::F32(pts),
-            verts: None,
-            lines: None,
-            polys: None,
-            strips: None,
-            data: vtkio::model::Attributes::new(),
-        });
+        // Collect maps and trajectory
+        let maps: Vec<pointcloud::PointCloud2> =
+            report_progress(&map_msgs, ProgressPhase::ReadingMap, progress)
+                .map(|msg| {
+                    let d = bag::BagDeserializer::new(msg.to_vec());
+                    parse_map(d, is_legacy_pointcloud(map_type.as_deref()), options)
+                })
+                .collect::<Result<_, _>>()?;
+        let mut trajectory: Vec<pose::PoseStamped> = parse_messages(
+            &traj_msgs,
+            ProgressPhase::ReadingTrajectory,
+            progress,
+            |msg| {
+                let d = bag::BagDeserializer::new(msg.to_vec());
+                trajectory::parse_poses(d, options.trajectory_type)
+            },
+        )?;
+        let replaced = trajectory::check_orientations(&mut trajectory);
+        if let Some(warning) = replaced_orientations_warning(replaced) {
+            options.warn(&warning);
+        }
 
-        let map_vtk = Vtk {
-            version: vtkio::model::Version { major: 3, minor: 0 },
-            byte_order: vtkio::model::ByteOrder::BigEndian,
-            title: String::from(format!("UVT file generated from {}", fname)),
-            file_path: None,
-            data: data,
-        };
+        let map_vtk = Self::build_map(&maps, &fname, options, progress)?;
 
         Ok(Self {
             map: map_vtk,
-            trajectory: trajectory,
+            trajectory,
         })
     }
 
-    /// Writes the UVT data (map and trajectory) to a file.
+    /// Reads a ROS bag file one message at a time, in constant memory regardless of its size.
     ///
-    /// The output file contains a VTK map encoded in legacy ASCII format,
-    /// followed by a delimiter and the trajectory data.
+    /// Unlike [`Uvt::read_rosbag`], the trajectory is not kept: each stamped pose is given to
+    /// `on_pose` as soon as its message is parsed, in bag order. Map messages are parsed one at
+    /// a time too, and only the cloud selected by `keep_map` is retained to build the map.
     ///
     /// # Arguments
     ///
-    /// * `path` - The destination file path.
+    /// * `path` - A path to the ROS bag file.
+    /// * `map_topic` - The topic name for map messages.
+    /// * `traj_topic` - The topic name for trajectory messages.
+    /// * `options` - Options controlling the extraction. The map options apply to the retained cloud.
+    /// * `keep_map` - A predicate, called on each map cloud in bag order, once cropped to the
+    ///   spatial filter of `options`. A cloud replaces the retained one when it returns `true`,
+    ///   so `|_| true` keeps the last cloud.
+    /// * `on_pose` - A callback, called with each stamped pose of the trajectory.
     ///
     /// # Returns
     ///
-    /// `Ok(())` if the file was written successfully, or an `Error` otherwise.
-    pub fn write_file<P: AsRef<path::Path>>(&self, path: P) -> Result<(), std::io::Error> {
-        let export_path = path::absolute(path)?.clone();
-        println!("Writing file to {}", export_path.display());
-
-        //
-        // Map
-        //
-        let mut map_str = String::new();
-        Vtk::write_legacy_ascii(self.map.clone(), &mut map_str)
-            .expect(&format!("Failed to write file"));
-
-        //
-        // Trajectory
-        //
-        let uvt_trajectory = self.trajectory.clone();
-
-        // Retrieve frame ID
-        let frame_id = uvt_trajectory
-            .first()
-            .ok_or(Error::new(ErrorKind::InvalidData, "Missing poses"))?
-            .header
-            .frame_id
-            .clone();
-        let frame_str = format!("frame_id : {}", frame_id);
+    /// The VTK map built from the retained cloud, empty if no cloud was retained.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ROS bag file cannot be read or a message cannot be parsed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{ReadOptions, Uvt};
+    ///
+    /// let mut n_poses = 0;
+    /// let map = Uvt::stream_rosbag(
+    ///     "my_file.bag",
+    ///     "/map",
+    ///     "/odom",
+    ///     &ReadOptions::default(),
+    ///     |_| true,
+    ///     |_| n_poses += 1,
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn stream_rosbag<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+        options: &ReadOptions,
+        mut keep_map: impl FnMut(&pointcloud::PointCloud2) -> bool,
+        mut on_pose: impl FnMut(pose::PoseStamped),
+    ) -> Result<Vtk, UvtError> {
+        check_read_options(options)?;
+        let absolute_path = path::absolute(&path)?;
+        let fname = file_name(&absolute_path);
 
-        let traj_poses: Vec<String> = uvt_trajectory
-            .into_iter()
-            .map(|pose| {
-                let dofs = pose.pose.to_6dof();
-                let x = pose::round(dofs.0, 6);
-                let y = pose::round(dofs.1, 6);
-                let z = pose::round(dofs.2, 6);
-                let roll = pose::round(dofs.3, 6);
-                let pitch = pose::round(dofs.4, 6);
-                let yaw = pose::round(dofs.5, 6);
-
-                format!("{x},{y},{z},{roll},{pitch},{yaw}")
-            })
-            .collect();
-        let traj_str = traj_poses.join("\n");
-        let uvt_str = vec![map_str, TRAJ_DELIM.to_string(), frame_str, traj_str].join("\n");
+        let bag = RosBag::new(path)?;
+        let map_type = Self::retrieve_topic_type(&bag, map_topic);
 
-        fs::write(export_path, uvt_str)?;
+        let mut map = None;
+        let mut replaced_orientations = 0;
+        Self::for_each_topic_message(&bag, &[map_topic, traj_topic], |i, data| {
+            let d = bag::BagDeserializer::new(data.to_vec());
+            if i == 0 {
+                let cloud = parse_map(d, is_legacy_pointcloud(map_type.as_deref()), options)?;
+                if keep_map(&cloud) {
+                    map = Some(cloud);
+                }
+            } else {
+                let mut poses = trajectory::parse_poses(d, options.trajectory_type)?;
+                replaced_orientations += trajectory::check_orientations(&mut poses);
+                poses.into_iter().for_each(&mut on_pose);
+            }
+            Ok(())
+        })?;
+        if let Some(warning) = replaced_orientations_warning(replaced_orientations) {
+            options.warn(&warning);
+        }
 
-        Ok(())
+        let maps: Vec<pointcloud::PointCloud2> = map.into_iter().collect();
+        Self::build_map(&maps, &fname, options, &mut |_| {})
+    }
+
+    /// Reads the TF transforms recorded in a ROS bag file.
+    ///
+    /// The method parses every `tf2_msgs/TFMessage` published on `/tf_static` and `/tf`,
+    /// in that order. Topics that are absent from the bag are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the ROS bag file.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `(stamp, parent frame, child frame, transform)` tuples.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ROS bag file cannot be read or a TF message cannot be parsed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let transforms = Uvt::read_transforms_rosbag("my_file.bag").unwrap();
+    /// ```
+    pub fn read_transforms_rosbag<P: AsRef<path::Path>>(
+        path: P,
+    ) -> Result<Vec<(pose::Time, String, String, pose::Pose)>, UvtError> {
+        let bag = RosBag::new(path)?;
+
+        let topics: Vec<String> = bag
+            .index_records()
+            .filter_map(Result::ok)
+            .filter_map(|record| match record {
+                IndexRecord::Connection(conn) => Some(conn.topic.to_string()),
+                _ => None,
+            })
+            .collect();
+
+        let tf_msgs: Vec<Vec<Vec<u8>>> = TF_TOPICS
+            .iter()
+            .filter(|&&topic| topics.iter().any(|t| t == topic))
+            .map(|topic| Self::retrieve_topic_messages(&bag, topic))
+            .collect::<Result<_, _>>()?;
+        let transforms: Vec<Vec<tf::StampedTransform>> = tf_msgs
+            .into_iter()
+            .flatten()
+            .map(|msg| tf::parse_transforms(bag::BagDeserializer::new(msg)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(transforms.into_iter().flatten().collect())
+    }
+
+    /// Retrieves messages for a given topic from an MCAP file.
+    ///
+    /// This internal method reads an MCAP memory-mapped file and extracts the messages
+    /// matching the specified topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `mcap_map` - A memory-mapped representation of an MCAP file.
+    /// * `topic` - The name of the topic for which to retrieve messages.
+    ///
+    /// # Returns
+    ///
+    /// A vector of message data as byte vectors, empty if no message was recorded on `topic`.
+    fn retrieve_mcap_topic_messages(
+        mcap_map: &Mmap,
+        topic: &str,
+    ) -> Result<Vec<Vec<u8>>, UvtError> {
+        let messages = mcap_crate::MessageStream::new(mcap_map).map_err(mcap_error)?;
+
+        let mut topic_msgs = Vec::new();
+        for stream_msg in messages {
+            let msg = stream_msg.map_err(mcap_error)?;
+            if msg.channel.topic == topic {
+                topic_msgs.push(msg.data.to_vec());
+            }
+        }
+        Ok(topic_msgs)
+    }
+
+    /// Retrieves the names of all topics of an MCAP file.
+    fn retrieve_mcap_topics(mcap_map: &Mmap) -> Vec<String> {
+        let mut topics: Vec<String> = mcap_crate::MessageStream::new(mcap_map)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|msg| msg.channel.topic.clone())
+            .collect();
+        topics.sort();
+        topics.dedup();
+        topics
+    }
+
+    /// Retrieves the message type of a given topic from an MCAP file.
+    ///
+    /// # Arguments
+    ///
+    /// * `mcap_map` - A memory-mapped representation of an MCAP file.
+    /// * `topic` - The name of the topic.
+    ///
+    /// # Returns
+    ///
+    /// The schema name (e.g. `sensor_msgs/msg/PointCloud2`), or `None` if the topic has no messages.
+    fn retrieve_mcap_topic_type(mcap_map: &Mmap, topic: &str) -> Option<String> {
+        mcap_crate::MessageStream::new(mcap_map)
+            .ok()?
+            .filter_map(Result::ok)
+            .find(|msg| msg.channel.topic == topic)
+            .and_then(|msg| {
+                msg.channel
+                    .schema
+                    .as_ref()
+                    .map(|schema| schema.name.clone())
+            })
+    }
+
+    /// Lists the topics of an MCAP file along with their schema names.
+    ///
+    /// The channels are taken from the summary section of the file, or from its messages
+    /// if the file has no summary.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the MCAP file.
+    ///
+    /// # Returns
+    ///
+    /// The `(topic, schema)` pairs of the MCAP channels, sorted by topic name.
+    /// Channels without a schema have an empty schema name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the MCAP file cannot be read.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// for (topic, schema) in Uvt::list_mcap_topics("my_file.mcap").unwrap() {
+    ///     println!("{} ({})", topic, schema);
+    /// }
+    /// ```
+    pub fn list_mcap_topics<P: AsRef<path::Path>>(
+        path: P,
+    ) -> Result<Vec<(String, String)>, UvtError> {
+        let fd = fs::File::open(path)?;
+        let mapped = unsafe { Mmap::map(&fd) }?;
+
+        let channel_topic = |channel: &mcap_crate::Channel| {
+            let schema = channel
+                .schema
+                .as_ref()
+                .map(|schema| schema.name.clone())
+                .unwrap_or_default();
+            (channel.topic.clone(), schema)
+        };
+        let summary = mcap_crate::Summary::read(&mapped).map_err(mcap_error)?;
+        let mut topics: Vec<(String, String)> = match summary {
+            Some(summary) => summary
+                .channels
+                .values()
+                .map(|channel| channel_topic(channel))
+                .collect(),
+            None => mcap_crate::MessageStream::new(&mapped)
+                .map_err(mcap_error)?
+                .filter_map(Result::ok)
+                .map(|msg| channel_topic(&msg.channel))
+                .collect(),
+        };
+        topics.sort();
+        topics.dedup();
+        Ok(topics)
+    }
+
+    /// Reads an MCAP file and extracts UVT data.
+    ///
+    /// The method reads messages for the map and trajectory topics, parses pointcloud data,
+    /// and constructs a VTK map using the last pointcloud.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the MCAP file.
+    /// * `map_topic` - The topic name for map messages.
+    /// * `traj_topic` - The topic name for trajectory messages.
+    ///
+    /// # Returns
+    ///
+    /// A `Uvt` instance containing the map and trajectory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the MCAP file cannot be read or parsed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_mcap("my_file.mcap", "/map", "/odom").unwrap();
+    /// ```
+    pub fn read_mcap<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+    ) -> Result<Self, UvtError> {
+        Self::read_mcap_with_options(path, map_topic, traj_topic, &ReadOptions::default())
+    }
+
+    /// Reads an MCAP file and extracts UVT data, with extraction options.
+    ///
+    /// See [`Uvt::read_mcap`] and [`ReadOptions`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the MCAP file.
+    /// * `map_topic` - The topic name for map messages.
+    /// * `traj_topic` - The topic name for trajectory messages.
+    /// * `options` - Options controlling the extraction.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{Point, ReadOptions, Uvt};
+    ///
+    /// let options = ReadOptions {
+    ///     spatial_filter: Some((Point::new(-50.0, -50.0, -5.0), Point::new(50.0, 50.0, 5.0))),
+    ///     ..Default::default()
+    /// };
+    /// let uvt = Uvt::read_mcap_with_options("my_file.mcap", "/map", "/odom", &options).unwrap();
+    /// ```
+    pub fn read_mcap_with_options<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+        options: &ReadOptions,
+    ) -> Result<Self, UvtError> {
+        Self::read_mcap_impl(path, map_topic, traj_topic, options, &mut tqdm_progress())
+    }
+
+    /// Reads an MCAP file and extracts UVT data, reporting the progress to a callback
+    /// instead of printing it.
+    ///
+    /// See [`Uvt::read_mcap`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the MCAP file.
+    /// * `map_topic` - The topic name for map messages.
+    /// * `traj_topic` - The topic name for trajectory messages.
+    /// * `progress` - A callback, called with a [`ProgressEvent`] for each processed message.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_mcap_with_progress("my_file.mcap", "/map", "/odom", |event| {
+    ///     eprintln!("{}: {}/{}", event.phase, event.current, event.total);
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn read_mcap_with_progress<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+        mut progress: impl FnMut(ProgressEvent),
+    ) -> Result<Self, UvtError> {
+        let options = ReadOptions::default();
+        Self::read_mcap_impl(path, map_topic, traj_topic, &options, &mut progress)
+    }
+
+    fn read_mcap_impl<P: AsRef<path::Path>>(
+        path: P,
+        map_topic: &str,
+        traj_topic: &str,
+        options: &ReadOptions,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Self, UvtError> {
+        check_read_options(options)?;
+        let absolute_path = path::absolute(&path)?;
+        let fname = file_name(&absolute_path);
+
+        let fd = fs::File::open(path.as_ref())?;
+        let mapped = unsafe { Mmap::map(&fd) }?;
+
+        let map_msgs = Self::retrieve_mcap_topic_messages(&mapped, map_topic)?;
+        let traj_msgs = Self::retrieve_mcap_topic_messages(&mapped, traj_topic)?;
+        if let Some(warning) = check_topic_counts(
+            (map_topic, map_msgs.len()),
+            (traj_topic, traj_msgs.len()),
+            || Self::retrieve_mcap_topics(&mapped),
+            options,
+        )? {
+            options.warn(&warning);
+        }
+        let map_type = Self::retrieve_mcap_topic_type(&mapped, map_topic);
+
+        // Collect maps and trajectory
+        let maps: Vec<pointcloud::PointCloud2> =
+            report_progress(&map_msgs, ProgressPhase::ReadingMap, progress)
+                .map(|msg| {
+                    let d = mcap::McapDeserializer::new(msg.to_vec());
+                    parse_map(d, is_legacy_pointcloud(map_type.as_deref()), options)
+                })
+                .collect::<Result<_, _>>()?;
+        let mut trajectory: Vec<pose::PoseStamped> = parse_messages(
+            &traj_msgs,
+            ProgressPhase::ReadingTrajectory,
+            progress,
+            |msg| {
+                let d = mcap::McapDeserializer::new(msg.to_vec());
+                trajectory::parse_poses(d, options.trajectory_type)
+            },
+        )?;
+        let replaced = trajectory::check_orientations(&mut trajectory);
+        if let Some(warning) = replaced_orientations_warning(replaced) {
+            options.warn(&warning);
+        }
+
+        let map_vtk = Self::build_map(&maps, &fname, options, progress)?;
+
+        Ok(Self {
+            map: map_vtk,
+            trajectory,
+        })
+    }
+
+    /// Reads the TF transforms recorded in an MCAP file.
+    ///
+    /// The method parses every `tf2_msgs/msg/TFMessage` published on `/tf_static` and `/tf`,
+    /// in that order.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the MCAP file.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `(stamp, parent frame, child frame, transform)` tuples.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the MCAP file cannot be read or a TF message cannot be parsed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let transforms = Uvt::read_transforms_mcap("my_file.mcap").unwrap();
+    /// ```
+    pub fn read_transforms_mcap<P: AsRef<path::Path>>(
+        path: P,
+    ) -> Result<Vec<(pose::Time, String, String, pose::Pose)>, UvtError> {
+        let fd = fs::File::open(path.as_ref())?;
+        let mapped = unsafe { Mmap::map(&fd) }?;
+
+        let tf_msgs: Vec<Vec<Vec<u8>>> = TF_TOPICS
+            .iter()
+            .map(|topic| Self::retrieve_mcap_topic_messages(&mapped, topic))
+            .collect::<Result<_, _>>()?;
+        let transforms: Vec<Vec<tf::StampedTransform>> = tf_msgs
+            .into_iter()
+            .flatten()
+            .map(|msg| tf::parse_transforms(mcap::McapDeserializer::new(msg)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(transforms.into_iter().flatten().collect())
+    }
+
+    /// Builds the VTK map from the parsed map pointclouds.
+    ///
+    /// The last pointcloud is used as the map, unless `options.merge_leaf_size` is set,
+    /// in which case every pointcloud is merged into a voxel grid one at a time, or
+    /// `options.accumulate_maps` is set, in which case every pointcloud is concatenated.
+    /// Without any pointcloud, the map is empty.
+    /// Points with a non-finite coordinate, such as the NaN placeholders of invalid returns,
+    /// are dropped. The pointclouds are expected to be already cropped to the spatial filter
+    /// of `options`, see [`parse_map`].
+    /// When the map cloud has a `ring` field, it is kept as the `ring` point data of the map,
+    /// and when it has a packed `rgb` or `rgba` field, it is kept as the `rgba` color scalars.
+    /// The progress of the merging of several pointclouds is reported to `progress`.
+    fn build_map(
+        maps: &[pointcloud::PointCloud2],
+        fname: &str,
+        options: &ReadOptions,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Vtk, Error> {
+        // Retrieve points, and their scan ring and color if any, from a pointcloud
+        let cloud_points = |m: &pointcloud::PointCloud2| -> Result<MapPoints, Error> {
+            let has_ring = m.fields.iter().any(|f| f.name == RING_FIELD);
+            let colors = m.colors();
+            let mut cloud = MapPoints {
+                rings: has_ring.then(Vec::new),
+                colors: colors.as_ref().map(|_| Vec::new()),
+                ..Default::default()
+            };
+            for (i, pt) in m.points()?.iter().enumerate() {
+                let point = Point::new(pt["x"], pt["y"], pt["z"]);
+                if !point.is_finite() {
+                    continue;
+                }
+                cloud.points.push(point);
+                if let Some(rings) = &mut cloud.rings {
+                    rings.push(pt.get(RING_FIELD).map_or(0, |&r| r as u16));
+                }
+                if let (Some(cloud_colors), Some(colors)) = (&mut cloud.colors, &colors) {
+                    cloud_colors.push(colors[i]);
+                }
+            }
+            Ok(cloud)
+        };
+
+        let MapPoints {
+            points,
+            rings,
+            colors,
+        } = match options.merge_leaf_size {
+            None if options.accumulate_maps => {
+                let mut seen: HashSet<[u64; 3]> = HashSet::new();
+                let mut points = Vec::new();
+                let mut rings = Vec::new();
+                let mut colors = Vec::new();
+                let mut all_ringed = !maps.is_empty();
+                let mut all_colored = !maps.is_empty();
+                for m in report_progress(maps, ProgressPhase::AccumulatingMap, progress) {
+                    let cloud = cloud_points(m)?;
+                    all_ringed &= cloud.rings.is_some();
+                    all_colored &= cloud.colors.is_some();
+                    for (i, pt) in cloud.points.iter().enumerate() {
+                        if seen.insert([pt.x.to_bits(), pt.y.to_bits(), pt.z.to_bits()]) {
+                            points.push(*pt);
+                            rings.push(cloud.rings.as_ref().map_or(0, |r| r[i]));
+                            colors.push(cloud.colors.as_ref().map_or([0; 4], |c| c[i]));
+                        }
+                    }
+                }
+                MapPoints {
+                    points,
+                    rings: all_ringed.then_some(rings),
+                    colors: all_colored.then_some(colors),
+                }
+            }
+            Some(leaf_size) => {
+                let mut grid = pointcloud::VoxelGrid::new(leaf_size)?;
+                for m in report_progress(maps, ProgressPhase::MergingMap, progress) {
+                    grid.insert(cloud_points(m)?.points);
+                }
+                // Voxel centroids mix several rings and colors
+                MapPoints {
+                    points: grid.points(),
+                    ..Default::default()
+                }
+            }
+            // Use last pointcloud as the map
+            None => maps.last().map_or(Ok(MapPoints::default()), cloud_points)?,
+        };
+
+        let mut attributes = vtkio::model::Attributes::new();
+        if let Some(rings) = rings {
+            attributes
+                .point
+                .push(Attribute::scalars(RING_FIELD, 1).with_data(rings));
+        }
+        if let Some(colors) = colors {
+            // Legacy VTK files store ASCII color scalars as floats between 0 and 1
+            let channels: Vec<f32> = colors.iter().flatten().map(|&c| c as f32 / 255.0).collect();
+            attributes
+                .point
+                .push(Attribute::color_scalars(COLOR_FIELD, 4).with_data(channels));
+        }
+
+        Ok(points_vtk(
+            &points,
+            attributes,
+            format!("UVT file generated from {}", fname),
+            options.map_precision,
+        ))
+    }
+
+    /// Writes the UVT data (map and trajectory) to a file.
+    ///
+    /// The output file contains a VTK map encoded in legacy ASCII format,
+    /// followed by a delimiter and the trajectory data, with one
+    /// `x,y,z,roll,pitch,yaw,sec,nanosec` line per pose, rounded to 6 decimals.
+    /// See [`Uvt::write_file_with_options`] to change the output format.
+    /// A map without any point is rejected, see [`WriteOptions::allow_empty_map`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The destination file path.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the file was written successfully, or an `Error` otherwise.
+    pub fn write_file<P: AsRef<path::Path>>(&self, path: P) -> Result<(), UvtError> {
+        self.write_file_with_options(path, &WriteOptions::default())
+    }
+
+    /// Writes the UVT data (map and trajectory) to a file, with the given options.
+    ///
+    /// The output file contains a VTK map encoded in legacy ASCII or binary format,
+    /// depending on `options.map_encoding`, followed by a delimiter and the trajectory data,
+    /// rounded to `options.decimals` decimals.
+    /// [`Uvt::read_file`] reads both encodings.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The destination file path.
+    /// * `options` - Options controlling the output format.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{MapEncoding, Uvt, WriteOptions};
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// let options = WriteOptions {
+    ///     map_encoding: MapEncoding::Binary,
+    ///     ..Default::default()
+    /// };
+    /// uvt.write_file_with_options("compact.uvt", &options).unwrap();
+    /// ```
+    pub fn write_file_with_options<P: AsRef<path::Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<(), UvtError> {
+        if !options.allow_empty_map {
+            self.check_map_not_empty()?;
+        }
+
+        let mut map_bytes = match options.map_encoding {
+            MapEncoding::Ascii => {
+                let mut map_str = String::new();
+                Vtk::write_legacy_ascii(self.map.clone(), &mut map_str)
+                    .map_err(|e| Error::other(format!("Failed to write map: {}", e)))?;
+                map_str.into_bytes()
+            }
+            MapEncoding::Binary => {
+                // The reader expects big-endian binary data
+                let mut map = self.map.clone();
+                map.byte_order = vtkio::model::ByteOrder::BigEndian;
+                let mut map_bytes = Vec::new();
+                map.write_legacy(&mut map_bytes)
+                    .map_err(|e| Error::other(format!("Failed to write map: {}", e)))?;
+                map_bytes
+            }
+        };
+        map_bytes.push(b'\n');
+
+        write_uvt(path, &map_bytes, &self.trajectory, options.decimals)
+    }
+
+    /// Writes the UVT data to a file, with the map saved in a separate sidecar file.
+    ///
+    /// The UVT file only contains a `map_ref : <map_path>` reference, followed by the delimiter
+    /// and the trajectory data. The sidecar format is given by the extension of `map_path`
+    /// (e.g. `.vtk` for legacy VTK, `.vtp` for XML PolyData).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The destination UVT file path.
+    /// * `map_path` - The destination map file path. A relative path is relative to the
+    ///   directory of the UVT file.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// uvt.write_file_with_map_ref("light.uvt", "map.vtp").unwrap();
+    /// ```
+    pub fn write_file_with_map_ref<P: AsRef<path::Path>, Q: AsRef<path::Path>>(
+        &self,
+        path: P,
+        map_path: Q,
+    ) -> Result<(), UvtError> {
+        // Fail before writing the map if the trajectory cannot be written
+        self.trajectory_frame_id()?;
+        self.check_map_not_empty()?;
+
+        let map_ref = map_path.as_ref();
+        let export_path = path::absolute(path.as_ref())?;
+        let sidecar_path = export_path
+            .parent()
+            .unwrap_or(path::Path::new(""))
+            .join(map_ref);
+        self.map.clone().export(&sidecar_path).map_err(|e| {
+            Error::other(format!(
+                "Failed to write map {}: {}",
+                sidecar_path.display(),
+                e
+            ))
+        })?;
+
+        write_uvt(
+            export_path,
+            format!("{} : {}\n", MAP_REF_KEY, map_ref.display()).as_bytes(),
+            &self.trajectory,
+            WriteOptions::default().decimals,
+        )
+    }
+
+    /// Returns a [`UvtError::Vtk`] error if the map is a single inline piece without any point,
+    /// as extracted from an empty pointcloud.
+    fn check_map_not_empty(&self) -> Result<(), UvtError> {
+        match self.map_buffer() {
+            Ok(buffer) if buffer.len() == 0 => Err(UvtError::Vtk(String::from(
+                "The map has no points, set WriteOptions::allow_empty_map to write it anyway",
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the trajectory in the KITTI odometry pose format.
+    ///
+    /// Each pose is written as its 3x4 `[R | t]` transform matrix, flattened in row-major
+    /// order into 12 space-separated values, one pose per line.
+    /// An empty trajectory gives an empty file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The destination file path.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// uvt.write_trajectory_kitti("poses.txt").unwrap();
+    /// ```
+    pub fn write_trajectory_kitti<P: AsRef<path::Path>>(&self, path: P) -> Result<(), UvtError> {
+        let lines: String = self
+            .trajectory
+            .iter()
+            .map(|p| {
+                let [r0, r1, r2] = p.pose.orientation.to_rotation_matrix();
+                let t = p.pose.position;
+                let values: Vec<String> = [r0[0], r0[1], r0[2], t.x]
+                    .into_iter()
+                    .chain([r1[0], r1[1], r1[2], t.y])
+                    .chain([r2[0], r2[1], r2[2], t.z])
+                    .map(|v| v.to_string())
+                    .collect();
+                values.join(" ") + "\n"
+            })
+            .collect();
+        fs::write(path, lines)
+    }
+
+    /// Writes the trajectory as CSV, with a `seq,sec,nanosec,x,y,z,qx,qy,qz,qw` header row
+    /// followed by one row per pose.
+    ///
+    /// Values are written with full precision, unlike the 6-decimal values of [`Uvt::write_file`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The destination file path.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// uvt.write_trajectory_csv("trajectory.csv").unwrap();
+    /// ```
+    pub fn write_trajectory_csv<P: AsRef<path::Path>>(&self, path: P) -> Result<(), UvtError> {
+        let mut csv = String::from("seq,sec,nanosec,x,y,z,qx,qy,qz,qw\n");
+        for p in &self.trajectory {
+            let (pt, q) = (p.pose.position, p.pose.orientation);
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{}\n",
+                p.header.seq,
+                p.header.stamp.sec,
+                p.header.stamp.nanosec,
+                pt.x,
+                pt.y,
+                pt.z,
+                q.x,
+                q.y,
+                q.z,
+                q.w
+            ));
+        }
+        fs::write(path, csv)
+    }
+
+    /// Writes the map points to a PLY file, as `float` `x`, `y` and `z` vertex properties.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The destination file path.
+    /// * `binary` - Whether to write the `binary_little_endian` format instead of `ascii`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map is not a single inline PolyData piece,
+    /// or [`UvtError::Io`] if the file cannot be written.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// uvt.write_map_ply("map.ply", true).unwrap();
+    /// ```
+    pub fn write_map_ply<P: AsRef<path::Path>>(
+        &self,
+        path: P,
+        binary: bool,
+    ) -> Result<(), UvtError> {
+        let coords = self.map_f32_coords()?;
+        let n_points = coords.len() / 3;
+
+        let format = if binary {
+            "binary_little_endian"
+        } else {
+            "ascii"
+        };
+        let header = [
+            String::from("ply"),
+            format!("format {} 1.0", format),
+            format!("comment {}", self.map.title),
+            format!("element vertex {}", n_points),
+            String::from("property float x"),
+            String::from("property float y"),
+            String::from("property float z"),
+            String::from("end_header\n"),
+        ];
+        let mut ply = header.join("\n").into_bytes();
+        if binary {
+            ply.extend(coords.iter().flat_map(|v| v.to_le_bytes()));
+        } else {
+            for c in coords.chunks_exact(3) {
+                ply.extend(format!("{} {} {}\n", c[0], c[1], c[2]).into_bytes());
+            }
+        }
+        fs::write(path, ply)?;
+        Ok(())
+    }
+
+    /// Writes the map points to a PCD v0.7 file, with FLOAT32 `x`, `y` and `z` fields.
+    ///
+    /// When the map has an `intensity` point attribute, it is written as an additional
+    /// FLOAT32 `intensity` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The destination file path.
+    /// * `binary` - Whether to write the `binary` data format instead of `ascii`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map is not a single inline PolyData piece,
+    /// or [`UvtError::Io`] if the file cannot be written.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// uvt.write_map_pcd("map.pcd", true).unwrap();
+    /// ```
+    pub fn write_map_pcd<P: AsRef<path::Path>>(
+        &self,
+        path: P,
+        binary: bool,
+    ) -> Result<(), UvtError> {
+        let coords = self.map_f32_coords()?;
+        let n_points = coords.len() / 3;
+        let intensities = self
+            .map_point_attribute(INTENSITY_FIELD)
+            .filter(|values| values.len() == n_points);
+
+        let (fields, sizes, types, counts) = match intensities {
+            Some(_) => ("x y z intensity", "4 4 4 4", "F F F F", "1 1 1 1"),
+            None => ("x y z", "4 4 4", "F F F", "1 1 1"),
+        };
+        let header = [
+            String::from("# .PCD v0.7 - Point Cloud Data file format"),
+            String::from("VERSION 0.7"),
+            format!("FIELDS {}", fields),
+            format!("SIZE {}", sizes),
+            format!("TYPE {}", types),
+            format!("COUNT {}", counts),
+            format!("WIDTH {}", n_points),
+            String::from("HEIGHT 1"),
+            String::from("VIEWPOINT 0 0 0 1 0 0 0"),
+            format!("POINTS {}", n_points),
+            format!("DATA {}\n", if binary { "binary" } else { "ascii" }),
+        ];
+        let mut pcd = header.join("\n").into_bytes();
+
+        let points = coords.chunks_exact(3).enumerate().map(|(i, c)| {
+            let intensity = intensities.as_ref().map(|values| values[i] as f32);
+            c.iter().copied().chain(intensity).collect::<Vec<f32>>()
+        });
+        for values in points {
+            if binary {
+                pcd.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+            } else {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                pcd.extend((values.join(" ") + "\n").into_bytes());
+            }
+        }
+        fs::write(path, pcd)?;
+        Ok(())
+    }
+
+    /// Extracts the map points.
+    ///
+    /// The coordinates of the points are converted to f64, whether the map stores them
+    /// as F32, F64 or integer values.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map is not a single inline PolyData piece,
+    /// or if its coordinates are not numeric.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("example.uvt").unwrap();
+    /// let points = uvt.map_points().unwrap();
+    /// println!("{} map points", points.len());
+    /// ```
+    pub fn map_points(&self) -> Result<Vec<Point>, UvtError> {
+        let coords = match self.map_buffer()? {
+            vtkio::IOBuffer::F64(coords) => coords.clone(),
+            vtkio::IOBuffer::F32(coords) => coords.iter().map(|&v| v as f64).collect(),
+            buffer => buffer
+                .clone()
+                .cast_into::<f64>()
+                .ok_or_else(|| UvtError::Vtk(String::from("Unsupported map point buffer type")))?,
+        };
+        Ok(coords
+            .chunks_exact(3)
+            .map(|c| Point::new(c[0], c[1], c[2]))
+            .collect())
+    }
+
+    /// Extracts the map points, each paired with the value of the scalar field `field`.
+    ///
+    /// The field is either a coordinate (`x`, `y` or `z`) or a point attribute of the map,
+    /// such as `intensity` or `ring`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map is not a single inline PolyData piece,
+    /// or if it has no point attribute named `field`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("example.uvt").unwrap();
+    /// let intensities = uvt.map_points_with("intensity").unwrap();
+    /// ```
+    pub fn map_points_with(&self, field: &str) -> Result<Vec<(Point, f64)>, UvtError> {
+        let points = self.map_points()?;
+
+        let values: Vec<f64> = match field {
+            "x" => points.iter().map(|pt| pt.x).collect(),
+            "y" => points.iter().map(|pt| pt.y).collect(),
+            "z" => points.iter().map(|pt| pt.z).collect(),
+            _ => self
+                .map_point_attribute(field)
+                .ok_or_else(|| UvtError::Vtk(format!("Point data '{}' not found in map", field)))?,
+        };
+        Ok(points.into_iter().zip(values).collect())
+    }
+
+    /// Extracts the RGBA colors of the map points, from color scalars such as the `rgba`
+    /// point data kept from packed color fields of the recorded pointclouds.
+    ///
+    /// RGB color scalars are opaque.
+    ///
+    /// # Returns
+    ///
+    /// The color of each point, or `None` if the map has no color scalars for its points.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_rosbag("dataset.bag", "/map", "/odom").unwrap();
+    /// if let Some(colors) = uvt.map_colors() {
+    ///     println!("First point color: {:?}", colors[0]);
+    /// }
+    /// ```
+    pub fn map_colors(&self) -> Option<Vec<[u8; 4]>> {
+        let DataSet::PolyData { pieces, .. } = &self.map.data else {
+            return None;
+        };
+        let [Piece::Inline(piece)] = pieces.as_slice() else {
+            return None;
+        };
+        let (num_comp, data) = piece.data.point.iter().find_map(|attr| match attr {
+            Attribute::DataArray(DataArray {
+                elem: vtkio::model::ElementType::ColorScalars(num_comp @ (3 | 4)),
+                data,
+                ..
+            }) => Some((*num_comp as usize, data)),
+            _ => None,
+        })?;
+
+        // Color scalars are floats between 0 and 1 in ASCII files, and bytes in binary files
+        let channels: Vec<u8> = match data {
+            vtkio::IOBuffer::U8(channels) => channels.clone(),
+            data => data
+                .clone()
+                .cast_into::<f64>()?
+                .into_iter()
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect(),
+        };
+        if channels.len() != num_comp * piece.num_points() {
+            return None;
+        }
+        Some(
+            channels
+                .chunks_exact(num_comp)
+                .map(|c| [c[0], c[1], c[2], c.get(3).copied().unwrap_or(255)])
+                .collect(),
+        )
+    }
+
+    /// Copies the values of the point attribute `name` of the map, if any.
+    fn map_point_attribute(&self, name: &str) -> Option<Vec<f64>> {
+        let DataSet::PolyData { pieces, .. } = &self.map.data else {
+            return None;
+        };
+        let [Piece::Inline(piece)] = pieces.as_slice() else {
+            return None;
+        };
+        piece.data.point.iter().find_map(|attr| match attr {
+            Attribute::DataArray(DataArray {
+                name: attr_name,
+                data,
+                ..
+            }) if attr_name == name => data.clone().cast_into::<f64>(),
+            _ => None,
+        })
+    }
+
+    /// Copies the coordinates of the map points as f32 values.
+    fn map_f32_coords(&self) -> Result<Vec<f32>, UvtError> {
+        match self.map_buffer()? {
+            vtkio::IOBuffer::F32(coords) => Ok(coords.clone()),
+            buffer => buffer
+                .clone()
+                .cast_into::<f64>()
+                .map(|coords| coords.into_iter().map(|v| v as f32).collect())
+                .ok_or_else(|| UvtError::Vtk(String::from("Unsupported map point buffer type"))),
+        }
+    }
+
+    /// Groups the map points by LiDAR scan ring.
+    ///
+    /// The rings are given by the `ring` point data of the map, which is kept when
+    /// reading a recording whose map cloud has a `ring` field.
+    ///
+    /// # Returns
+    ///
+    /// The map points of each ring, or an empty map if the map has no ring data.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_rosbag("dataset.bag", "/velodyne_points", "/odom").unwrap();
+    /// for (ring, points) in uvt.points_by_ring() {
+    ///     println!("Ring {}: {} points", ring, points.len());
+    /// }
+    /// ```
+    pub fn points_by_ring(&self) -> HashMap<u16, Vec<Point>> {
+        let mut by_ring: HashMap<u16, Vec<Point>> = HashMap::new();
+        let DataSet::PolyData { pieces, .. } = &self.map.data else {
+            return by_ring;
+        };
+
+        for piece in pieces {
+            let Piece::Inline(piece) = piece else {
+                continue;
+            };
+            let rings = piece.data.point.iter().find_map(|attr| match attr {
+                Attribute::DataArray(DataArray { name, data, .. }) if name == RING_FIELD => {
+                    data.clone().cast_into::<u16>()
+                }
+                _ => None,
+            });
+            let (Some(rings), Some(coords)) = (rings, piece.points.clone().cast_into::<f64>())
+            else {
+                continue;
+            };
+            for (ring, c) in rings.into_iter().zip(coords.chunks_exact(3)) {
+                by_ring
+                    .entry(ring)
+                    .or_default()
+                    .push(Point::new(c[0], c[1], c[2]));
+            }
+        }
+
+        by_ring
+    }
+
+    /// Computes the axis-aligned bounding box of the map.
+    ///
+    /// # Returns
+    ///
+    /// The (min, max) corners of the box, or `None` if the map has no point with finite
+    /// coordinates or is not a single inline PolyData piece.
+    pub fn map_bounds(&self) -> Option<(Point, Point)> {
+        bounds(self.map_points().ok()?)
+    }
+
+    /// Returns an iterator over the stamped poses of the trajectory.
+    pub fn poses(&self) -> impl Iterator<Item = &pose::PoseStamped> {
+        self.trajectory.iter()
+    }
+
+    /// Returns the frame in which the trajectory is expressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`UvtError::Trajectory`] error if the trajectory is empty, or if its poses
+    /// are expressed in several frames, e.g. when the frame changed during the recording.
+    pub fn trajectory_frame_id(&self) -> Result<&str, UvtError> {
+        trajectory_frame_id(&self.trajectory)
+    }
+
+    /// Returns the stamped pose at `index`, or `None` if it is out of bounds.
+    pub fn pose_at(&self, index: usize) -> Option<&pose::PoseStamped> {
+        self.trajectory.get(index)
+    }
+
+    /// Returns the number of poses in the trajectory.
+    pub fn num_poses(&self) -> usize {
+        self.trajectory.len()
+    }
+
+    /// Computes the pose of the vehicle at a given time.
+    ///
+    /// The trajectory is assumed to be sorted by stamp. Between two stamped poses,
+    /// the pose is interpolated with [`pose::Pose::interpolate`].
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The query time.
+    ///
+    /// # Returns
+    ///
+    /// The pose at time `t`, or `None` if `t` is outside the time range of the trajectory.
+    pub fn pose_at_time(&self, t: pose::Time) -> Option<pose::Pose> {
+        let i = self.trajectory.partition_point(|p| p.header.stamp < t);
+        let after = self.trajectory.get(i)?;
+        if after.header.stamp == t {
+            return Some(after.pose);
+        }
+        let before = self.trajectory.get(i.checked_sub(1)?)?;
+
+        let (t0, t1) = (
+            before.header.stamp.as_nanos(),
+            after.header.stamp.as_nanos(),
+        );
+        let ratio = (t.as_nanos() - t0) as f64 / (t1 - t0) as f64;
+        Some(before.pose.interpolate(&after.pose, ratio))
+    }
+
+    /// Resamples the trajectory at a fixed time step.
+    ///
+    /// Poses are sampled every `period` from the first stamp of the trajectory up to its last
+    /// stamp, and interpolated with [`Uvt::pose_at_time`]. The resampled poses keep the frame
+    /// of the trajectory and are numbered from 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `period` - The time step between two resampled poses.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidInput` I/O error if `period` is zero, or a [`UvtError::Trajectory`]
+    /// error if the stamps of the trajectory are not strictly increasing, e.g. when they were
+    /// not recorded.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// let at_10_hz = uvt.resample(Duration::from_millis(100)).unwrap();
+    /// ```
+    pub fn resample(&self, period: Duration) -> Result<Vec<pose::PoseStamped>, UvtError> {
+        if period.is_zero() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The resampling period must be positive",
+            )
+            .into());
+        }
+        if let Some(i) = self
+            .trajectory
+            .windows(2)
+            .position(|w| w[1].header.stamp <= w[0].header.stamp)
+        {
+            return Err(UvtError::Trajectory(format!(
+                "Stamps must be strictly increasing to resample the trajectory, pose {} is not",
+                i + 1
+            )));
+        }
+        let (Some(first), Some(last)) = (self.trajectory.first(), self.trajectory.last()) else {
+            return Ok(vec![]);
+        };
+
+        let frame_id = &first.header.frame_id;
+        let (start, end) = (first.header.stamp.as_nanos(), last.header.stamp.as_nanos());
+        Ok((start..=end)
+            .step_by(period.as_nanos() as usize)
+            .zip(0..)
+            .filter_map(|(t, seq)| {
+                let stamp = pose::Time::from_nanos(t);
+                let pose = self.pose_at_time(stamp)?;
+                Some(pose::PoseStamped::new(
+                    pose::Header {
+                        seq,
+                        stamp,
+                        frame_id: frame_id.clone(),
+                    },
+                    pose,
+                ))
+            })
+            .collect())
+    }
+
+    /// Extracts the stamped poses between two times, both included.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start of the time range.
+    /// * `end` - The end of the time range.
+    ///
+    /// # Returns
+    ///
+    /// The stamped poses whose stamp is within `[start, end]`, in trajectory order.
+    pub fn slice_time(&self, start: pose::Time, end: pose::Time) -> Vec<pose::PoseStamped> {
+        self.trajectory
+            .iter()
+            .filter(|p| (start..=end).contains(&p.header.stamp))
+            .cloned()
+            .collect()
+    }
+
+    /// Computes the axis-aligned bounding box of the trajectory positions.
+    ///
+    /// # Returns
+    ///
+    /// The (min, max) corners of the box, or `None` if the trajectory has no position
+    /// with finite coordinates.
+    pub fn trajectory_bounds(&self) -> Option<(Point, Point)> {
+        bounds(self.trajectory.iter().map(|p| p.pose.position))
+    }
+
+    /// Computes the centroid of the trajectory positions.
+    ///
+    /// # Returns
+    ///
+    /// The mean position, or `None` if the trajectory is empty.
+    pub fn trajectory_centroid(&self) -> Option<Point> {
+        centroid(self.trajectory.iter().map(|p| p.pose.position))
+    }
+
+    /// Computes the distance travelled along the trajectory, i.e. the sum of the
+    /// Euclidean distances between consecutive positions.
+    /// Returns 0 for fewer than two poses.
+    pub fn trajectory_length(&self) -> f64 {
+        pose::path_length(self.trajectory.iter().map(|p| p.pose.position))
+    }
+
+    /// Computes the density of the map, in points per square unit of the XY plane,
+    /// over the XY bounding box of the map.
+    ///
+    /// See [`Uvt::map_density_over`] to use the area covered by the trajectory instead.
+    pub fn map_density(&self) -> f64 {
+        self.map_density_over(DensityArea::default())
+    }
+
+    /// Computes the density of the map, in points per square unit of the XY plane.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area over which the map points are counted.
+    ///
+    /// # Returns
+    ///
+    /// The number of map points divided by the area, or NaN if the area is empty
+    /// (e.g. an empty map or a straight trajectory) or the map has no point buffer.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{DensityArea, Uvt};
+    ///
+    /// let uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// println!("{:.1} points/m²", uvt.map_density_over(DensityArea::TrajectoryHull));
+    /// ```
+    pub fn map_density_over(&self, area: DensityArea) -> f64 {
+        let Some(coords) = self
+            .map_buffer()
+            .ok()
+            .and_then(|buffer| buffer.clone().cast_into::<f64>())
+        else {
+            return f64::NAN;
+        };
+        let n_points = coords.len() / 3;
+
+        let area = match area {
+            DensityArea::MapBoundingBox => {
+                let (min, max) = coords.chunks_exact(3).fold(
+                    ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]),
+                    |(min, max), c| {
+                        (
+                            [min[0].min(c[0]), min[1].min(c[1])],
+                            [max[0].max(c[0]), max[1].max(c[1])],
+                        )
+                    },
+                );
+                ((max[0] - min[0]) * (max[1] - min[1])).max(0.0)
+            }
+            DensityArea::TrajectoryHull => convex_hull_area(
+                self.trajectory
+                    .iter()
+                    .map(|p| (p.pose.position.x, p.pose.position.y))
+                    .collect(),
+            ),
+        };
+
+        if area > 0.0 {
+            n_points as f64 / area
+        } else {
+            f64::NAN
+        }
+    }
+
+    /// Appends a pose to the trajectory.
+    ///
+    /// The pose is given the next sequence number, i.e. the largest one of the trajectory plus one,
+    /// and the frame of the trajectory. On an empty trajectory, the sequence starts at 0 and the
+    /// frame is left empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `pose` - The pose to append.
+    /// * `stamp` - The time at which the pose was recorded.
+    pub fn append_pose(&mut self, pose: pose::Pose, stamp: pose::Time) {
+        let seq = self
+            .trajectory
+            .iter()
+            .map(|p| p.header.seq)
+            .max()
+            .map_or(0, |seq| seq + 1);
+        let frame_id = self
+            .trajectory
+            .last()
+            .map(|p| p.header.frame_id.clone())
+            .unwrap_or_default();
+
+        self.trajectory.push(pose::PoseStamped::new(
+            pose::Header {
+                seq,
+                stamp,
+                frame_id,
+            },
+            pose,
+        ));
+    }
+
+    /// Borrows the coordinate buffer of the map points, without copying it.
+    ///
+    /// The buffer holds the flattened `x, y, z` coordinates of every point,
+    /// so its length is three times the number of map points.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map is not a single inline PolyData piece.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let uvt = Uvt::read_file("example.uvt").unwrap();
+    /// if let vtkio::IOBuffer::F32(coords) = uvt.map_buffer().unwrap() {
+    ///     println!("{} map points", coords.len() / 3);
+    /// }
+    /// ```
+    pub fn map_buffer(&self) -> Result<&vtkio::IOBuffer, UvtError> {
+        let DataSet::PolyData { pieces, .. } = &self.map.data else {
+            return Err(UvtError::Vtk(String::from(
+                "The map is not a PolyData dataset",
+            )));
+        };
+        match pieces.as_slice() {
+            [Piece::Inline(piece)] => Ok(&piece.points),
+            [_] => Err(UvtError::Vtk(String::from(
+                "The map contains a non-inline piece",
+            ))),
+            _ => Err(UvtError::Vtk(format!(
+                "The map has {} pieces instead of one",
+                pieces.len()
+            ))),
+        }
+    }
+
+    /// Downsamples the map by replacing the points of each voxel by their centroid.
+    ///
+    /// The map points are bucketed into a grid of cubic voxels of edge length `voxel_size`,
+    /// and the map is rebuilt with one point per occupied voxel. Point attributes, such as
+    /// the `ring` of each point, are dropped since a centroid mixes several points.
+    ///
+    /// # Arguments
+    ///
+    /// * `voxel_size` - The edge length of the voxels, in map units.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidInput` I/O error if `voxel_size` is not positive and finite, or
+    /// [`UvtError::Vtk`] if the map is not a single inline PolyData piece.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let mut uvt = Uvt::read_rosbag("dataset.bag", "/map", "/odom").unwrap();
+    /// uvt.voxel_downsample(0.1).unwrap();
+    /// uvt.write_file("dataset.uvt").unwrap();
+    /// ```
+    pub fn voxel_downsample(&mut self, voxel_size: f64) -> Result<(), UvtError> {
+        let precision = MapPrecision::of(self.map_buffer()?);
+        let mut grid = pointcloud::VoxelGrid::new(voxel_size)?;
+        grid.insert(self.map_points()?);
+
+        self.map = points_vtk(
+            &grid.points(),
+            vtkio::model::Attributes::new(),
+            self.map.title.clone(),
+            precision,
+        );
+        Ok(())
+    }
+
+    /// Crops the UVT to an axis-aligned box, in place.
+    ///
+    /// Only the map points and the trajectory poses whose position lies within the box
+    /// are kept. See [`Uvt::crop_map`] and [`Uvt::crop_trajectory`] to crop only one of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The minimum corner of the box, inclusive.
+    /// * `max` - The maximum corner of the box, inclusive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The box is inverted, i.e. a coordinate of `min` exceeds the one of `max`
+    /// - The map is not a single inline PolyData piece
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{Point, Uvt};
+    ///
+    /// let mut uvt = Uvt::read_file("example.uvt").unwrap();
+    /// uvt.crop(Point::new(-50.0, -50.0, -5.0), Point::new(50.0, 50.0, 5.0))
+    ///     .unwrap();
+    /// ```
+    pub fn crop(&mut self, min: Point, max: Point) -> Result<(), UvtError> {
+        self.crop_map(min, max)?;
+        self.crop_trajectory(min, max)
+    }
+
+    /// Keeps only the map points within an axis-aligned box, in place.
+    ///
+    /// The point attributes, such as the `ring` of each point, are cropped along with
+    /// the points. Cells, such as vertices, are dropped since they index the original points.
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The minimum corner of the box, inclusive.
+    /// * `max` - The maximum corner of the box, inclusive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The box is inverted, i.e. a coordinate of `min` exceeds the one of `max`
+    /// - The map is not a single inline PolyData piece
+    /// - A point attribute does not have one value per map point
+    pub fn crop_map(&mut self, min: Point, max: Point) -> Result<(), UvtError> {
+        check_box(&min, &max)?;
+        let keep: Vec<bool> = self
+            .map_points()?
+            .iter()
+            .map(|pt| pt.is_within(&min, &max))
+            .collect();
+
+        let DataSet::PolyData { pieces, .. } = &mut self.map.data else {
+            return Err(UvtError::Vtk(String::from(
+                "The map is not a PolyData dataset",
+            )));
+        };
+        let [Piece::Inline(piece)] = pieces.as_mut_slice() else {
+            return Err(UvtError::Vtk(String::from(
+                "The map is not a single inline piece",
+            )));
+        };
+
+        // Check every buffer before cropping, so that the map is left untouched on error
+        let mut buffers = vec![(&mut piece.points, 3)];
+        for attr in piece.data.point.iter_mut() {
+            match attr {
+                Attribute::DataArray(array) => {
+                    let num_comp = array.num_comp();
+                    buffers.push((&mut array.data, num_comp));
+                }
+                Attribute::Field { data_array, .. } => {
+                    for array in data_array.iter_mut() {
+                        let num_comp = array.num_comp();
+                        buffers.push((&mut array.data, num_comp));
+                    }
+                }
+            }
+        }
+        if let Some((buffer, _)) = buffers
+            .iter()
+            .find(|(buffer, num_comp)| !can_retain_tuples(buffer, *num_comp, &keep))
+        {
+            return Err(UvtError::Vtk(format!(
+                "A point attribute has {} values for {} map points",
+                buffer.len(),
+                keep.len()
+            )));
+        }
+        for (buffer, num_comp) in buffers {
+            retain_tuples(buffer, num_comp, &keep);
+        }
+
+        piece.verts = None;
+        piece.lines = None;
+        piece.polys = None;
+        piece.strips = None;
+        piece.data.cell.clear();
+        Ok(())
+    }
+
+    /// Keeps only the trajectory poses whose position is within an axis-aligned box, in place.
+    ///
+    /// The poses keep their header, including their sequence number.
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The minimum corner of the box, inclusive.
+    /// * `max` - The maximum corner of the box, inclusive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the box is inverted, i.e. a coordinate of `min` exceeds the one of `max`.
+    pub fn crop_trajectory(&mut self, min: Point, max: Point) -> Result<(), UvtError> {
+        check_box(&min, &max)?;
+        self.trajectory
+            .retain(|p| p.pose.position.is_within(&min, &max));
+        Ok(())
+    }
+
+    /// Expresses the UVT (map and trajectory) in another frame.
+    ///
+    /// The transforms are `(parent frame, child frame, transform)` tuples, where each transform
+    /// maps points expressed in the child frame into the parent frame, as in the ROS TF tree.
+    /// The chain of transforms linking the current trajectory frame to `target_frame` is composed
+    /// and applied to every map point and trajectory pose.
+    ///
+    /// # Arguments
+    ///
+    /// * `transforms` - The available transforms, e.g. from `read_transforms_rosbag`.
+    /// * `target_frame` - The frame in which the UVT should be expressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The trajectory is empty, so its frame is unknown
+    /// - No chain of transforms connects the current frame to `target_frame`
+    /// - The map is not an inline `PolyData` dataset
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let mut uvt = Uvt::read_file("my_file.uvt").unwrap();
+    /// let transforms: Vec<_> = Uvt::read_transforms_rosbag("my_file.bag")
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|(_, parent, child, tf)| (parent, child, tf))
+    ///     .collect();
+    /// uvt.reframe(&transforms, "map").unwrap();
+    /// ```
+    pub fn reframe(
+        &mut self,
+        transforms: &[(String, String, pose::Pose)],
+        target_frame: &str,
+    ) -> Result<(), UvtError> {
+        let source_frame = self
+            .trajectory
+            .first()
+            .ok_or(UvtError::Trajectory(String::from("Missing poses")))?
+            .header
+            .frame_id
+            .clone();
+
+        // Each edge maps points from one frame into its neighbour
+        let mut edges: HashMap<&str, Vec<(&str, pose::Pose)>> = HashMap::new();
+        for (parent, child, tf) in transforms {
+            edges
+                .entry(child.as_str())
+                .or_default()
+                .push((parent.as_str(), *tf));
+            edges
+                .entry(parent.as_str())
+                .or_default()
+                .push((child.as_str(), tf.inverse()));
+        }
+
+        // Breadth-first search for the transform chain
+        let identity = pose::Pose::from_6dof((0.0, 0.0, 0.0, 0.0, 0.0, 0.0));
+        let mut chains: HashMap<&str, pose::Pose> =
+            HashMap::from([(source_frame.as_str(), identity)]);
+        let mut queue = VecDeque::from([source_frame.as_str()]);
+        while let Some(frame) = queue.pop_front() {
+            if frame == target_frame {
+                break;
+            }
+            let chain = chains[frame];
+            for (next, tf) in edges.get(frame).into_iter().flatten() {
+                if !chains.contains_key(next) {
+                    chains.insert(next, tf.compose(&chain));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let chain = *chains.get(target_frame).ok_or(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "No transform chain connects frame '{}' to frame '{}'",
+                source_frame, target_frame
+            ),
+        ))?;
+
+        self.transform(&chain)?;
+        self.trajectory
+            .iter_mut()
+            .for_each(|pose| pose.header.frame_id = target_frame.to_string());
+
+        Ok(())
+    }
+
+    /// Applies a rigid transform to the whole UVT, in place.
+    ///
+    /// Every map point `p` becomes `t.transform_point(p)`, and every trajectory pose `p`
+    /// becomes `t.compose(&p)`. See [`pose::Pose::transform_point`] for the convention.
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The rigid transform to apply.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map is not made of inline PolyData pieces
+    /// with floating-point coordinates.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{Uvt, pose::Pose};
+    ///
+    /// let mut uvt = Uvt::read_file("example.uvt").unwrap();
+    /// // Rotate by 90° around Z, then move 10 units along X
+    /// let t = Pose::from_6dof((10.0, 0.0, 0.0, 0.0, 0.0, std::f64::consts::FRAC_PI_2));
+    /// uvt.transform(&t).unwrap();
+    /// ```
+    pub fn transform(&mut self, t: &pose::Pose) -> Result<(), UvtError> {
+        let DataSet::PolyData { pieces, .. } = &mut self.map.data else {
+            return Err(UvtError::Vtk(String::from(
+                "The map is not a PolyData dataset",
+            )));
+        };
+
+        for piece in pieces.iter_mut() {
+            let Piece::Inline(piece) = piece else {
+                return Err(UvtError::Vtk(String::from(
+                    "The map contains a non-inline piece",
+                )));
+            };
+            match &mut piece.points {
+                vtkio::IOBuffer::F32(values) => values.chunks_exact_mut(3).for_each(|c| {
+                    let pt = t.transform_point(Point::new(c[0] as f64, c[1] as f64, c[2] as f64));
+                    c.copy_from_slice(&Into::<[f32; 3]>::into(pt));
+                }),
+                vtkio::IOBuffer::F64(values) => values.chunks_exact_mut(3).for_each(|c| {
+                    let pt = t.transform_point(Point::new(c[0], c[1], c[2]));
+                    c.copy_from_slice(&[pt.x, pt.y, pt.z]);
+                }),
+                _ => {
+                    return Err(UvtError::Vtk(String::from(
+                        "Unsupported map point buffer type",
+                    )));
+                }
+            }
+        }
+
+        self.trajectory
+            .iter_mut()
+            .for_each(|pose| pose.pose = t.compose(&pose.pose));
+
+        Ok(())
+    }
+
+    /// Moves the UVT so that it is centered at the origin, in place.
+    ///
+    /// Georeferenced maps, e.g. in UTM coordinates, have coordinates of hundreds of thousands
+    /// of meters, which lose their centimeter precision once stored as f32 map points.
+    /// The centroid of the trajectory, or of the map if the trajectory is empty, is subtracted
+    /// from every map point and trajectory pose.
+    ///
+    /// Recentering cannot restore the precision already lost by f32 map points, so it only
+    /// helps maps read with [`MapPrecision::Double`].
+    ///
+    /// # Returns
+    ///
+    /// The offset subtracted from the UVT, to translate it back with [`Uvt::transform`].
+    /// The offset is zero for a UVT without any pose or map point.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map is not made of inline PolyData pieces
+    /// with floating-point coordinates.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::{MapPrecision, ReadOptions, Uvt, pose::Pose};
+    ///
+    /// let options = ReadOptions {
+    ///     map_precision: MapPrecision::Double,
+    ///     ..Default::default()
+    /// };
+    /// let mut uvt = Uvt::read_rosbag_with_options("utm.bag", "/map", "/odom", &options).unwrap();
+    /// let offset = uvt.recenter().unwrap();
+    /// uvt.write_file("utm.uvt").unwrap();
+    ///
+    /// // Back to the original coordinates
+    /// uvt.transform(&Pose::from_6dof((offset.x, offset.y, offset.z, 0.0, 0.0, 0.0)))
+    ///     .unwrap();
+    /// ```
+    pub fn recenter(&mut self) -> Result<Point, UvtError> {
+        let offset = match self.trajectory_centroid() {
+            Some(offset) => offset,
+            None => centroid(self.map_points()?).unwrap_or(Point::new(0.0, 0.0, 0.0)),
+        };
+        self.transform(&pose::Pose::from_6dof((
+            -offset.x, -offset.y, -offset.z, 0.0, 0.0, 0.0,
+        )))?;
+        Ok(offset)
+    }
+
+    /// Merges several UVTs, e.g. extracted from the successive bags of a mission, into one.
+    ///
+    /// The trajectories are concatenated in the order of `parts`, keeping their timestamps,
+    /// and the poses are given new sequence numbers starting at 0. The map points are
+    /// concatenated into a single inline PolyData piece. Point attributes, such as the `ring`
+    /// of each point, are dropped. The title of the map is the one of the first part.
+    ///
+    /// The parts must be expressed in the same frame, see [`Uvt::reframe`] to bring them into
+    /// a common frame beforehand.
+    ///
+    /// # Arguments
+    ///
+    /// * `parts` - The UVTs to merge.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `parts` is empty
+    /// - The trajectories of the parts are expressed in different frames
+    /// - The map of a part is not a single inline PolyData piece
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let parts = vec![
+    ///     Uvt::read_rosbag("mission_0.bag", "/map", "/odom").unwrap(),
+    ///     Uvt::read_rosbag("mission_1.bag", "/map", "/odom").unwrap(),
+    /// ];
+    /// let mission = Uvt::merge(parts).unwrap();
+    /// ```
+    pub fn merge(parts: Vec<Uvt>) -> Result<Self, UvtError> {
+        let title = parts
+            .first()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "No UVT to merge"))?
+            .map
+            .title
+            .clone();
+
+        let mut frames = parts
+            .iter()
+            .filter_map(|part| part.trajectory.first())
+            .map(|pose| pose.header.frame_id.as_str());
+        if let Some(frame) = frames.next()
+            && let Some(other) = frames.find(|&other| other != frame)
+        {
+            return Err(UvtError::Trajectory(format!(
+                "Cannot merge UVTs expressed in frames '{}' and '{}'",
+                frame, other
+            )));
+        }
+
+        // Keep double precision coordinates if any part has some
+        let precision = match parts
+            .iter()
+            .filter_map(|part| part.map_buffer().ok())
+            .any(|buffer| MapPrecision::of(buffer) == MapPrecision::Double)
+        {
+            true => MapPrecision::Double,
+            false => MapPrecision::Single,
+        };
+
+        let mut points = Vec::new();
+        let mut trajectory = Vec::new();
+        for part in parts {
+            points.extend(part.map_points()?);
+            trajectory.extend(part.trajectory);
+        }
+        trajectory
+            .iter_mut()
+            .zip(0..)
+            .for_each(|(pose, seq)| pose.header.seq = seq);
+
+        Ok(Self::new(
+            points_vtk(&points, vtkio::model::Attributes::new(), title, precision),
+            trajectory,
+        ))
+    }
+
+    /// Converts the UVT into a [`SharedUvt`], whose clones share the map and trajectory.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uvt::Uvt;
+    ///
+    /// let shared = Uvt::read_file("my_file.uvt").unwrap().shared();
+    /// let for_plot = shared.clone(); // No deep copy of the map
+    /// ```
+    pub fn shared(self) -> SharedUvt {
+        SharedUvt {
+            map: Arc::new(self.map),
+            trajectory: Arc::new(self.trajectory),
+        }
+    }
+}
+
+/// Indexes the trajectory poses.
+///
+/// # Panics
+///
+/// Panics if `index` is out of bounds. See [`Uvt::pose_at`] for a checked access.
+impl std::ops::Index<usize> for Uvt {
+    type Output = pose::PoseStamped;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.trajectory[index]
+    }
+}
+
+/// A UVT whose map section is kept unparsed, as read by [`Uvt::read_file_lazy`].
+///
+/// Useful for trajectory-only workflows, or to preserve a map byte for byte.
+#[derive(Debug, Clone)]
+pub struct LazyUvt {
+    /// The map section of the UVT file, up to the trajectory delimiter.
+    /// It is text, unless the map is a binary legacy VTK map.
+    pub raw_map: Vec<u8>,
+    /// The vehicle's trajectory, saved as a sequence of stamped poses.
+    pub trajectory: Vec<pose::PoseStamped>,
+    /// Directory of the UVT file, against which a `map_ref` is resolved.
+    base_dir: Option<path::PathBuf>,
+}
+
+impl LazyUvt {
+    /// Parses the map section into a VTK map.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UvtError::Vtk`] if the map cannot be parsed, or its `map_ref` cannot be read.
+    pub fn map_parsed(&self) -> Result<Vtk, UvtError> {
+        parse_map_section(&self.raw_map, self.base_dir.as_deref())
+    }
+
+    /// Writes the unparsed map section and the trajectory to a UVT file.
+    ///
+    /// A `map_ref` is written as is, so it must still be valid relative to the new file.
+    pub fn write_file<P: AsRef<path::Path>>(&self, path: P) -> Result<(), UvtError> {
+        write_uvt(
+            path,
+            &self.raw_map,
+            &self.trajectory,
+            WriteOptions::default().decimals,
+        )
+    }
+
+    /// Parses the map section and converts into a [`Uvt`].
+    pub fn into_uvt(self) -> Result<Uvt, UvtError> {
+        Ok(Uvt {
+            map: self.map_parsed()?,
+            trajectory: self.trajectory,
+        })
+    }
+}
+
+/// A read-only UVT whose map and trajectory are reference-counted.
+///
+/// Cloning a `SharedUvt` only increments reference counts, so it can be fanned out
+/// to several consumers (or threads) without copying a potentially huge map.
+///
+/// The data behind an `Arc` cannot be mutated in place. To modify it, convert it back
+/// with [`SharedUvt::into_uvt`], which moves the data out if this is the last handle and
+/// deep-copies it otherwise.
+#[derive(Debug, Clone)]
+pub struct SharedUvt {
+    /// The environment map
+    pub map: Arc<vtkio::Vtk>,
+    /// The vehicle's trajectory, saved as a sequence of stamped poses.
+    pub trajectory: Arc<Vec<pose::PoseStamped>>,
+}
+
+impl SharedUvt {
+    /// Converts back into an owned [`Uvt`].
+    ///
+    /// The map and trajectory are only copied if other handles still share them.
+    pub fn into_uvt(self) -> Uvt {
+        Uvt {
+            map: Arc::unwrap_or_clone(self.map),
+            trajectory: Arc::unwrap_or_clone(self.trajectory),
+        }
+    }
+}
+
+impl From<Uvt> for SharedUvt {
+    fn from(uvt: Uvt) -> Self {
+        uvt.shared()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn uvt_data(points: Vec<f32>, frame_id: &str) -> Uvt {
+        let data = vtkio::model::DataSet::inline(vtkio::model::PolyDataPiece {
+            points: vtkio::IOBuffer::F32(points),
+            verts: None,
+            lines: None,
+            polys: None,
+            strips: None,
+            data: vtkio::model::Attributes::new(),
+        });
+        let map = Vtk {
+            version: vtkio::model::Version { major: 3, minor: 0 },
+            byte_order: vtkio::model::ByteOrder::BigEndian,
+            title: String::from("Test map"),
+            file_path: None,
+            data,
+        };
+        let header = pose::Header {
+            seq: 0,
+            stamp: Duration::from_secs(0).into(),
+            frame_id: frame_id.to_string(),
+        };
+        let trajectory = vec![pose::PoseStamped::new(
+            header,
+            pose::Pose::from_6dof((0.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+        )];
+        Uvt { map, trajectory }
+    }
+
+    fn map_values(uvt: &Uvt) -> Vec<f32> {
+        match &uvt.map.data {
+            DataSet::PolyData { pieces, .. } => match &pieces[0] {
+                Piece::Inline(piece) => match &piece.points {
+                    vtkio::IOBuffer::F32(values) => values.clone(),
+                    _ => panic!("Unexpected buffer type"),
+                },
+                _ => panic!("Unexpected piece type"),
+            },
+            _ => panic!("Unexpected dataset type"),
+        }
+    }
+
+    #[test]
+    fn test_read_rosbag_spatial_filter() {
+        let path = fixtures::temp_path("spatial_filter.bag");
+        let clouds = [
+            vec![Point::new(0.0, 0.0, 0.0), Point::new(5.0, 0.0, 0.0)],
+            vec![
+                Point::new(1.0, 1.0, 1.0),
+                Point::new(-3.0, 2.0, 0.5),
+                Point::new(0.5, 0.5, 0.5),
+            ],
+        ];
+        let maps = clouds
+            .iter()
+            .map(|points| {
+                let cloud = fixtures::xyz_pointcloud(points);
+                fixtures::pointcloud2(fixtures::MessageWriter::ros1(), &cloud)
+            })
+            .collect();
+        let odom = fixtures::odometry(
+            fixtures::MessageWriter::ros1(),
+            0,
+            pose::Time { sec: 1, nanosec: 0 },
+            "odom",
+            "base_link",
+            &pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0)),
+        );
+        fixtures::write_bag(
+            &path,
+            &[
+                ("/map", "sensor_msgs/PointCloud2", maps),
+                ("/odom", "nav_msgs/Odometry", vec![odom]),
+            ],
+        );
+
+        let options = ReadOptions {
+            spatial_filter: Some((Point::new(-1.0, -1.0, -1.0), Point::new(2.0, 2.0, 2.0))),
+            accumulate_maps: true,
+            ..Default::default()
+        };
+        let unfiltered = ReadOptions {
+            spatial_filter: None,
+            ..options.clone()
+        };
+        let uvt = Uvt::read_rosbag_with_options(&path, "/map", "/odom", &options).unwrap();
+        let all = Uvt::read_rosbag_with_options(&path, "/map", "/odom", &unfiltered).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(map_values(&all).len(), 5 * 3);
+        assert_eq!(
+            map_values(&uvt),
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.5, 0.5, 0.5]
+        );
+    }
+
+    #[test]
+    fn test_build_map_double_precision() {
+        // FLOAT64 x, y, z fields, with a UTM-like northing that rounds in f32
+        let northing = 5_000_000.123;
+        let mut cloud = fixtures::xyz_pointcloud(&[]);
+        for (i, field) in cloud.fields.iter_mut().enumerate() {
+            field.offset = 8 * i as u32;
+            field.datatype = pointcloud::DataType::FLOAT64;
+        }
+        cloud.data = [300_000.5, northing, 12.25]
+            .iter()
+            .flat_map(|v: &f64| v.to_le_bytes())
+            .collect();
+        cloud.width = 1;
+        cloud.point_step = 24;
+        cloud.row_step = 24;
+        assert_ne!(northing as f32 as f64, northing);
+
+        let single = Uvt::build_map(
+            std::slice::from_ref(&cloud),
+            "test",
+            &ReadOptions::default(),
+            &mut |_| {},
+        )
+        .unwrap();
+        let single = Uvt::new(single, vec![]);
+        assert!(matches!(single.map_buffer(), Ok(vtkio::IOBuffer::F32(_))));
+        assert_ne!(single.map_points().unwrap()[0].y, northing);
+
+        let options = ReadOptions {
+            map_precision: MapPrecision::Double,
+            ..Default::default()
+        };
+        let mut uvt = uvt_data(vec![], "map");
+        uvt.map = Uvt::build_map(&[cloud], "test", &options, &mut |_| {}).unwrap();
+        assert!(matches!(uvt.map_buffer(), Ok(vtkio::IOBuffer::F64(_))));
+        let expected = vec![Point::new(300_000.5, northing, 12.25)];
+        assert_eq!(uvt.map_points().unwrap(), expected);
+
+        let path = fixtures::temp_path("double.uvt");
+        uvt.write_file(&path).unwrap();
+        let read = Uvt::read_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.map_points().unwrap(), expected);
+
+        // Downsampling keeps the precision of the map
+        uvt.voxel_downsample(0.1).unwrap();
+        assert!(matches!(uvt.map_buffer(), Ok(vtkio::IOBuffer::F64(_))));
+    }
+
+    #[test]
+    fn test_build_map_non_finite_points() {
+        // Valid points interleaved with NaN and infinite placeholders
+        let cloud = fixtures::xyz_pointcloud(&[
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(f64::NAN, f64::NAN, f64::NAN),
+            Point::new(1.0, 2.0, 3.0),
+            Point::new(4.0, f64::INFINITY, 0.0),
+            Point::new(-1.0, 0.5, f64::NAN),
+            Point::new(2.0, -1.0, 1.0),
+        ]);
+        let expected = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 2.0, 3.0),
+            Point::new(2.0, -1.0, 1.0),
+        ];
+        assert_eq!(Vec::<Point>::try_from(cloud.clone()).unwrap(), expected);
+
+        for options in [
+            ReadOptions::default(),
+            ReadOptions {
+                accumulate_maps: true,
+                ..Default::default()
+            },
+            ReadOptions {
+                merge_leaf_size: Some(0.1),
+                ..Default::default()
+            },
+        ] {
+            let map = Uvt::build_map(
+                std::slice::from_ref(&cloud),
+                "nan.bag",
+                &options,
+                &mut |_| {},
+            )
+            .unwrap();
+            let uvt = Uvt::new(map, vec![]);
+            let points = uvt.map_points().unwrap();
+            assert_eq!(points.len(), 3);
+            assert!(points.iter().all(Point::is_finite));
+            assert_eq!(
+                uvt.map_bounds(),
+                Some((Point::new(0.0, -1.0, 0.0), Point::new(2.0, 2.0, 3.0)))
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_map_large_cloud() {
+        let points: Vec<Point> = (0..200_000)
+            .map(|i| {
+                let t = i as f64 * 1e-3;
+                Point::new(t.cos() * t, t.sin() * t, -t / 3.0)
+            })
+            .collect();
+        let expected: Vec<f32> = points
+            .iter()
+            .flat_map(|&pt| Into::<[f32; 3]>::into(pt))
+            .collect();
+        let cloud = fixtures::xyz_pointcloud(&points);
+
+        let map =
+            Uvt::build_map(&[cloud], "large.bag", &ReadOptions::default(), &mut |_| {}).unwrap();
+        let uvt = Uvt {
+            map,
+            trajectory: vec![],
+        };
+
+        // Coordinates go through the f32 cloud and back, without any other rounding
+        assert_eq!(map_values(&uvt), expected);
+    }
+
+    #[test]
+    fn test_build_map_merge() {
+        // Overlapping clouds, all within the unit cube
+        let maps: Vec<pointcloud::PointCloud2> = (0..5)
+            .map(|c| {
+                let points: Vec<Point> = (0..100)
+                    .map(|i| {
+                        let v = ((i * 7 + c * 13) % 100) as f64 / 100.0;
+                        Point::new(v, 1.0 - v, (v * 3.0) % 1.0)
+                    })
+                    .collect();
+                fixtures::xyz_pointcloud(&points)
+            })
+            .collect();
+
+        let options = ReadOptions {
+            merge_leaf_size: Some(0.5),
+            ..Default::default()
+        };
+        let uvt = Uvt {
+            map: Uvt::build_map(&maps, "test", &options, &mut |_| {}).unwrap(),
+            trajectory: vec![],
+        };
+        let n_points = map_values(&uvt).len() / 3;
+        // At most one point per voxel of the 2 x 2 x 2 grid
+        assert!(n_points > 1 && n_points <= 8);
+
+        let last_only = Uvt {
+            map: Uvt::build_map(&maps, "test", &ReadOptions::default(), &mut |_| {}).unwrap(),
+            trajectory: vec![],
+        };
+        assert_eq!(map_values(&last_only).len(), 3 * 100);
+    }
+
+    #[test]
+    fn test_points_by_ring() {
+        // Packed x, y, z FLOAT32 and ring UINT16 fields
+        let mut cloud = fixtures::xyz_pointcloud(&[]);
+        cloud.fields.push(pointcloud::PointField {
+            name: String::from(RING_FIELD),
+            offset: 12,
+            datatype: pointcloud::DataType::UINT16,
+            count: 1,
+        });
+        cloud.point_step = 14;
+        let points = [
+            (Point::new(1.0, 0.0, -1.0), 0_u16),
+            (Point::new(2.0, 0.0, 0.0), 1),
+            (Point::new(3.0, 0.0, -1.0), 0),
+        ];
+        for (pt, ring) in points {
+            cloud.data.extend(
+                Into::<[f32; 3]>::into(pt)
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes()),
+            );
+            cloud.data.extend(ring.to_le_bytes());
+        }
+        cloud.width = points.len() as u32;
+        cloud.row_step = 14 * cloud.width;
+
+        let uvt = Uvt {
+            map: Uvt::build_map(&[cloud], "test", &ReadOptions::default(), &mut |_| {}).unwrap(),
+            trajectory: vec![],
+        };
+        let by_ring = uvt.points_by_ring();
+
+        assert_eq!(by_ring.len(), 2);
+        assert_eq!(
+            by_ring[&0],
+            vec![Point::new(1.0, 0.0, -1.0), Point::new(3.0, 0.0, -1.0)]
+        );
+        assert_eq!(by_ring[&1], vec![Point::new(2.0, 0.0, 0.0)]);
+
+        // No ring data
+        assert!(
+            uvt_data(vec![1.0, 2.0, 3.0], "map")
+                .points_by_ring()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_build_map_colors() {
+        // Packed x, y, z FLOAT32 and rgb FLOAT32 fields
+        let mut cloud = fixtures::xyz_pointcloud(&[]);
+        cloud.fields.push(pointcloud::PointField {
+            name: String::from("rgb"),
+            offset: 12,
+            datatype: pointcloud::DataType::FLOAT32,
+            count: 1,
+        });
+        cloud.point_step = 16;
+        let points = [
+            (Point::new(1.0, 0.0, 0.0), 0x00ff8000_u32),
+            (Point::new(2.0, 0.0, 0.0), 0x000000ff),
+        ];
+        for (pt, rgb) in points {
+            cloud.data.extend(
+                Into::<[f32; 3]>::into(pt)
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes()),
+            );
+            cloud.data.extend(rgb.to_le_bytes());
+        }
+        cloud.width = points.len() as u32;
+        cloud.row_step = 16 * cloud.width;
+
+        let mut uvt = uvt_data(vec![0.0; 3], "map");
+        uvt.map = Uvt::build_map(&[cloud], "test", &ReadOptions::default(), &mut |_| {}).unwrap();
+        let expected = vec![[255, 128, 0, 255], [0, 0, 255, 255]];
+        assert_eq!(uvt.map_colors(), Some(expected.clone()));
+
+        let path = fixtures::temp_path("colors.uvt");
+        uvt.write_file(&path).unwrap();
+        let read = Uvt::read_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.map_colors(), Some(expected));
+
+        assert!(uvt_data(vec![1.0, 2.0, 3.0], "map").map_colors().is_none());
+    }
+
+    #[test]
+    fn test_map_ref_round_trip() {
+        let uvt_path = fixtures::temp_path("map_ref.uvt");
+        let map_name = uvt_path.with_extension("vtp");
+        let map_name = map_name.file_name().unwrap();
+
+        let uvt = uvt_data(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], "map");
+        uvt.write_file_with_map_ref(&uvt_path, map_name).unwrap();
+
+        let content = fs::read_to_string(&uvt_path).unwrap();
+        let read = Uvt::read_file(&uvt_path);
+        fs::remove_file(&uvt_path).unwrap();
+        fs::remove_file(uvt_path.with_file_name(map_name)).unwrap();
+
+        assert!(content.starts_with(&format!("map_ref : {}\n", map_name.display())));
+        let read = read.unwrap();
+        assert_eq!(map_values(&read), map_values(&uvt));
+        assert_eq!(read.trajectory.len(), 1);
+        assert_eq!(read.trajectory[0].header.frame_id, "map");
+    }
+
+    #[test]
+    fn test_map_density() {
+        // 11 x 11 grid with a 0.5 spacing, over a 5 x 5 square
+        let points: Vec<f32> = (0..11)
+            .flat_map(|i| (0..11).flat_map(move |j| [i as f32 * 0.5, j as f32 * 0.5, 1.0]))
+            .collect();
+        let mut uvt = uvt_data(points, "map");
+        assert_eq!(uvt.map_density(), 121.0 / 25.0);
+
+        // Trajectory going around a 10 x 2 rectangle
+        for (x, y) in [(10.0, 0.0), (10.0, 2.0), (5.0, 1.0), (0.0, 2.0)] {
+            uvt.append_pose(
+                pose::Pose::from_6dof((x, y, 0.0, 0.0, 0.0, 0.0)),
+                pose::Time { sec: 0, nanosec: 0 },
+            );
+        }
+        assert_eq!(
+            uvt.map_density_over(DensityArea::TrajectoryHull),
+            121.0 / 20.0
+        );
+
+        // A straight trajectory covers no area
+        uvt.trajectory.truncate(2);
+        assert!(uvt.map_density_over(DensityArea::TrajectoryHull).is_nan());
+    }
+
+    #[test]
+    fn test_lazy_map_round_trip() {
+        let path = fixtures::temp_path("lazy.uvt");
+        let copy_path = fixtures::temp_path("lazy_copy.uvt");
+        let uvt = uvt_data(vec![1.5, 2.0, 3.0, 4.0, 5.0, 6.25], "map");
+        uvt.write_file(&path).unwrap();
+        // Extra whitespace that a reparse would not preserve
+        let original = fs::read_to_string(&path).unwrap().replacen("\n", "  \n", 1);
+        fs::write(&path, &original).unwrap();
+
+        let lazy = Uvt::read_file_lazy(&path).unwrap();
+        lazy.write_file(&copy_path).unwrap();
+        let copy = fs::read_to_string(&copy_path).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&copy_path).unwrap();
+
+        assert_eq!(copy, original);
+        assert_eq!(lazy.trajectory.len(), 1);
+        let uvt_read = lazy.into_uvt().unwrap();
+        assert_eq!(map_values(&uvt_read), map_values(&uvt));
+
+        let broken = LazyUvt {
+            raw_map: b"not a vtk file".to_vec(),
+            trajectory: vec![],
+            base_dir: None,
+        };
+        assert!(matches!(broken.map_parsed(), Err(UvtError::Vtk(_))));
+    }
+
+    #[test]
+    fn test_binary_map_round_trip() {
+        let path = fixtures::temp_path("binary.uvt");
+        let ascii_path = fixtures::temp_path("ascii.uvt");
+        // 0x23 bytes ('#') in the binary map must not be mistaken for the delimiter
+        let hash = f32::from_be_bytes([0x23; 4]);
+        let uvt = uvt_data(vec![1.5, -2.0, 3.0, hash, 10.0, 1e-3], "map");
+        let options = WriteOptions {
+            map_encoding: MapEncoding::Binary,
+            ..Default::default()
+        };
+        uvt.write_file_with_options(&path, &options).unwrap();
+        uvt.write_file(&ascii_path).unwrap();
+
+        let content = fs::read(&path).unwrap();
+        let read = Uvt::read_file(&path).unwrap();
+        let lazy = Uvt::read_file_lazy(&path).unwrap().into_uvt().unwrap();
+        let read_ascii = Uvt::read_file(&ascii_path).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&ascii_path).unwrap();
+
+        assert!(content.windows(6).any(|w| w == b"BINARY"));
+        assert_eq!(map_values(&read), map_values(&uvt));
+        assert_eq!(map_values(&lazy), map_values(&uvt));
+        assert_eq!(map_values(&read_ascii), map_values(&uvt));
+        assert_eq!(read.trajectory[0].pose, uvt.trajectory[0].pose);
+    }
+
+    #[test]
+    fn test_write_decimals() {
+        let path = fixtures::temp_path("decimals.uvt");
+        let mut uvt = uvt_data(vec![1.0, 2.0, 3.0], "utm");
+        uvt.trajectory[0].pose.position = Point::new(5_412_345.123456789, 0.25, 0.0);
+        let read_x = |options: &WriteOptions| {
+            uvt.write_file_with_options(&path, options).unwrap();
+            Uvt::read_file(&path).unwrap().trajectory[0].pose.position.x
+        };
+
+        let default_x = read_x(&WriteOptions::default());
+        let nine_x = read_x(&WriteOptions {
+            decimals: Some(9),
+            ..Default::default()
+        });
+        let full_x = read_x(&WriteOptions {
+            decimals: None,
+            ..Default::default()
+        });
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(default_x, 5_412_345.123457);
+        assert_eq!(nine_x, 5_412_345.123456789);
+        assert_eq!(full_x, 5_412_345.123456789);
+    }
+
+    #[test]
+    fn test_trajectory_frame_id() {
+        let mut uvt = timed_uvt();
+        uvt.map = uvt_data(vec![1.0, 2.0, 3.0], "map").map;
+        assert_eq!(uvt.trajectory_frame_id().unwrap(), "map");
+
+        uvt.trajectory[2].header.frame_id = String::from("odom");
+        let err = uvt.trajectory_frame_id().unwrap_err();
+        assert!(matches!(err, UvtError::Trajectory(_)));
+        assert_eq!(
+            err.to_string(),
+            "Invalid trajectory: Poses are expressed in several frames: map, odom"
+        );
+
+        let path = fixtures::temp_path("mixed_frames.uvt");
+        let err = uvt.write_file(&path).unwrap_err();
+        assert!(matches!(err, UvtError::Trajectory(_)));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_write_empty_map() {
+        let path = fixtures::temp_path("empty_map.uvt");
+        let uvt = uvt_data(vec![], "map");
+
+        let err = uvt.write_file(&path).unwrap_err();
+        assert!(matches!(err, UvtError::Vtk(_)));
+        assert!(err.to_string().contains("no points"));
+        assert!(!path.exists());
+
+        let options = WriteOptions {
+            allow_empty_map: true,
+            ..Default::default()
+        };
+        uvt.write_file_with_options(&path, &options).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(content.contains(crate::TRAJ_DELIMITER));
+    }
+
+    #[test]
+    fn test_written_file_has_delimiter() {
+        let path = fixtures::temp_path("delimiter.uvt");
+        uvt_data(vec![1.0, 2.0, 3.0], "map")
+            .write_file(&path)
+            .unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let (map_section, traj_section) = content.split_once(crate::TRAJ_DELIMITER).unwrap();
+        assert!(map_section.starts_with("# vtk DataFile"));
+        assert!(traj_section.trim_start().starts_with("frame_id : map"));
+    }
+
+    #[test]
+    fn test_delimiter_in_vtk_title() {
+        let path = fixtures::temp_path("delimiter_title.uvt");
+        let mut uvt = uvt_data(vec![1.0, 2.0, 3.0], "map");
+        uvt.map.title = format!("Map {}", TRAJ_DELIMITER);
+        uvt.write_file(&path).unwrap();
+
+        let read = Uvt::read_file(&path).unwrap();
+        assert_eq!(read.map.title, uvt.map.title);
+        assert_eq!(map_values(&read), vec![1.0, 2.0, 3.0]);
+        assert_eq!(read.trajectory.len(), 1);
+
+        // A delimiter line in the map makes the split ambiguous
+        let content = fs::read_to_string(&path).unwrap();
+        let doubled = content.replacen(
+            TRAJ_DELIMITER,
+            &format!("{}\n{}", TRAJ_DELIMITER, TRAJ_DELIMITER),
+            1,
+        );
+        fs::write(&path, doubled).unwrap();
+        let err = Uvt::read_file(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(err, UvtError::Format(_)), "{}", err);
+    }
+
+    #[test]
+    fn test_stamps_round_trip() {
+        let path = fixtures::temp_path("stamps.uvt");
+        let mut uvt = uvt_data(vec![1.0, 2.0, 3.0], "map");
+        uvt.trajectory[0].header.stamp = pose::Time {
+            sec: 1_700_000_000,
+            nanosec: 123_456_789,
+        };
+        uvt.append_pose(
+            pose::Pose::from_6dof((1.0, 2.0, 0.0, 0.0, 0.0, 0.0)),
+            pose::Time {
+                sec: 1_700_000_001,
+                nanosec: 5,
+            },
+        );
+        uvt.write_file(&path).unwrap();
+        let read = Uvt::read_file(&path);
+
+        // Legacy 6-column lines have zero stamps
+        let content = fs::read_to_string(&path).unwrap();
+        let legacy: Vec<String> = content
+            .lines()
+            .map(|line| match line.rsplitn(3, ',').collect::<Vec<_>>()[..] {
+                [_, _, pose] if !line.contains(' ') => pose.to_string(),
+                _ => line.to_string(),
+            })
+            .collect();
+        fs::write(&path, legacy.join("\n")).unwrap();
+        let read_legacy = Uvt::read_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        let stamps: Vec<pose::Time> = read
+            .unwrap()
+            .trajectory
+            .iter()
+            .map(|p| p.header.stamp)
+            .collect();
+        let expected: Vec<pose::Time> = uvt.trajectory.iter().map(|p| p.header.stamp).collect();
+        assert_eq!(stamps, expected);
+
+        let read_legacy = read_legacy.unwrap();
+        assert_eq!(read_legacy.trajectory.len(), 2);
+        assert!(
+            read_legacy
+                .trajectory
+                .iter()
+                .all(|p| p.header.stamp == pose::Time { sec: 0, nanosec: 0 })
+        );
+    }
+
+    #[test]
+    fn test_from_points_round_trip() {
+        let path = fixtures::temp_path("from_points.uvt");
+        let points = vec![Point::new(0.5, 1.0, -2.0), Point::new(3.0, 4.0, 5.0)];
+        let trajectory = uvt_data(vec![], "map").trajectory;
+        let uvt = Uvt::from_points(points, trajectory);
+        uvt.write_file(&path).unwrap();
+        let read = Uvt::read_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        let read = read.unwrap();
+        assert_eq!(map_values(&read), vec![0.5, 1.0, -2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(read.trajectory.len(), 1);
+        assert_eq!(read.trajectory[0].header.frame_id, "map");
+
+        let rebuilt = Uvt::new(read.map, read.trajectory);
+        assert_eq!(map_values(&rebuilt), map_values(&uvt));
+    }
+
+    #[test]
+    fn test_read_file_malformed() {
+        let path = fixtures::temp_path("malformed.uvt");
+        let uvt = uvt_data(vec![1.0, 2.0, 3.0], "map");
+        uvt.write_file(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let (head, _) = content.rsplit_once('\n').unwrap();
+        let with_pose = |line: &str| format!("{}\n{}", head, line);
+
+        let cases = [
+            (
+                with_pose("0,0,zero,0,0,0"),
+                "Line 2: failed to parse values",
+            ),
+            (
+                with_pose("0,0,0,0,0,0,1.5,0"),
+                "Line 2: failed to parse values in '0,0,0,0,0,0,1.5,0'",
+            ),
+            (with_pose("0,0,0,0,0"), "Line 2: expected 6 or 8 values"),
+            (content.replace("frame_id : map", "frame_id map"), "Line 1"),
+            (content.replace("POINTS", "PINTS"), "Failed to parse vtk"),
+            (content.replace(TRAJ_DELIMITER, ""), "delimiter"),
+        ];
+        let errors: Vec<UvtError> = cases
+            .iter()
+            .map(|(corrupted, _)| {
+                fs::write(&path, corrupted).unwrap();
+                Uvt::read_file(&path).err().unwrap()
+            })
+            .collect();
+        fs::remove_file(&path).unwrap();
+
+        for (err, (_, message)) in errors.iter().zip(&cases) {
+            assert!(!matches!(err, UvtError::Io(_)), "{:?}", err);
+            assert!(err.to_string().contains(message), "{}", err);
+        }
+        assert!(matches!(errors[0], UvtError::Parse { line: 2, .. }));
+        assert!(matches!(errors[5], UvtError::Format(_)));
+    }
+
+    #[test]
+    fn test_build_map_accumulate() {
+        let maps = [
+            fixtures::xyz_pointcloud(&[Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0)]),
+            fixtures::xyz_pointcloud(&[Point::new(1.0, 0.0, 0.0), Point::new(2.0, 0.5, 0.0)]),
+        ];
+        let options = ReadOptions {
+            accumulate_maps: true,
+            ..Default::default()
+        };
+
+        let accumulated = Uvt::new(
+            Uvt::build_map(&maps, "test", &options, &mut |_| {}).unwrap(),
+            vec![],
+        );
+        assert_eq!(
+            map_values(&accumulated),
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.5, 0.0]
+        );
+
+        let single = Uvt::new(
+            Uvt::build_map(&maps[..1], "test", &options, &mut |_| {}).unwrap(),
+            vec![],
+        );
+        assert_eq!(map_values(&single), vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+        for options in [options, ReadOptions::default()] {
+            let empty = Uvt::new(
+                Uvt::build_map(&[], "test", &options, &mut |_| {}).unwrap(),
+                vec![],
+            );
+            assert!(map_values(&empty).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_read_rosbag_missing_topic() {
+        let path = fixtures::temp_path("missing_topic.bag");
+        let pose = pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0));
+        let odom = fixtures::odometry(
+            fixtures::MessageWriter::ros1(),
+            0,
+            pose::Time { sec: 1, nanosec: 0 },
+            "odom",
+            "base_link",
+            &pose,
+        );
+        fixtures::write_bag(
+            &path,
+            &[
+                ("/odom", "nav_msgs/Odometry", vec![odom]),
+                ("/velodyne_points", "sensor_msgs/PointCloud2", vec![]),
+            ],
+        );
+
+        let err = Uvt::read_rosbag(&path, "/map", "/odom").err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        match &err {
+            UvtError::MissingTopic { topic, available } => {
+                assert_eq!(topic, "/map");
+                assert_eq!(available, &["/odom", "/velodyne_points"]);
+            }
+            e => panic!("Unexpected error: {}", e),
+        }
+        assert_eq!(
+            err.to_string(),
+            "Topic '/map' has no messages (available: /odom, /velodyne_points)"
+        );
+    }
+
+    #[test]
+    fn test_read_rosbag_truncated_trajectory() {
+        let path = fixtures::temp_path("truncated_trajectory.bag");
+        let cloud = fixtures::xyz_pointcloud(&[Point::new(0.0, 0.0, 0.0)]);
+        let map = fixtures::pointcloud2(fixtures::MessageWriter::ros1(), &cloud);
+        let mut odom = fixtures::odometry(
+            fixtures::MessageWriter::ros1(),
+            0,
+            pose::Time { sec: 1, nanosec: 0 },
+            "odom",
+            "base_link",
+            &pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0)),
+        );
+        odom.truncate(odom.len() / 2);
+        fixtures::write_bag(
+            &path,
+            &[
+                ("/map", "sensor_msgs/PointCloud2", vec![map]),
+                ("/odom", "nav_msgs/Odometry", vec![odom]),
+            ],
+        );
+
+        let err = Uvt::read_rosbag(&path, "/map", "/odom").err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        match err {
+            UvtError::Io(e) => assert_eq!(e.kind(), ErrorKind::UnexpectedEof),
+            e => panic!("Unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_read_rosbag_truncated_map() {
+        let path = fixtures::temp_path("truncated_map.bag");
+        let cloud = fixtures::xyz_pointcloud(&[Point::new(0.0, 0.0, 0.0)]);
+        let mut map = fixtures::pointcloud2(fixtures::MessageWriter::ros1(), &cloud);
+        map.truncate(map.len() / 2);
+        let odom = fixtures::odometry(
+            fixtures::MessageWriter::ros1(),
+            0,
+            pose::Time { sec: 1, nanosec: 0 },
+            "odom",
+            "base_link",
+            &pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0)),
+        );
+        fixtures::write_bag(
+            &path,
+            &[
+                ("/map", "sensor_msgs/PointCloud2", vec![map]),
+                ("/odom", "nav_msgs/Odometry", vec![odom]),
+            ],
+        );
+
+        let err = Uvt::read_rosbag(&path, "/map", "/odom").err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(err, UvtError::Io(_)), "Unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_read_rosbag_invalid_leaf_size() {
+        let options = ReadOptions {
+            merge_leaf_size: Some(f64::NAN),
+            ..Default::default()
+        };
+
+        // The options are checked before the bag is opened
+        let err = Uvt::read_rosbag_with_options("missing.bag", "/map", "/odom", &options)
+            .err()
+            .unwrap();
+
+        assert!(
+            matches!(&err, UvtError::Io(e) if e.kind() == ErrorKind::InvalidInput),
+            "Unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_read_rosbag_multiple_connections() {
+        let path = fixtures::temp_path("multiple_connections.bag");
+        let odom = |x: f64| {
+            fixtures::odometry(
+                fixtures::MessageWriter::ros1(),
+                0,
+                pose::Time { sec: 1, nanosec: 0 },
+                "odom",
+                "base_link",
+                &pose::Pose::from_6dof((x, 0.0, 0.0, 0.0, 0.0, 0.0)),
+            )
+        };
+        fixtures::write_bag(
+            &path,
+            &[
+                ("/odom", "nav_msgs/Odometry", vec![odom(1.0), odom(2.0)]),
+                ("/map", "sensor_msgs/PointCloud2", vec![]),
+                ("/odom", "nav_msgs/Odometry", vec![odom(3.0)]),
+            ],
+        );
+
+        let bag = RosBag::new(&path).unwrap();
+        let msgs = Uvt::retrieve_topic_messages(&bag, "/odom").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(msgs, vec![odom(1.0), odom(2.0), odom(3.0)]);
+    }
+
+    #[test]
+    fn test_read_rosbag_compressed_chunks() {
+        let odoms: Vec<Vec<u8>> = (0..3)
+            .map(|i| {
+                fixtures::odometry(
+                    fixtures::MessageWriter::ros1(),
+                    i,
+                    pose::Time {
+                        sec: i as i32,
+                        nanosec: 0,
+                    },
+                    "odom",
+                    "base_link",
+                    &pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        let connections = [("/odom", "nav_msgs/Odometry", odoms.clone())];
+
+        for compression in ["bz2", "lz4"] {
+            let path = fixtures::temp_path(&format!("compressed_{}.bag", compression));
+            fixtures::write_bag_compressed(&path, &connections, compression);
+            let bag = RosBag::new(&path).unwrap();
+            let msgs = Uvt::retrieve_topic_messages(&bag, "/odom");
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(msgs.unwrap(), odoms);
+        }
+
+        let path = fixtures::temp_path("compressed_zstd.bag");
+        fixtures::write_bag_compressed(&path, &connections, "zstd");
+        let bag = RosBag::new(&path).unwrap();
+        let err = Uvt::retrieve_topic_messages(&bag, "/odom").err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let err = Error::from(err);
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert!(err.to_string().contains("unsupported compression"));
+    }
+
+    #[test]
+    fn test_read_prints_nothing() {
+        const CHILD_VAR: &str = "UVT_TEST_QUIET_CHILD";
+        const TEST_NAME: &str = "tests::test_read_prints_nothing";
+
+        if std::env::var_os(CHILD_VAR).is_some() {
+            let uvt_path = fixtures::temp_path("quiet.uvt");
+            uvt_data(vec![1.0, 2.0, 3.0], "map")
+                .write_file(&uvt_path)
+                .unwrap();
+            Uvt::read_file(&uvt_path).unwrap();
+            fs::remove_file(&uvt_path).unwrap();
+
+            let cloud = fixtures::xyz_pointcloud(&[Point::new(1.0, 2.0, 3.0)]);
+            let odom = |w| {
+                fixtures::odometry(
+                    w,
+                    0,
+                    pose::Time { sec: 1, nanosec: 0 },
+                    "odom",
+                    "base_link",
+                    &pose::Pose::from_6dof((1.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                )
+            };
+            let bag_path = fixtures::temp_path("quiet.bag");
+            fixtures::write_bag(
+                &bag_path,
+                &[
+                    (
+                        "/map",
+                        "sensor_msgs/PointCloud2",
+                        vec![fixtures::pointcloud2(
+                            fixtures::MessageWriter::ros1(),
+                            &cloud,
+                        )],
+                    ),
+                    (
+                        "/odom",
+                        "nav_msgs/Odometry",
+                        vec![odom(fixtures::MessageWriter::ros1())],
+                    ),
+                ],
+            );
+            Uvt::read_rosbag(&bag_path, "/map", "/odom").unwrap();
+            fs::remove_file(&bag_path).unwrap();
+
+            let mcap_path = fixtures::temp_path("quiet.mcap");
+            fixtures::write_mcap(
+                &mcap_path,
+                &[
+                    (
+                        "/map",
+                        "sensor_msgs/msg/PointCloud2",
+                        vec![fixtures::pointcloud2(
+                            fixtures::MessageWriter::cdr(),
+                            &cloud,
+                        )],
+                    ),
+                    (
+                        "/odom",
+                        "nav_msgs/msg/Odometry",
+                        vec![odom(fixtures::MessageWriter::cdr())],
+                    ),
+                ],
+            );
+            Uvt::read_mcap(&mcap_path, "/map", "/odom").unwrap();
+            fs::remove_file(&mcap_path).unwrap();
+            return;
+        }
+
+        quiet_child_output(TEST_NAME, CHILD_VAR);
+    }
+
+    /// Runs the test `test_name` again in a child process with `child_var` set, so that its
+    /// output is not captured, and checks that it only prints the lines of the test harness
+    /// to stdout.
+    fn quiet_child_output(test_name: &str, child_var: &str) -> std::process::Output {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([test_name, "--exact", "--nocapture", "--test-threads=1"])
+            .env(child_var, "1")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", stdout);
+
+        let result_line = format!("test {} ... ok", test_name);
+        for line in stdout.lines() {
+            assert!(
+                line.is_empty()
+                    || line == "running 1 test"
+                    || line == result_line
+                    || line.starts_with("test result: ok."),
+                "Unexpected output: {}",
+                line
+            );
+        }
+        output
+    }
+
+    #[test]
+    fn test_read_rosbag_with_progress() {
+        let path = fixtures::temp_path("progress.bag");
+        let cloud = fixtures::xyz_pointcloud(&[Point::new(1.0, 2.0, 3.0)]);
+        let map = fixtures::pointcloud2(fixtures::MessageWriter::ros1(), &cloud);
+        let odom = fixtures::odometry(
+            fixtures::MessageWriter::ros1(),
+            0,
+            pose::Time { sec: 1, nanosec: 0 },
+            "odom",
+            "base_link",
+            &pose::Pose::from_6dof((1.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+        );
+        fixtures::write_bag(
+            &path,
+            &[
+                ("/map", "sensor_msgs/PointCloud2", vec![map.clone(), map]),
+                ("/odom", "nav_msgs/Odometry", vec![odom; 3]),
+            ],
+        );
+
+        let mut events = Vec::new();
+        let uvt =
+            Uvt::read_rosbag_with_progress(&path, "/map", "/odom", |e| events.push(e)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(uvt.trajectory.len(), 3);
+        let progress = |phase| -> Vec<(usize, usize)> {
+            events
+                .iter()
+                .filter(|e| e.phase == phase)
+                .map(|e| (e.current, e.total))
+                .collect()
+        };
+        assert_eq!(progress(ProgressPhase::ReadingMap), [(1, 2), (2, 2)]);
+        assert_eq!(
+            progress(ProgressPhase::ReadingTrajectory),
+            [(1, 3), (2, 3), (3, 3)]
+        );
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    fn test_stream_rosbag() {
+        let path = fixtures::temp_path("stream.bag");
+        let map = |n: usize| {
+            let points: Vec<Point> = (0..n).map(|i| Point::new(i as f64, 0.0, 0.0)).collect();
+            let cloud = fixtures::xyz_pointcloud(&points);
+            fixtures::pointcloud2(fixtures::MessageWriter::ros1(), &cloud)
+        };
+        let odoms: Vec<Vec<u8>> = (0..4)
+            .map(|i| {
+                fixtures::odometry(
+                    fixtures::MessageWriter::ros1(),
+                    i,
+                    pose::Time {
+                        sec: i as i32,
+                        nanosec: 0,
+                    },
+                    "odom",
+                    "base_link",
+                    &pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        fixtures::write_bag(
+            &path,
+            &[
+                (
+                    "/map",
+                    "sensor_msgs/PointCloud2",
+                    vec![map(1), map(3), map(2)],
+                ),
+                ("/odom", "nav_msgs/Odometry", odoms),
+            ],
+        );
+
+        // Keep the largest cloud
+        let mut largest = 0;
+        let mut poses = Vec::new();
+        let map_vtk = Uvt::stream_rosbag(
+            &path,
+            "/map",
+            "/odom",
+            &ReadOptions::default(),
+            |cloud| {
+                let keep = cloud.width > largest;
+                largest = largest.max(cloud.width);
+                keep
+            },
+            |pose| poses.push(pose),
+        )
+        .unwrap();
+        let expected = Uvt::read_rosbag(&path, "/map", "/odom").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(poses.len(), 4);
+        assert_eq!(poses, expected.trajectory);
+        let uvt = Uvt::new(map_vtk, poses);
+        assert_eq!(
+            uvt.map_bounds(),
+            Some((Point::new(0.0, 0.0, 0.0), Point::new(2.0, 0.0, 0.0)))
+        );
+    }
+
+    #[test]
+    fn test_parse_messages_in_order() {
+        let odoms: Vec<Vec<u8>> = (0..2 * PARSE_BATCH_SIZE + 3)
+            .map(|i| {
+                fixtures::odometry(
+                    fixtures::MessageWriter::ros1(),
+                    i as u32,
+                    pose::Time {
+                        sec: i as i32,
+                        nanosec: 0,
+                    },
+                    "odom",
+                    "base_link",
+                    &pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        let parse = |msg: &[u8]| {
+            let d = bag::BagDeserializer::new(msg.to_vec());
+            trajectory::parse_poses(d, TrajectoryType::Odometry)
+        };
+
+        let mut events = Vec::new();
+        let parallel = parse_messages(
+            &odoms,
+            ProgressPhase::ReadingTrajectory,
+            &mut |e| events.push(e.current),
+            parse,
+        )
+        .unwrap();
+        let sequential: Vec<pose::PoseStamped> =
+            odoms.iter().flat_map(|m| parse(m).unwrap()).collect();
+
+        assert_eq!(parallel, sequential);
+        assert_eq!(events, (1..=odoms.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_list_topics() {
+        let cloud = fixtures::xyz_pointcloud(&[Point::new(0.0, 0.0, 0.0)]);
+        let map = fixtures::pointcloud2(fixtures::MessageWriter::ros1(), &cloud);
+        let bag_path = fixtures::temp_path("list_topics.bag");
+        fixtures::write_bag(
+            &bag_path,
+            &[
+                ("/odom", "nav_msgs/Odometry", vec![]),
+                ("/map", "sensor_msgs/PointCloud2", vec![map]),
+            ],
+        );
+        let bag_topics = Uvt::list_rosbag_topics(&bag_path).unwrap();
+        fs::remove_file(&bag_path).unwrap();
+
+        assert_eq!(
+            bag_topics,
+            vec![
+                ("/map".to_string(), "sensor_msgs/PointCloud2".to_string()),
+                ("/odom".to_string(), "nav_msgs/Odometry".to_string()),
+            ]
+        );
+
+        let map = fixtures::pointcloud2(fixtures::MessageWriter::cdr(), &cloud);
+        let mcap_path = fixtures::temp_path("list_topics.mcap");
+        fixtures::write_mcap(
+            &mcap_path,
+            &[
+                ("/odom", "nav_msgs/msg/Odometry", vec![]),
+                ("/map", "sensor_msgs/msg/PointCloud2", vec![map]),
+            ],
+        );
+        let mcap_topics = Uvt::list_mcap_topics(&mcap_path).unwrap();
+        fs::remove_file(&mcap_path).unwrap();
+
+        assert_eq!(
+            mcap_topics,
+            vec![
+                (
+                    "/map".to_string(),
+                    "sensor_msgs/msg/PointCloud2".to_string()
+                ),
+                ("/odom".to_string(), "nav_msgs/msg/Odometry".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_voxel_downsample() {
+        // 10 x 10 x 10 points spaced by 0.1 in the unit cube
+        let points: Vec<Point> = (0..1000)
+            .map(|i| {
+                let coord = |j: usize| 0.05 + 0.1 * j as f64;
+                Point::new(coord(i % 10), coord(i / 10 % 10), coord(i / 100))
+            })
+            .collect();
+        let mut uvt = Uvt::from_points(points, vec![]);
+
+        uvt.voxel_downsample(0.5).unwrap();
+
+        let coords = uvt
+            .map_buffer()
+            .unwrap()
+            .clone()
+            .cast_into::<f64>()
+            .unwrap();
+        assert_eq!(coords.len(), 8 * 3);
+        assert!(
+            coords
+                .iter()
+                .all(|&c| (c - 0.25).abs() < 1e-6 || (c - 0.75).abs() < 1e-6)
+        );
+
+        let mut not_polydata = Uvt::new(
+            Vtk {
+                data: DataSet::ImageData {
+                    extent: vtkio::model::Extent::Dims([1, 1, 1]),
+                    origin: [0.0; 3],
+                    spacing: [1.0; 3],
+                    meta: None,
+                    pieces: vec![],
+                },
+                ..uvt.map.clone()
+            },
+            vec![],
+        );
+        assert!(matches!(
+            not_polydata.voxel_downsample(0.5),
+            Err(UvtError::Vtk(_))
+        ));
+        assert!(matches!(
+            uvt.voxel_downsample(0.0),
+            Err(UvtError::Io(e)) if e.kind() == ErrorKind::InvalidInput
+        ));
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name(path::Path::new("/data/run.bag")), "run.bag");
+        assert_eq!(file_name(path::Path::new("/")), "/");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let invalid = path::Path::new(std::ffi::OsStr::from_bytes(b"/data/r\xffn.bag"));
+            assert_eq!(file_name(invalid), "r\u{fffd}n.bag");
+        }
+    }
+
+    #[test]
+    fn test_crop() {
+        // 4 x 4 x 4 points in the cube [-1.5, 1.5]^3, each with its index as attribute
+        let points: Vec<Point> = (0..64)
+            .map(|i| {
+                let coord = |j: usize| j as f64 - 1.5;
+                Point::new(coord(i % 4), coord(i / 4 % 4), coord(i / 16))
+            })
+            .collect();
+        let trajectory: Vec<pose::PoseStamped> = (0..4)
+            .map(|i| {
+                let c = i as f64 - 1.5;
+                pose::PoseStamped::new(
+                    pose::Header {
+                        seq: i,
+                        stamp: pose::Time {
+                            sec: i as i32,
+                            nanosec: 0,
+                        },
+                        frame_id: String::from("map"),
+                    },
+                    pose::Pose::from_6dof((c, c, c, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        let mut uvt = Uvt::from_points(points.clone(), trajectory);
+        if let DataSet::PolyData { pieces, .. } = &mut uvt.map.data
+            && let Piece::Inline(piece) = &mut pieces[0]
+        {
+            piece
+                .data
+                .point
+                .push(Attribute::scalars("index", 1).with_data((0..64).collect::<Vec<u32>>()));
+        }
+
+        // Inverted boxes are rejected, without touching the UVT
+        let (min, max) = (Point::new(0.0, 0.0, 0.0), Point::new(2.0, 2.0, 2.0));
+        assert!(uvt.crop(max, min).is_err());
+        assert!(
+            uvt.crop_trajectory(Point::new(0.0, 1.0, 0.0), Point::new(2.0, 0.5, 2.0))
+                .is_err()
+        );
+        assert_eq!(uvt.map_points().unwrap().len(), 64);
+        assert_eq!(uvt.num_poses(), 4);
+
+        uvt.crop(min, max).unwrap();
+
+        let cropped = uvt.map_points().unwrap();
+        assert_eq!(cropped.len(), 8);
+        assert!(cropped.iter().all(|pt| pt.is_within(&min, &max)));
+        let indices = uvt.map_point_attribute("index").unwrap();
+        let expected: Vec<Point> = indices.iter().map(|&i| points[i as usize]).collect();
+        assert_eq!(cropped, expected);
+
+        let seqs: Vec<u32> = uvt.poses().map(|p| p.header.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_recenter() {
+        // UTM-like coordinates
+        let origin = Point::new(500_000.0, 5_000_000.0, 100.0);
+        let points: Vec<Point> = (0..10)
+            .map(|i| origin + Point::new(i as f64 * 0.01, -(i as f64) * 0.02, 0.5))
+            .collect();
+        let trajectory: Vec<pose::PoseStamped> = (0..3)
+            .map(|i| {
+                let pose =
+                    pose::Pose::from_6dof((origin.x + i as f64, origin.y, origin.z, 0.0, 0.0, 0.3));
+                let header = pose::Header {
+                    seq: i,
+                    stamp: pose::Time {
+                        sec: i as i32,
+                        nanosec: 0,
+                    },
+                    frame_id: String::from("utm"),
+                };
+                pose::PoseStamped::new(header, pose)
+            })
+            .collect();
+        let mut uvt = Uvt::from_points(points, trajectory.clone());
+        let original_points = uvt.map_points().unwrap();
+
+        let offset = uvt.recenter().unwrap();
+        assert!(offset.distance(&(origin + Point::new(1.0, 0.0, 0.0))) < 1e-9);
+        let centroid = uvt.trajectory_centroid().unwrap();
+        assert!(centroid.norm() < 1e-6);
+
+        uvt.transform(&pose::Pose::from_6dof((
+            offset.x, offset.y, offset.z, 0.0, 0.0, 0.0,
+        )))
+        .unwrap();
+        for (pt, original) in uvt.map_points().unwrap().iter().zip(&original_points) {
+            for (v, o) in [(pt.x, original.x), (pt.y, original.y), (pt.z, original.z)] {
+                assert!((v - o).abs() <= o.abs() * f32::EPSILON as f64);
+            }
+        }
+        for (p, original) in uvt.trajectory.iter().zip(&trajectory) {
+            assert!(p.pose.position.distance(&original.pose.position) < 1e-6);
+            assert_eq!(p.pose.orientation, original.pose.orientation);
+        }
+
+        // Without trajectory, the map is centered
+        let mut map_only = Uvt::from_points(
+            vec![Point::new(10.0, 0.0, 0.0), Point::new(20.0, 2.0, 0.0)],
+            vec![],
+        );
+        assert_eq!(map_only.recenter().unwrap(), Point::new(15.0, 1.0, 0.0));
+        assert_eq!(
+            map_only.map_points().unwrap(),
+            vec![Point::new(-5.0, -1.0, 0.0), Point::new(5.0, 1.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn test_bounds_and_centroid() {
+        let empty = Uvt::from_points(vec![], vec![]);
+        assert_eq!(empty.map_bounds(), None);
+        assert_eq!(empty.trajectory_bounds(), None);
+        assert_eq!(empty.trajectory_centroid(), None);
+
+        let mut uvt = Uvt::from_points(
+            vec![Point::new(1.0, -2.0, 0.5), Point::new(-3.0, 4.0, 0.25)],
+            vec![],
+        );
+        assert_eq!(
+            uvt.map_bounds(),
+            Some((Point::new(-3.0, -2.0, 0.25), Point::new(1.0, 4.0, 0.5)))
+        );
+
+        let position = Point::new(1.0, 2.0, 3.0);
+        uvt.append_pose(
+            pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0)),
+            pose::Time { sec: 0, nanosec: 0 },
+        );
+        assert_eq!(uvt.trajectory_bounds(), Some((position, position)));
+        assert_eq!(uvt.trajectory_centroid(), Some(position));
+
+        uvt.append_pose(
+            pose::Pose::from_6dof((3.0, 0.0, 3.0, 0.0, 0.0, 0.0)),
+            pose::Time { sec: 1, nanosec: 0 },
+        );
+        assert_eq!(
+            uvt.trajectory_bounds(),
+            Some((Point::new(1.0, 0.0, 3.0), Point::new(3.0, 2.0, 3.0)))
+        );
+        assert_eq!(uvt.trajectory_centroid(), Some(Point::new(2.0, 1.0, 3.0)));
+        assert_eq!(uvt.trajectory_length(), 2.0_f64.sqrt() * 2.0);
+
+        // Non-finite positions are skipped
+        uvt.append_pose(
+            pose::Pose::from_6dof((f64::NAN, 0.0, f64::INFINITY, 0.0, 0.0, 0.0)),
+            pose::Time { sec: 2, nanosec: 0 },
+        );
+        assert_eq!(
+            uvt.trajectory_bounds(),
+            Some((Point::new(1.0, 0.0, 3.0), Point::new(3.0, 2.0, 3.0)))
+        );
+        let nan = Uvt::from_points(vec![Point::new(f64::NAN, 0.0, 0.0)], vec![]);
+        assert_eq!(nan.map_bounds(), None);
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut uvt = Uvt::from_points(vec![Point::new(1.0, 2.0, 3.0)], vec![]);
+        uvt.append_pose(
+            pose::Pose::from_6dof((1.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+            pose::Time { sec: 0, nanosec: 0 },
+        );
+
+        // 90° yaw, then a translation of (10, 0, -1)
+        let t = pose::Pose::from_6dof((10.0, 0.0, -1.0, 0.0, 0.0, std::f64::consts::FRAC_PI_2));
+        uvt.transform(&t).unwrap();
+
+        let coords = uvt
+            .map_buffer()
+            .unwrap()
+            .clone()
+            .cast_into::<f64>()
+            .unwrap();
+        assert!(
+            Point::new(coords[0], coords[1], coords[2]).distance(&Point::new(8.0, 1.0, 2.0)) < 1e-6
+        );
+
+        let (x, y, z, roll, pitch, yaw) = uvt.trajectory[0].pose.to_6dof();
+        assert!(Point::new(x, y, z).distance(&Point::new(10.0, 1.0, -1.0)) < 1e-12);
+        assert!(roll.abs() < 1e-12 && pitch.abs() < 1e-12);
+        assert!((yaw - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_write_trajectory_kitti() {
+        let path = fixtures::temp_path("poses_kitti.txt");
+        let mut uvt = Uvt::from_points(vec![], vec![]);
+        uvt.write_trajectory_kitti(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        uvt.append_pose(
+            pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0)),
+            pose::Time { sec: 0, nanosec: 0 },
+        );
+        uvt.append_pose(
+            pose::Pose::from_6dof((-4.0, 0.5, 0.0, 0.0, 0.0, std::f64::consts::FRAC_PI_2)),
+            pose::Time { sec: 1, nanosec: 0 },
+        );
+        uvt.write_trajectory_kitti(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let expected = [
+            [1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 1.0, 3.0],
+            // 90° yaw
+            [0.0, -1.0, 0.0, -4.0, 1.0, 0.0, 0.0, 0.5, 0.0, 0.0, 1.0, 0.0],
+        ];
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), expected.len());
+        for (line, expected) in lines.iter().zip(expected) {
+            let values: Vec<f64> = line.split(' ').map(|v| v.parse().unwrap()).collect();
+            assert_eq!(values.len(), 12);
+            for (v, e) in values.iter().zip(expected) {
+                assert!((v - e).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_trajectory_csv() {
+        let path = fixtures::temp_path("trajectory.csv");
+        let mut uvt = Uvt::from_points(vec![], vec![]);
+        uvt.append_pose(
+            pose::Pose {
+                position: Point::new(1.0 / 3.0, -2.5, 1e-9),
+                orientation: pose::Quaternion::new(0.0, 0.0, 0.6, 0.8),
+            },
+            pose::Time {
+                sec: 1_700_000_000,
+                nanosec: 42,
+            },
+        );
+        uvt.append_pose(
+            pose::Pose::from_6dof((0.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+            pose::Time {
+                sec: 1_700_000_001,
+                nanosec: 0,
+            },
+        );
+        uvt.write_trajectory_csv(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "seq,sec,nanosec,x,y,z,qx,qy,qz,qw");
+        assert_eq!(
+            lines[1],
+            "0,1700000000,42,0.3333333333333333,-2.5,0.000000001,0,0,0.6,0.8"
+        );
+    }
+
+    /// Reads the vertices of a PLY file with `float` `x`, `y` and `z` properties only.
+    fn read_ply_vertices(path: &path::Path) -> Vec<Point> {
+        let content = fs::read(path).unwrap();
+        let header_end = b"end_header\n";
+        let data_start = content
+            .windows(header_end.len())
+            .position(|w| w == header_end)
+            .unwrap()
+            + header_end.len();
+        let header = std::str::from_utf8(&content[..data_start]).unwrap();
+        let n_vertices: usize = header
+            .lines()
+            .find_map(|line| line.strip_prefix("element vertex "))
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let values: Vec<f64> = if header.contains("format binary_little_endian 1.0") {
+            content[data_start..]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+                .collect()
+        } else {
+            std::str::from_utf8(&content[data_start..])
+                .unwrap()
+                .split_whitespace()
+                .map(|v| v.parse().unwrap())
+                .collect()
+        };
+        assert_eq!(values.len(), 3 * n_vertices);
+        values
+            .chunks_exact(3)
+            .map(|c| Point::new(c[0], c[1], c[2]))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_map_ply() {
+        let points = vec![
+            Point::new(1.0, 2.0, 3.0),
+            Point::new(-0.5, 0.25, 1e-3),
+            Point::new(0.0, 0.0, 0.0),
+        ];
+        let uvt = Uvt::from_points(points.clone(), vec![]);
+        let path = fixtures::temp_path("map.ply");
+
+        uvt.write_map_ply(&path, false).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("ply\nformat ascii 1.0\n"));
+        assert!(content.contains("\nelement vertex 3\n"));
+        let ascii_points = read_ply_vertices(&path);
+
+        uvt.write_map_ply(&path, true).unwrap();
+        let binary_points = read_ply_vertices(&path);
+        fs::remove_file(&path).unwrap();
+
+        for read in [ascii_points, binary_points] {
+            assert_eq!(read.len(), points.len());
+            for (pt, expected) in read.iter().zip(&points) {
+                assert!(pt.distance(expected) < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_map_pcd() {
+        let points = vec![Point::new(1.0, 2.0, 3.0), Point::new(-0.5, 0.25, 0.0)];
+        let path = fixtures::temp_path("map.pcd");
+
+        let uvt = Uvt::from_points(points.clone(), vec![]);
+        uvt.write_map_pcd(&path, false).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines[1..],
+            [
+                "VERSION 0.7",
+                "FIELDS x y z",
+                "SIZE 4 4 4",
+                "TYPE F F F",
+                "COUNT 1 1 1",
+                "WIDTH 2",
+                "HEIGHT 1",
+                "VIEWPOINT 0 0 0 1 0 0 0",
+                "POINTS 2",
+                "DATA ascii",
+                "1 2 3",
+                "-0.5 0.25 0",
+            ]
+        );
+
+        let mut attributes = vtkio::model::Attributes::new();
+        attributes
+            .point
+            .push(Attribute::scalars(INTENSITY_FIELD, 1).with_data(vec![10.0_f32, 20.0]));
+        let uvt = Uvt::new(
+            points_vtk(&points, attributes, String::new(), MapPrecision::Single),
+            vec![],
+        );
+        uvt.write_map_pcd(&path, true).unwrap();
+        let content = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let header_end = content
+            .windows(12)
+            .position(|w| w == b"DATA binary\n")
+            .unwrap()
+            + 12;
+        let header = std::str::from_utf8(&content[..header_end]).unwrap();
+        assert!(header.contains("\nFIELDS x y z intensity\nSIZE 4 4 4 4\nTYPE F F F F\n"));
+        assert!(header.contains("\nPOINTS 2\n"));
+        let values: Vec<f32> = content[header_end..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, [1.0, 2.0, 3.0, 10.0, -0.5, 0.25, 0.0, 20.0]);
+    }
+
+    #[test]
+    fn test_read_map_ply() {
+        let points = vec![
+            Point::new(1.0, 2.0, 3.0),
+            Point::new(-0.5, 0.25, 1e-3),
+            Point::new(0.0, 0.0, 0.0),
+        ];
+        let mut uvt = Uvt::from_points(points.clone(), vec![]);
+        uvt.map.title = String::from("Small map");
+        let path = fixtures::temp_path("read_map.ply");
+
+        for binary in [false, true] {
+            uvt.write_map_ply(&path, binary).unwrap();
+            let read = Uvt::read_map_ply(&path).unwrap();
+
+            assert_eq!(read.map.title, "Small map");
+            assert!(read.trajectory.is_empty());
+            let coords = map_values(&read);
+            assert_eq!(coords.len() / 3, points.len());
+            for (c, expected) in coords.chunks_exact(3).zip(&points) {
+                let pt = Point::new(c[0] as f64, c[1] as f64, c[2] as f64);
+                assert!(pt.distance(expected) < 1e-6);
+            }
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_with_trajectory_from_csv() {
+        let map_path = fixtures::temp_path("standalone_map.vtk");
+        let csv_path = fixtures::temp_path("standalone_trajectory.csv");
+        let mut uvt = timed_uvt();
+        uvt.map = uvt_data(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], "map").map;
+        uvt.write_trajectory_csv(&csv_path).unwrap();
+
+        // Legacy VTK maps are read in ASCII and binary
+        uvt.map.clone().export_ascii(&map_path).unwrap();
+        let ascii_map = Uvt::read_map_vtk(&map_path).unwrap();
+        uvt.map.clone().export_be(&map_path).unwrap();
+        let binary_map = Uvt::read_map_vtk(&map_path).unwrap();
+        assert_eq!(map_values(&ascii_map), map_values(&uvt));
+        assert_eq!(map_values(&binary_map), map_values(&uvt));
+
+        let read = binary_map
+            .with_trajectory_from_csv(&csv_path, "map")
+            .unwrap();
+        assert_eq!(read.trajectory, uvt.trajectory);
+
+        fs::write(&csv_path, "x,y,z\n1,2,3\n").unwrap();
+        let err = read.with_trajectory_from_csv(&csv_path, "map").unwrap_err();
+        assert!(matches!(err, UvtError::Parse { line: 1, .. }));
+
+        fs::remove_file(&map_path).unwrap();
+        fs::remove_file(&csv_path).unwrap();
+    }
+
+    #[test]
+    fn test_with_trajectory_from_csv_invalid_orientation() {
+        const CHILD_VAR: &str = "UVT_TEST_QUIET_CSV_CHILD";
+        const TEST_NAME: &str = "tests::test_with_trajectory_from_csv_invalid_orientation";
+
+        let csv_path = fixtures::temp_path(if std::env::var_os(CHILD_VAR).is_some() {
+            "invalid_orientation_child.csv"
+        } else {
+            "invalid_orientation.csv"
+        });
+        fs::write(
+            &csv_path,
+            "x,y,z,qx,qy,qz,qw\n1,2,3,0,0,0,0\n4,5,6,0,0,0,1\n",
+        )
+        .unwrap();
+        let map = uvt_data(vec![1.0, 2.0, 3.0], "map");
+
+        if std::env::var_os(CHILD_VAR).is_some() {
+            let uvt = map.with_trajectory_from_csv(&csv_path, "map").unwrap();
+            fs::remove_file(&csv_path).unwrap();
+            assert_eq!(uvt.num_poses(), 2);
+            return;
+        }
+
+        static WARNINGS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        let options = ReadOptions {
+            on_warning: Some(|message| WARNINGS.lock().unwrap().push(message.to_string())),
+            ..Default::default()
+        };
+        let uvt = map
+            .with_trajectory_from_csv_with_options(&csv_path, "map", &options)
+            .unwrap();
+        fs::remove_file(&csv_path).unwrap();
+
+        assert_eq!(
+            uvt.trajectory[0].pose.orientation,
+            pose::Quaternion::new(0.0, 0.0, 0.0, 1.0)
+        );
+        assert_eq!(WARNINGS.lock().unwrap().len(), 1);
+
+        // Without warning callback, nothing is printed
+        let output = quiet_child_output(TEST_NAME, CHILD_VAR);
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    }
+
+    #[test]
+    fn test_map_points_with() {
+        let points = [Point::new(1.0, 2.0, 3.0), Point::new(-0.5, 0.25, 0.0)];
+        let mut attributes = vtkio::model::Attributes::new();
+        attributes
+            .point
+            .push(Attribute::scalars(INTENSITY_FIELD, 1).with_data(vec![10.0_f32, 20.0]));
+        let uvt = Uvt::new(
+            points_vtk(&points, attributes, String::new(), MapPrecision::Single),
+            vec![],
+        );
+
+        assert_eq!(
+            uvt.map_points_with(INTENSITY_FIELD).unwrap(),
+            vec![(points[0], 10.0), (points[1], 20.0)]
+        );
+        assert_eq!(
+            uvt.map_points_with("z").unwrap(),
+            vec![(points[0], 3.0), (points[1], 0.0)]
+        );
+        assert!(matches!(
+            uvt.map_points_with(RING_FIELD),
+            Err(UvtError::Vtk(msg)) if msg == "Point data 'ring' not found in map"
+        ));
+    }
+
+    #[test]
+    fn test_pose_accessors() {
+        let mut uvt = uvt_data(vec![], "map");
+        let mut second = uvt.trajectory[0].clone();
+        second.header.seq = 1;
+        second.pose.position = Point::new(1.0, 2.0, 3.0);
+        uvt.trajectory.push(second);
+
+        assert_eq!(uvt.num_poses(), 2);
+        assert_eq!(
+            uvt.poses().map(|p| p.header.seq).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(uvt.pose_at(1), Some(&uvt.trajectory[1]));
+        assert_eq!(uvt.pose_at(2), None);
+        assert_eq!(uvt[1].pose.position, Point::new(1.0, 2.0, 3.0));
+    }
+
+    fn timed_uvt() -> Uvt {
+        let mut uvt = uvt_data(vec![], "map");
+        uvt.trajectory = (0..3)
+            .map(|i| {
+                let header = pose::Header {
+                    seq: i,
+                    stamp: pose::Time {
+                        sec: 10 + i as i32,
+                        nanosec: 0,
+                    },
+                    frame_id: String::from("map"),
+                };
+                let pose = pose::Pose::from_6dof((2.0 * i as f64, 0.0, 0.0, 0.0, 0.0, 0.0));
+                pose::PoseStamped::new(header, pose)
+            })
+            .collect();
+        uvt
+    }
+
+    #[test]
+    fn test_pose_at_time() {
+        let uvt = timed_uvt();
+
+        let exact = uvt
+            .pose_at_time(pose::Time {
+                sec: 11,
+                nanosec: 0,
+            })
+            .unwrap();
+        assert_eq!(exact, uvt.trajectory[1].pose);
+
+        let half = pose::Time {
+            sec: 11,
+            nanosec: 500_000_000,
+        };
+        let interpolated = uvt.pose_at_time(half).unwrap();
+        assert!(interpolated.position.distance(&Point::new(3.0, 0.0, 0.0)) < 1e-12);
+
+        assert!(
+            uvt.pose_at_time(pose::Time {
+                sec: 9,
+                nanosec: 999_999_999
+            })
+            .is_none()
+        );
+        assert!(
+            uvt.pose_at_time(pose::Time {
+                sec: 12,
+                nanosec: 1
+            })
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_resample() {
+        // 1 Hz trajectory, moving 2 units along X per second
+        let uvt = timed_uvt();
+
+        let resampled = uvt.resample(Duration::from_millis(500)).unwrap();
+
+        assert_eq!(resampled.len(), 5);
+        let stamps: Vec<i64> = resampled
+            .iter()
+            .map(|p| p.header.stamp.as_nanos())
+            .collect();
+        assert_eq!(
+            stamps,
+            (0..5)
+                .map(|i| 10_000_000_000 + i * 500_000_000)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(resampled[1].header.seq, 1);
+        assert_eq!(resampled[1].header.frame_id, "map");
+        assert_eq!(resampled[1].pose.position, Point::new(1.0, 0.0, 0.0));
+        assert_eq!(resampled[4].pose, uvt.trajectory[2].pose);
+
+        assert_eq!(
+            Error::from(uvt.resample(Duration::ZERO).unwrap_err()).kind(),
+            ErrorKind::InvalidInput
+        );
+        let mut unsorted = timed_uvt();
+        unsorted.trajectory.swap(0, 1);
+        assert!(matches!(
+            unsorted.resample(Duration::from_secs(1)).unwrap_err(),
+            UvtError::Trajectory(_)
+        ));
+    }
+
+    #[test]
+    fn test_slice_time() {
+        let uvt = timed_uvt();
+
+        let slice = uvt.slice_time(
+            pose::Time {
+                sec: 10,
+                nanosec: 1,
+            },
+            pose::Time {
+                sec: 12,
+                nanosec: 0,
+            },
+        );
+        assert_eq!(slice, uvt.trajectory[1..]);
+        assert!(
+            uvt.slice_time(
+                pose::Time {
+                    sec: 13,
+                    nanosec: 0
+                },
+                pose::Time {
+                    sec: 14,
+                    nanosec: 0
+                }
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_check_topic_counts() {
+        let topics = || vec![String::from("/map"), String::from("/odom")];
+        let options = ReadOptions::default();
+
+        let warning = check_topic_counts(("/mapp", 0), ("/odom", 500), topics, &options)
+            .unwrap()
+            .unwrap();
+        assert!(warning.contains("'/mapp'"));
+        assert!(warning.contains("available: /map, /odom"));
+
+        let warning = check_topic_counts(("/map", 3), ("/odm", 0), topics, &options)
+            .unwrap()
+            .unwrap();
+        assert!(warning.contains("'/odm'"));
+
+        // Both populated or both empty is fine
+        assert!(
+            check_topic_counts(("/map", 3), ("/odom", 500), topics, &options)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            check_topic_counts(("/map", 0), ("/odom", 0), topics, &options)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_read_mcap_strict_topics() {
+        let path = fixtures::temp_path("strict_topics.mcap");
+        let pose = pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0));
+        let odom = fixtures::odometry(
+            fixtures::MessageWriter::cdr(),
+            0,
+            pose::Time { sec: 1, nanosec: 0 },
+            "odom",
+            "base_link",
+            &pose,
+        );
+        fixtures::write_mcap(&path, &[("/odom", "nav_msgs/msg/Odometry", vec![odom])]);
+
+        static WARNINGS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        let lenient = ReadOptions {
+            on_warning: Some(|message| WARNINGS.lock().unwrap().push(message.to_string())),
+            ..Default::default()
+        };
+        let uvt = Uvt::read_mcap_with_options(&path, "/mapp", "/odom", &lenient);
+
+        let options = ReadOptions {
+            strict_topics: true,
+            ..Default::default()
+        };
+        let err = Uvt::read_mcap_with_options(&path, "/mapp", "/odom", &options)
+            .err()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(uvt.unwrap().num_poses(), 1);
+        let warnings = WARNINGS.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'/mapp'"));
+        assert!(warnings[0].contains("available: /odom"));
+
+        assert!(matches!(err, UvtError::MissingTopic { .. }));
+        assert!(err.to_string().contains("'/mapp'"));
+        assert!(err.to_string().contains("available: /odom"));
+    }
+
+    #[test]
+    fn test_read_mcap_pose_array() {
+        let path = fixtures::temp_path("pose_array.mcap");
+        let mut w = fixtures::MessageWriter::cdr();
+        w.header(0, pose::Time { sec: 5, nanosec: 0 }, "map").u32(3);
+        (0..3).for_each(|i| {
+            w.pose(&pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)));
+        });
+        let map = fixtures::pointcloud2(
+            fixtures::MessageWriter::cdr(),
+            &fixtures::xyz_pointcloud(&[Point::new(1.0, 2.0, 3.0)]),
+        );
+        fixtures::write_mcap(
+            &path,
+            &[
+                ("/map", "sensor_msgs/msg/PointCloud2", vec![map]),
+                (
+                    "/candidates",
+                    "geometry_msgs/msg/PoseArray",
+                    vec![w.finish()],
+                ),
+            ],
+        );
+
+        let options = ReadOptions {
+            trajectory_type: TrajectoryType::PoseArray,
+            ..Default::default()
+        };
+        let trajectory = Uvt::read_mcap_with_options(&path, "/map", "/candidates", &options)
+            .map(|uvt| uvt.trajectory);
+        fs::remove_file(&path).unwrap();
+
+        let trajectory = trajectory.unwrap();
+        assert_eq!(trajectory.len(), 3);
+        assert_eq!(trajectory[2].pose.position, Point::new(2.0, 0.0, 0.0));
+        assert!(trajectory.iter().all(|p| p.header.stamp.sec == 5));
+    }
+
+    #[test]
+    fn test_read_mcap_compressed() {
+        let odoms: Vec<Vec<u8>> = (0..3)
+            .map(|i| {
+                fixtures::odometry(
+                    fixtures::MessageWriter::cdr(),
+                    0,
+                    pose::Time { sec: i, nanosec: 0 },
+                    "odom",
+                    "base_link",
+                    &pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        let map = fixtures::pointcloud2(
+            fixtures::MessageWriter::cdr(),
+            &fixtures::xyz_pointcloud(&[Point::new(1.0, 2.0, 3.0)]),
+        );
+
+        for (name, compression) in [
+            ("zstd.mcap", mcap_crate::Compression::Zstd),
+            ("lz4.mcap", mcap_crate::Compression::Lz4),
+        ] {
+            let path = fixtures::temp_path(name);
+            fixtures::write_mcap_compressed(
+                &path,
+                &[
+                    ("/map", "sensor_msgs/msg/PointCloud2", vec![map.clone()]),
+                    ("/odom", "nav_msgs/msg/Odometry", odoms.clone()),
+                ],
+                Some(compression),
+            );
+            let uvt = Uvt::read_mcap(&path, "/map", "/odom");
+            fs::remove_file(&path).unwrap();
+
+            let uvt = uvt.unwrap();
+            assert_eq!(map_values(&uvt), vec![1.0, 2.0, 3.0]);
+            assert_eq!(uvt.trajectory.len(), 3);
+            assert_eq!(uvt.trajectory[2].pose.position, Point::new(2.0, 0.0, 0.0));
+            assert_eq!(uvt.trajectory[2].header.stamp.sec, 2);
+        }
+    }
+
+    #[test]
+    fn test_append_pose() {
+        let mut uvt = uvt_data(vec![], "odom");
+        uvt.trajectory[0].header.seq = 4;
+
+        (1..4).for_each(|i| {
+            uvt.append_pose(
+                pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                pose::Time { sec: i, nanosec: 0 },
+            )
+        });
+
+        let seqs: Vec<u32> = uvt.trajectory.iter().map(|p| p.header.seq).collect();
+        assert_eq!(seqs, vec![4, 5, 6, 7]);
+        assert!(uvt.trajectory.iter().all(|p| p.header.frame_id == "odom"));
+        assert_eq!(uvt.trajectory[3].header.stamp.sec, 3);
+        assert_eq!(uvt.trajectory[3].pose.position, Point::new(3.0, 0.0, 0.0));
+
+        uvt.trajectory.clear();
+        uvt.append_pose(
+            pose::Pose::from_6dof((0.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+            pose::Time { sec: 0, nanosec: 0 },
+        );
+        assert_eq!(uvt.trajectory[0].header.seq, 0);
+    }
+
+    #[test]
+    fn test_map_buffer() {
+        let uvt = uvt_data(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], "map");
+        let buffer = uvt.map_buffer().unwrap();
+        assert_eq!(buffer.len(), 3 * 2);
+        assert!(matches!(buffer, vtkio::IOBuffer::F32(v) if v[3..] == [4.0, 5.0, 6.0]));
+
+        let uvt = Uvt {
+            map: Vtk {
+                data: DataSet::inline(vtkio::model::ImageDataPiece {
+                    extent: vtkio::model::Extent::Dims([1, 1, 1]),
+                    data: vtkio::model::Attributes::new(),
+                }),
+                ..uvt.map
+            },
+            trajectory: vec![],
+        };
+        assert!(matches!(uvt.map_buffer(), Err(UvtError::Vtk(_))));
+    }
+
+    #[test]
+    fn test_map_points() {
+        let uvt = uvt_data(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], "map");
+        assert_eq!(
+            uvt.map_points().unwrap(),
+            vec![Point::new(1.0, 2.0, 3.0), Point::new(4.0, 5.0, 6.0)]
+        );
+
+        let mut f64_uvt = uvt_data(vec![], "map");
+        if let DataSet::PolyData { pieces, .. } = &mut f64_uvt.map.data
+            && let Piece::Inline(piece) = &mut pieces[0]
+        {
+            piece.points = vtkio::IOBuffer::F64(vec![0.5, -1.5, 1e-9]);
+        }
+        assert_eq!(
+            f64_uvt.map_points().unwrap(),
+            vec![Point::new(0.5, -1.5, 1e-9)]
+        );
+    }
+
+    #[test]
+    fn test_map_points_unsupported_dataset() {
+        let uvt = Uvt {
+            map: Vtk {
+                data: DataSet::inline(vtkio::model::ImageDataPiece {
+                    extent: vtkio::model::Extent::Dims([2, 2, 2]),
+                    data: vtkio::model::Attributes::new(),
+                }),
+                ..uvt_data(vec![], "map").map
+            },
+            trajectory: vec![],
+        };
+        let err = uvt.map_points().unwrap_err();
+        assert!(matches!(err, UvtError::Vtk(_)));
+        assert!(err.to_string().contains("not a PolyData dataset"));
+    }
+
+    #[test]
+    fn test_shared_uvt() {
+        let uvt = uvt_data(vec![1.0, 2.0, 3.0], "map");
+        let shared = uvt.shared();
+        let other = shared.clone();
+
+        assert!(Arc::ptr_eq(&shared.map, &other.map));
+        assert!(Arc::ptr_eq(&shared.trajectory, &other.trajectory));
+
+        // Still shared: the data is copied
+        let uvt = other.into_uvt();
+        assert_eq!(map_values(&uvt), vec![1.0, 2.0, 3.0]);
+
+        // Last handle: the data is moved out
+        assert_eq!(Arc::strong_count(&shared.map), 1);
+        let uvt = shared.into_uvt();
+        assert_eq!(uvt.trajectory.len(), 1);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut first = timed_uvt();
+        first.map = uvt_data(vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0], "map").map;
+        let mut second = Uvt::from_points(
+            vec![Point::new(5.0, 5.0, 5.0)],
+            timed_uvt().trajectory[..2].to_vec(),
+        );
+        second
+            .trajectory
+            .iter_mut()
+            .for_each(|pose| pose.header.stamp.sec += 100);
+
+        let merged = Uvt::merge(vec![first, second]).unwrap();
+
+        assert_eq!(merged.num_poses(), 3 + 2);
+        assert_eq!(merged.map_buffer().unwrap().len(), 3 * 3);
+        assert_eq!(merged.map_bounds().unwrap().1, Point::new(5.0, 5.0, 5.0));
+        let seqs: Vec<u32> = merged.poses().map(|p| p.header.seq).collect();
+        assert_eq!(seqs, [0, 1, 2, 3, 4]);
+        let secs: Vec<i32> = merged.poses().map(|p| p.header.stamp.sec).collect();
+        assert_eq!(secs, [10, 11, 12, 110, 111]);
+
+        let mut other_frame = timed_uvt();
+        other_frame.trajectory[0].header.frame_id = String::from("odom");
+        let err = Uvt::merge(vec![timed_uvt(), other_frame]).err().unwrap();
+        assert!(err.to_string().contains("frames 'map' and 'odom'"));
+        assert!(Uvt::merge(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_reframe_two_links() {
+        let mut uvt = uvt_data(vec![1.0, 0.0, 0.0], "base_link");
+        let transforms = vec![
+            (
+                String::from("map"),
+                String::from("odom"),
+                pose::Pose::from_6dof((1.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+            ),
+            (
+                String::from("odom"),
+                String::from("base_link"),
+                pose::Pose::from_6dof((0.0, 2.0, 0.0, 0.0, 0.0, std::f64::consts::FRAC_PI_2)),
+            ),
+        ];
+
+        uvt.reframe(&transforms, "map").unwrap();
+
+        let values = map_values(&uvt);
+        assert!((values[0] - 1.0).abs() < 1e-6);
+        assert!((values[1] - 3.0).abs() < 1e-6);
+        assert!(values[2].abs() < 1e-6);
+
+        let pose = &uvt.trajectory[0];
+        assert_eq!(pose.header.frame_id, "map");
+        let (x, y, z, roll, pitch, yaw) = pose.pose.to_6dof();
+        assert!((x - 1.0).abs() < 1e-9);
+        assert!((y - 2.0).abs() < 1e-9);
+        assert!(z.abs() < 1e-9);
+        assert!(roll.abs() < 1e-9);
+        assert!(pitch.abs() < 1e-9);
+        assert!((yaw - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+
+        // And back to the original frame
+        uvt.reframe(&transforms, "base_link").unwrap();
+        let values = map_values(&uvt);
+        assert!((values[0] - 1.0).abs() < 1e-6);
+        assert!(values[1].abs() < 1e-6);
+        assert!(values[2].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_reframe_disconnected() {
+        let mut uvt = uvt_data(vec![1.0, 0.0, 0.0], "base_link");
+        let transforms = vec![(
+            String::from("map"),
+            String::from("odom"),
+            pose::Pose::from_6dof((1.0, 0.0, 0.0, 0.0, 0.0, 0.0)),
+        )];
+
+        let err = uvt.reframe(&transforms, "map").unwrap_err();
+        assert_eq!(Error::from(err).kind(), ErrorKind::NotFound);
+        assert_eq!(uvt.trajectory[0].header.frame_id, "base_link");
     }
 }
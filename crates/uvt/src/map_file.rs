@@ -0,0 +1,527 @@
+//! This module provides functionality to parse standalone PLY and PCD map files.
+//!
+//! Both formats are converted into an unorganized PointCloud2, so that their points are
+//! decoded like the points of a recorded map.
+use std::io;
+use std::time::Duration;
+
+use crate::deserialization::MessageDataBuffer;
+use crate::pointcloud::{self, DataType, PointCloud2, PointField};
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Multiplies two sizes read from a header, failing with an `InvalidData` error on overflow.
+fn checked_size(a: usize, b: usize) -> Result<usize, io::Error> {
+    a.checked_mul(b)
+        .ok_or_else(|| invalid_data(format!("Size {} x {} is too large", a, b)))
+}
+
+/// Splits the text header of a map file from its body.
+///
+/// Header lines are read up to, and including, the first line matching `is_last`.
+///
+/// # Returns
+///
+/// The trimmed header lines, and the offset of the body in `bytes`.
+fn split_header(
+    bytes: &[u8],
+    is_last: impl Fn(&str) -> bool,
+) -> Result<(Vec<String>, usize), io::Error> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let end = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| offset + i);
+        let line = String::from_utf8_lossy(&bytes[offset..end])
+            .trim()
+            .to_string();
+        offset = (end + 1).min(bytes.len());
+        let last = is_last(&line);
+        lines.push(line);
+        if last {
+            return Ok((lines, offset));
+        }
+    }
+    Err(invalid_data(String::from("Missing end of header")))
+}
+
+/// Builds an unorganized cloud with one FLOAT64 field per `(name, count)` pair, from the
+/// values of the points laid out one after the other.
+fn f64_cloud(fields: &[(String, u32)], values: Vec<f64>) -> PointCloud2 {
+    let mut offset = 0;
+    let fields: Vec<PointField> = fields
+        .iter()
+        .map(|(name, count)| {
+            let field = PointField {
+                name: name.clone(),
+                offset,
+                datatype: DataType::FLOAT64,
+                count: *count,
+            };
+            offset += 8 * count;
+            field
+        })
+        .collect();
+    let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    binary_cloud(fields, offset, data, false)
+}
+
+/// Builds an unorganized cloud from packed binary points of `point_step` bytes.
+fn binary_cloud(
+    fields: Vec<PointField>,
+    point_step: u32,
+    data: Vec<u8>,
+    is_bigendian: bool,
+) -> PointCloud2 {
+    let n_points = data.len() / (point_step.max(1) as usize);
+    PointCloud2 {
+        header: crate::pose::Header {
+            seq: 0,
+            stamp: Duration::ZERO.into(),
+            frame_id: String::new(),
+        },
+        height: 1,
+        width: n_points as u32,
+        fields,
+        is_bigendian,
+        point_step,
+        row_step: point_step * n_points as u32,
+        data,
+        is_dense: false,
+    }
+}
+
+/// Checks that the cloud has `x`, `y` and `z` fields and a consistent layout.
+fn check_cloud(cloud: PointCloud2) -> Result<PointCloud2, io::Error> {
+    for axis in ["x", "y", "z"] {
+        if !cloud.fields.iter().any(|f| f.name == axis && f.count == 1) {
+            return Err(invalid_data(format!("Missing '{}' point field", axis)));
+        }
+    }
+    cloud.validate()?;
+    Ok(cloud)
+}
+
+/// Parses whitespace-separated ASCII values, requiring at least `expected` of them.
+fn parse_ascii_values(body: &[u8], expected: usize) -> Result<Vec<f64>, io::Error> {
+    let values = String::from_utf8_lossy(body)
+        .split_ascii_whitespace()
+        .map(|token| {
+            token
+                .parse::<f64>()
+                .map_err(|_| invalid_data(format!("Invalid value '{}'", token)))
+        })
+        .collect::<Result<Vec<f64>, io::Error>>()?;
+    if values.len() < expected {
+        return Err(invalid_data(format!(
+            "Expected {} values, got {}",
+            expected,
+            values.len()
+        )));
+    }
+    Ok(values)
+}
+
+/// A property of a PLY element.
+enum PlyProperty {
+    /// A single value, e.g. `property float x`.
+    Scalar(String, DataType),
+    /// A variable-length list, e.g. `property list uchar int vertex_indices`.
+    List(DataType, DataType),
+}
+
+/// An element of a PLY file, e.g. `element vertex 42`.
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+fn ply_type(name: &str) -> Result<DataType, io::Error> {
+    Ok(match name {
+        "char" | "int8" => DataType::INT8,
+        "uchar" | "uint8" => DataType::UINT8,
+        "short" | "int16" => DataType::INT16,
+        "ushort" | "uint16" => DataType::UINT16,
+        "int" | "int32" => DataType::INT32,
+        "uint" | "uint32" => DataType::UINT32,
+        "float" | "float32" => DataType::FLOAT32,
+        "double" | "float64" => DataType::FLOAT64,
+        _ => return Err(invalid_data(format!("Unknown PLY type '{}'", name))),
+    })
+}
+
+/// Parses a PLY file into an unorganized cloud holding the scalar properties of its vertices.
+///
+/// The `ascii`, `binary_little_endian` and `binary_big_endian` formats are supported.
+/// The other elements, such as faces, are skipped.
+///
+/// # Returns
+///
+/// The vertex cloud, and the first `comment` of the header, if any.
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error if the file is malformed, or if its vertices have
+/// no `x`, `y` or `z` property.
+pub fn parse_ply(bytes: &[u8]) -> Result<(PointCloud2, Option<String>), io::Error> {
+    if !bytes.starts_with(b"ply") {
+        return Err(invalid_data(String::from("Missing 'ply' magic number")));
+    }
+    let (header, body_offset) = split_header(bytes, |line| line == "end_header")?;
+
+    let mut format = None;
+    let mut comment = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in &header[1..header.len() - 1] {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", fmt, _] => format = Some(fmt.to_string()),
+            ["comment", ..] => {
+                comment.get_or_insert_with(|| line["comment".len()..].trim().to_string());
+            }
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid_data(format!("Invalid element count in '{}'", line)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_type, item_type, _] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data(format!("Property outside of an element: '{}'", line)))?
+                .properties
+                .push(PlyProperty::List(
+                    ply_type(count_type)?,
+                    ply_type(item_type)?,
+                )),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data(format!("Property outside of an element: '{}'", line)))?
+                .properties
+                .push(PlyProperty::Scalar(name.to_string(), ply_type(ty)?)),
+            ["obj_info", ..] | [] => {}
+            _ => return Err(invalid_data(format!("Invalid PLY header line '{}'", line))),
+        }
+    }
+
+    let vertex_index = elements
+        .iter()
+        .position(|e| e.name == "vertex")
+        .ok_or_else(|| invalid_data(String::from("Missing vertex element")))?;
+    let vertex = &elements[vertex_index];
+    let fields: Vec<(String, u32)> = vertex
+        .properties
+        .iter()
+        .filter_map(|p| match p {
+            PlyProperty::Scalar(name, _) => Some((name.clone(), 1)),
+            PlyProperty::List(..) => None,
+        })
+        .collect();
+
+    let body = &bytes[body_offset..];
+    // Every value takes at least one byte, which bounds the number of values to allocate
+    let capacity = checked_size(vertex.count, fields.len())?.min(body.len());
+    let big_endian = match format.as_deref() {
+        Some("ascii") => {
+            // Values of the elements preceding the vertices
+            let mut tokens = String::from_utf8_lossy(body)
+                .split_ascii_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<String>>()
+                .into_iter();
+            let mut next = || -> Result<f64, io::Error> {
+                let token = tokens
+                    .next()
+                    .ok_or_else(|| invalid_data(String::from("Unexpected end of PLY data")))?;
+                token
+                    .parse::<f64>()
+                    .map_err(|_| invalid_data(format!("Invalid value '{}'", token)))
+            };
+            let mut values = Vec::with_capacity(capacity);
+            for (i, element) in elements[..=vertex_index].iter().enumerate() {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match property {
+                            PlyProperty::Scalar(..) if i == vertex_index => values.push(next()?),
+                            PlyProperty::Scalar(..) => {
+                                next()?;
+                            }
+                            PlyProperty::List(..) => {
+                                for _ in 0..next()? as usize {
+                                    next()?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            return Ok((check_cloud(f64_cloud(&fields, values))?, comment));
+        }
+        Some("binary_little_endian") => false,
+        Some("binary_big_endian") => true,
+        Some(fmt) => return Err(invalid_data(format!("Unknown PLY format '{}'", fmt))),
+        None => return Err(invalid_data(String::from("Missing PLY format"))),
+    };
+
+    let mut buf = MessageDataBuffer::new(body.to_vec());
+    let mut values = Vec::with_capacity(capacity);
+    for (i, element) in elements[..=vertex_index].iter().enumerate() {
+        for _ in 0..element.count {
+            for property in &element.properties {
+                match property {
+                    PlyProperty::Scalar(_, ty) => {
+                        let value = pointcloud::read_value(&mut buf, ty, big_endian)?;
+                        if i == vertex_index {
+                            values.push(value);
+                        }
+                    }
+                    PlyProperty::List(count_type, item_type) => {
+                        let n = pointcloud::read_value(&mut buf, count_type, big_endian)?;
+                        let skipped = checked_size(n as usize, item_type.size_in_bytes())?;
+                        let end = buf
+                            .offset()
+                            .checked_add(skipped)
+                            .ok_or_else(|| invalid_data(String::from("PLY list is too large")))?;
+                        buf.set_offset(end)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok((check_cloud(f64_cloud(&fields, values))?, comment))
+}
+
+fn pcd_type(ty: &str, size: &str) -> Result<DataType, io::Error> {
+    Ok(match (ty, size) {
+        ("I", "1") => DataType::INT8,
+        ("U", "1") => DataType::UINT8,
+        ("I", "2") => DataType::INT16,
+        ("U", "2") => DataType::UINT16,
+        ("I", "4") => DataType::INT32,
+        ("U", "4") => DataType::UINT32,
+        ("F", "4") => DataType::FLOAT32,
+        ("F", "8") => DataType::FLOAT64,
+        _ => {
+            return Err(invalid_data(format!(
+                "Unsupported PCD field type '{}' of size {}",
+                ty, size
+            )));
+        }
+    })
+}
+
+/// Retrieves the values of the `key` line of a PCD header.
+fn pcd_entry<'a>(
+    entries: &'a [(&str, Vec<&'a str>)],
+    key: &str,
+) -> Result<&'a [&'a str], io::Error> {
+    entries
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, values)| values.as_slice())
+        .ok_or_else(|| invalid_data(format!("Missing {} line in PCD header", key)))
+}
+
+/// Parses a PCD file into an unorganized cloud.
+///
+/// The `ascii` and `binary` data formats are supported, `binary_compressed` is not.
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error if the file is malformed, or if it has no `x`, `y`
+/// or `z` field, and an `Unsupported` error for compressed data.
+pub fn parse_pcd(bytes: &[u8]) -> Result<PointCloud2, io::Error> {
+    let (header, body_offset) = split_header(bytes, |line| line.starts_with("DATA"))?;
+
+    let mut entries: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in header
+        .iter()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    {
+        let mut tokens = line.split_whitespace();
+        if let Some(key) = tokens.next() {
+            entries.push((key, tokens.collect()));
+        }
+    }
+    let entry = |key: &str| pcd_entry(&entries, key);
+
+    let names = entry("FIELDS")?;
+    let sizes = entry("SIZE")?;
+    let types = entry("TYPE")?;
+    let counts: Vec<u32> = match entry("COUNT") {
+        Ok(counts) => counts
+            .iter()
+            .map(|c| {
+                c.parse()
+                    .map_err(|_| invalid_data(format!("Invalid COUNT '{}'", c)))
+            })
+            .collect::<Result<_, _>>()?,
+        Err(_) => vec![1; names.len()],
+    };
+    if sizes.len() != names.len() || types.len() != names.len() || counts.len() != names.len() {
+        return Err(invalid_data(String::from(
+            "FIELDS, SIZE, TYPE and COUNT lines have different lengths",
+        )));
+    }
+    let n_points: usize = entry("POINTS")?
+        .first()
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| invalid_data(String::from("Invalid POINTS line")))?;
+
+    let body = &bytes[body_offset..];
+    match entry("DATA")?.first().copied() {
+        Some("ascii") => {
+            let fields: Vec<(String, u32)> = names
+                .iter()
+                .zip(&counts)
+                .map(|(name, &count)| (name.to_string(), count))
+                .collect();
+            let per_point: usize = counts.iter().map(|&c| c as usize).sum();
+            let n_values = checked_size(n_points, per_point)?;
+            let mut values = parse_ascii_values(body, n_values)?;
+            values.truncate(n_values);
+            check_cloud(f64_cloud(&fields, values))
+        }
+        Some("binary") => {
+            let mut offset = 0;
+            let mut fields = Vec::with_capacity(names.len());
+            for ((name, (ty, size)), &count) in
+                names.iter().zip(types.iter().zip(sizes)).zip(&counts)
+            {
+                let datatype = pcd_type(ty, size)?;
+                let too_large = || invalid_data(format!("PCD field '{}' is too large", name));
+                let field_size = (datatype.size_in_bytes() as u32)
+                    .checked_mul(count)
+                    .ok_or_else(too_large)?;
+                fields.push(PointField {
+                    name: name.to_string(),
+                    offset,
+                    datatype,
+                    count,
+                });
+                offset = offset.checked_add(field_size).ok_or_else(too_large)?;
+            }
+            let data_len = checked_size(n_points, offset as usize)?;
+            let data = body
+                .get(..data_len)
+                .ok_or_else(|| {
+                    invalid_data(format!(
+                        "Expected {} bytes of PCD data, got {}",
+                        data_len,
+                        body.len()
+                    ))
+                })?
+                .to_vec();
+            check_cloud(binary_cloud(fields, offset, data, false))
+        }
+        Some("binary_compressed") => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Compressed PCD data is not supported",
+        )),
+        Some(format) => Err(invalid_data(format!(
+            "Unknown PCD data format '{}'",
+            format
+        ))),
+        None => Err(invalid_data(String::from("Missing PCD data format"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xyz(cloud: &PointCloud2) -> Vec<[f64; 3]> {
+        cloud
+            .points()
+            .unwrap()
+            .iter()
+            .map(|pt| [pt["x"], pt["y"], pt["z"]])
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_ply_skips_other_elements() {
+        let header = "ply\nformat {} 1.0\ncomment Test map\nelement face 1\n\
+                      property list uchar int vertex_indices\nelement vertex 2\n\
+                      property double x\nproperty double y\nproperty double z\n\
+                      property uchar red\nend_header\n";
+
+        let ascii = header.replace("{}", "ascii") + "3 0 1 2\n1 2 3 255\n4 5 6 0\n";
+        let (cloud, comment) = parse_ply(ascii.as_bytes()).unwrap();
+        assert_eq!(xyz(&cloud), vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert_eq!(comment.as_deref(), Some("Test map"));
+
+        let mut binary = header.replace("{}", "binary_big_endian").into_bytes();
+        binary.push(3);
+        binary.extend([0_i32, 1, 2].iter().flat_map(|v| v.to_be_bytes()));
+        for pt in [[1.0_f64, 2.0, 3.0], [4.0, 5.0, 6.0]] {
+            binary.extend(pt.iter().flat_map(|v| v.to_be_bytes()));
+            binary.push(255);
+        }
+        let (cloud, _) = parse_ply(&binary).unwrap();
+        assert_eq!(xyz(&cloud), vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert_eq!(cloud.points().unwrap()[0]["red"], 255.0);
+
+        let err = parse_ply(ascii.replace("property double z\n", "").as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_parse_pcd() {
+        let header = "# .PCD v0.7 - Point Cloud Data file format\nVERSION 0.7\n\
+                      FIELDS x y z intensity\nSIZE 4 4 4 1\nTYPE F F F U\nCOUNT 1 1 1 1\n\
+                      WIDTH 2\nHEIGHT 1\nVIEWPOINT 0 0 0 1 0 0 0\nPOINTS 2\nDATA {}\n";
+
+        let ascii = header.replace("{}", "ascii") + "1 2 3 10\n4 5 6 20\n";
+        let cloud = parse_pcd(ascii.as_bytes()).unwrap();
+        assert_eq!(xyz(&cloud), vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert_eq!(cloud.points().unwrap()[1]["intensity"], 20.0);
+
+        let mut binary = header.replace("{}", "binary").into_bytes();
+        for (pt, intensity) in [([1.0_f32, 2.0, 3.0], 10_u8), ([4.0, 5.0, 6.0], 20)] {
+            binary.extend(pt.iter().flat_map(|v| v.to_le_bytes()));
+            binary.push(intensity);
+        }
+        let cloud = parse_pcd(&binary).unwrap();
+        assert_eq!(cloud.point_step, 13);
+        assert_eq!(xyz(&cloud), vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert_eq!(cloud.points().unwrap()[1]["intensity"], 20.0);
+
+        let compressed = header.replace("{}", "binary_compressed");
+        let err = parse_pcd(compressed.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_parse_oversized_header_counts() {
+        let ply = format!(
+            "ply\nformat ascii 1.0\nelement vertex {}\nproperty double x\n\
+             property double y\nproperty double z\nend_header\n1 2 3\n",
+            usize::MAX
+        );
+        let err = parse_ply(ply.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let ply = ply.replace(&usize::MAX.to_string(), &(usize::MAX / 3).to_string());
+        let err = parse_ply(ply.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let pcd = |data: &str| {
+            format!(
+                "FIELDS x y z\nSIZE 8 8 8\nTYPE F F F\nCOUNT 1 1 {}\nPOINTS {}\nDATA {}\n",
+                u32::MAX,
+                usize::MAX,
+                data
+            )
+        };
+        for data in ["ascii", "binary"] {
+            let err = parse_pcd(pcd(data).as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
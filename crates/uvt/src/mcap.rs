@@ -1,29 +1,53 @@
 use std::io::{Error, ErrorKind};
 
-use crate::deserialization::{BufferReader, MessageDataBuffer};
-use crate::pointcloud::{PointCloud2Deserializer, PointField};
+use crate::deserialization::{BufferReader, Endian, MessageDataBuffer};
+use crate::pointcloud::{LegacyPointCloudDeserializer, PointCloud2Deserializer, PointField};
+use crate::tf::{StampedTransform, TransformDeserializer};
 use crate::trajectory::TrajectoryDeserializer;
 use crate::{pointcloud, pose};
 
 pub struct McapDeserializer {
     buf: MessageDataBuffer,
+    endian: Endian,
 }
 
 impl McapDeserializer {
+    /// Creates a deserializer for a CDR message, including its 4-byte encapsulation header.
+    /// The byte order of the message is given by the representation identifier of the header:
+    /// even identifiers (`CDR_BE`, `PL_CDR_BE`, ...) are big-endian, odd ones are little-endian.
     pub fn new(data: Vec<u8>) -> Self {
+        let endian = if data.get(1).is_some_and(|id| id % 2 == 0) {
+            Endian::Big
+        } else {
+            Endian::Little
+        };
         Self {
             buf: MessageDataBuffer::new(data),
+            endian,
         }
     }
 }
 
+/// Use [`BufferReader::endian`] to follow the byte order of the encapsulation header.
 impl BufferReader for McapDeserializer {
-    fn read_u32_le(&mut self) -> Result<u32, std::io::Error> {
-        self.buf.read_u32_le()
+    fn endian(&self) -> Endian {
+        self.endian
     }
 
-    fn read_f64_le(&mut self) -> Result<f64, std::io::Error> {
-        self.buf.read_f64_le()
+    fn read_u32(&mut self, endian: Endian) -> Result<u32, std::io::Error> {
+        self.buf.read_u32(endian)
+    }
+
+    fn read_i32(&mut self, endian: Endian) -> Result<i32, std::io::Error> {
+        self.buf.read_i32(endian)
+    }
+
+    fn read_f32(&mut self, endian: Endian) -> Result<f32, std::io::Error> {
+        self.buf.read_f32(endian)
+    }
+
+    fn read_f64(&mut self, endian: Endian) -> Result<f64, std::io::Error> {
+        self.buf.read_f64(endian)
     }
 
     fn read_byte(&mut self) -> Result<u8, std::io::Error> {
@@ -35,58 +59,53 @@ impl BufferReader for McapDeserializer {
 
         // Align to next field
         // Respect 4-byte or 8-byte data alignment of CDR
-        let padding = (next_alignment - (1 % next_alignment)) % next_alignment;
-        if padding > 0 {
-            let _ = self.buf.slice(padding);
-        }
+        self.buf.align_cdr(next_alignment);
 
         Ok(b)
     }
 
-    fn slice(&mut self, length: usize) -> Option<&[u8]> {
-        self.buf.slice(length)
-    }
-
     fn read_lp_string(&mut self) -> Result<String, std::io::Error> {
-        let s = self.buf.read_lp_string()?;
-        Ok(s)
+        self.buf.align_cdr(4);
+        let strlen = self.read_u32(self.endian)? as usize;
+        self.buf.read_string(strlen)
     }
 
     fn read_lp_string_aligned(&mut self, next_alignment: usize) -> Result<String, std::io::Error> {
-        let s = self.buf.read_lp_string()?;
+        let s = self.read_lp_string()?;
 
         let strdata = s.trim_end_matches("\0").to_string();
 
         // Align to next field
         // Respect 4-byte or 8-byte data alignment of CDR
-        let strlen = s.len();
-        let padding = (next_alignment - (strlen % next_alignment)) % next_alignment;
-
-        if padding > 0 {
-            let _ = self.buf.slice(padding);
-        }
+        self.buf.align_cdr(next_alignment);
 
         Ok(strdata)
     }
 
-    fn read_null_terminated_string(&mut self) -> Result<String, std::io::Error> {
-        self.buf.read_null_terminated_string()
-    }
-
     fn read_header(&mut self) -> Result<pose::Header, std::io::Error> {
-        // Ignore first 4 bytes
-        // Used in CDR for endianness
-        let _ = self.buf.read_u32_le();
+        // Skip the encapsulation header
+        // Its byte order was detected on creation
+        let _ = self.buf.slice(4);
 
         Ok(pose::Header {
             seq: 0,
             stamp: pose::Time {
-                sec: self.buf.read_i32_le()?,
-                nanosec: self.read_u32_le()?,
+                sec: self.read_i32(self.endian)?,
+                nanosec: self.read_u32(self.endian)?,
             },
             frame_id: self.read_lp_string_aligned(4)?,
         })
     }
+
+    /// Sequence lengths are 4-byte aligned
+    fn read_lp_array<T>(
+        &mut self,
+        f: impl FnMut(&mut Self) -> Result<T, std::io::Error>,
+    ) -> Result<Vec<T>, std::io::Error> {
+        self.buf.align_cdr(4);
+        let n = self.read_u32(self.endian)? as usize;
+        self.read_array(n, f)
+    }
 }
 
 impl PointCloud2Deserializer for McapDeserializer {
@@ -94,28 +113,22 @@ impl PointCloud2Deserializer for McapDeserializer {
     fn read_point_field(&mut self) -> Result<pointcloud::PointField, std::io::Error> {
         Ok(PointField {
             name: self.read_lp_string_aligned(4)?,
-            offset: self.read_u32_le()?,
-            datatype: self.read_byte_aligned(4)?.into(),
-            count: self.read_u32_le()?,
+            offset: self.read_u32(self.endian)?,
+            datatype: self.read_byte_aligned(4)?.try_into()?,
+            count: self.read_u32(self.endian)?,
         })
     }
 
     /// Read all point fields
     fn read_point_fields(&mut self) -> Result<Vec<pointcloud::PointField>, std::io::Error> {
-        let n_fields = self.buf.read_u32_le()?;
-        let fields = (0..n_fields)
-            .into_iter()
-            .map(|_| self.read_point_field())
-            .into_iter()
-            .collect();
-        fields
+        self.read_lp_array(|d| d.read_point_field())
     }
 
     /// Read point cloud data
     fn read_data(&mut self) -> Result<Vec<u8>, std::io::Error> {
         // TODO: Rely on fields
         // Point cloud data, size is (row_step*height)
-        let data_len = self.buf.read_u32_le()?;
+        let data_len = self.read_u32(self.endian)?;
         let data: Vec<u8> = self
             .buf
             .slice(data_len as usize)
@@ -130,23 +143,45 @@ impl PointCloud2Deserializer for McapDeserializer {
     }
 }
 
+impl LegacyPointCloudDeserializer for McapDeserializer {
+    /// Read the array of 32-bit points
+    fn read_points32(&mut self) -> Result<Vec<pose::Point>, std::io::Error> {
+        self.read_lp_array(|d| {
+            Ok(pose::Point {
+                x: d.read_f32(d.endian)? as f64,
+                y: d.read_f32(d.endian)? as f64,
+                z: d.read_f32(d.endian)? as f64,
+            })
+        })
+    }
+
+    /// Read the array of named channels
+    fn read_channels(&mut self) -> Result<Vec<(String, Vec<f32>)>, std::io::Error> {
+        self.read_lp_array(|d| {
+            let name = d.read_lp_string()?.trim_end_matches("\0").to_string();
+            let values = d.read_lp_array(|d| d.read_f32(d.endian))?;
+            Ok((name, values))
+        })
+    }
+}
+
 impl TrajectoryDeserializer for McapDeserializer {
     /// Read position point
     fn read_position(&mut self) -> Result<pose::Point, std::io::Error> {
         Ok(pose::Point {
-            x: self.buf.read_f64_le()?,
-            y: self.buf.read_f64_le()?,
-            z: self.buf.read_f64_le()?,
+            x: self.read_f64(self.endian)?,
+            y: self.read_f64(self.endian)?,
+            z: self.read_f64(self.endian)?,
         })
     }
 
     /// Read pose orientation as a quaternion
     fn read_orientation(&mut self) -> Result<pose::Quaternion, std::io::Error> {
         Ok(pose::Quaternion {
-            x: self.buf.read_f64_le()?,
-            y: self.buf.read_f64_le()?,
-            z: self.buf.read_f64_le()?,
-            w: self.buf.read_f64_le()?,
+            x: self.read_f64(self.endian)?,
+            y: self.read_f64(self.endian)?,
+            z: self.read_f64(self.endian)?,
+            w: self.read_f64(self.endian)?,
         })
     }
 
@@ -154,19 +189,162 @@ impl TrajectoryDeserializer for McapDeserializer {
     /// 6 x 6 covariance matrix = 36 covariance values
     /// https://docs.ros.org/en/noetic/api/geometry_msgs/html/msg/PoseWithCovarianceStamped.html
     fn read_covariance(&mut self) -> Result<Vec<f64>, std::io::Error> {
-        (0..36)
-            .into_iter()
-            .map(|_| self.buf.read_f64_le())
-            .into_iter()
-            .collect()
+        self.read_array(36, |d| d.read_f64(d.endian))
     }
 
     /// Read a twist vector
     fn read_vector(&mut self) -> Result<pose::Vector3, std::io::Error> {
         Ok(pose::Vector3::new(
-            self.buf.read_f64_le()?,
-            self.buf.read_f64_le()?,
-            self.buf.read_f64_le()?,
+            self.read_f64(self.endian)?,
+            self.read_f64(self.endian)?,
+            self.read_f64(self.endian)?,
         ))
     }
+
+    /// Read a pose, aligned on its 8-byte values
+    fn read_pose(&mut self) -> Result<pose::Pose, std::io::Error> {
+        self.buf.align_cdr(8);
+        Ok(pose::Pose {
+            position: self.read_position()?,
+            orientation: self.read_orientation()?,
+        })
+    }
+
+    /// Read the array of poses
+    fn read_poses(&mut self) -> Result<Vec<pose::Pose>, std::io::Error> {
+        self.read_lp_array(|d| d.read_pose())
+    }
+
+    /// Read the array of stamped poses
+    fn read_stamped_poses(&mut self) -> Result<Vec<pose::PoseStamped>, std::io::Error> {
+        self.read_lp_array(|d| {
+            // Nested headers have no encapsulation header
+            d.align(4);
+            let header = pose::Header {
+                seq: 0,
+                stamp: pose::Time {
+                    sec: d.read_i32(d.endian)?,
+                    nanosec: d.read_u32(d.endian)?,
+                },
+                frame_id: d.read_lp_string()?.trim_end_matches("\0").to_string(),
+            };
+            Ok(pose::PoseStamped {
+                header,
+                pose: d.read_pose()?,
+            })
+        })
+    }
+}
+
+impl TransformDeserializer for McapDeserializer {
+    /// Read a single stamped transform
+    fn read_transform_stamped(&mut self) -> Result<StampedTransform, std::io::Error> {
+        self.buf.align_cdr(4);
+        let stamp = pose::Time {
+            sec: self.read_i32(self.endian)?,
+            nanosec: self.read_u32(self.endian)?,
+        };
+        let frame_id = self.read_lp_string()?.trim_end_matches("\0").to_string();
+        self.buf.align_cdr(4);
+        let child_frame_id = self.read_lp_string()?.trim_end_matches("\0").to_string();
+        self.buf.align_cdr(8);
+        let transform = pose::Pose {
+            position: self.read_position()?,
+            orientation: self.read_orientation()?,
+        };
+        Ok((stamp, frame_id, child_frame_id, transform))
+    }
+
+    /// Read the array of stamped transforms
+    fn read_transforms(&mut self) -> Result<Vec<StampedTransform>, std::io::Error> {
+        // Skip the encapsulation header
+        // Its byte order was detected on creation
+        let _ = self.buf.slice(4);
+
+        self.read_lp_array(|d| d.read_transform_stamped())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, MessageWriter};
+    use crate::pointcloud::parse_pointcloud;
+    use crate::trajectory::parse_trajectory;
+
+    fn check_odometry(msg: Vec<u8>) {
+        let pose = pose::Pose::from_6dof((1.5, -2.0, 3.25, 0.0, 0.0, 0.5));
+        let stamp = pose::Time {
+            sec: 1_700_000_000,
+            nanosec: 42,
+        };
+        let parsed = parse_trajectory(McapDeserializer::new(msg)).unwrap();
+
+        assert_eq!(parsed.header.stamp, stamp);
+        assert_eq!(parsed.header.frame_id, "map");
+        assert_eq!(parsed.pose.position, pose.position);
+        assert_eq!(parsed.pose.orientation, pose.orientation);
+    }
+
+    fn odometry(w: MessageWriter) -> Vec<u8> {
+        fixtures::odometry(
+            w,
+            0,
+            pose::Time {
+                sec: 1_700_000_000,
+                nanosec: 42,
+            },
+            "map",
+            "base_link",
+            &pose::Pose::from_6dof((1.5, -2.0, 3.25, 0.0, 0.0, 0.5)),
+        )
+    }
+
+    #[test]
+    fn test_little_endian_cdr() {
+        check_odometry(odometry(MessageWriter::cdr()));
+    }
+
+    #[test]
+    fn test_big_endian_cdr() {
+        let msg = odometry(MessageWriter::cdr_be());
+        assert_eq!(msg[1], 0x00);
+        check_odometry(msg);
+    }
+
+    #[test]
+    fn test_endian_from_cdr_header() {
+        let mut payload = vec![0x00, 0x00, 0x00, 0x02];
+        payload.extend_from_slice(&[0x00, 0x00, 0x00, 0x02]);
+        let mut d = McapDeserializer::new([&[0x00, 0x00, 0x00, 0x00][..], &payload].concat());
+        assert_eq!(d.endian(), Endian::Big);
+        d.buf.slice(4).unwrap();
+        assert_eq!(d.read_u32(d.endian()).unwrap(), 2);
+        assert_eq!(d.read_u32(Endian::Little).unwrap(), 0x0200_0000);
+
+        let mut d = McapDeserializer::new([&[0x00, 0x01, 0x00, 0x00][..], &payload].concat());
+        assert_eq!(d.endian(), Endian::Little);
+        d.buf.slice(4).unwrap();
+        assert_eq!(d.read_u32(d.endian()).unwrap(), 0x0200_0000);
+        assert_eq!(d.read_u32(Endian::Big).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_big_endian_cdr_pointcloud() {
+        let points = [
+            pose::Point::new(1.5, -2.0, 3.25),
+            pose::Point::new(-0.5, 4.0, 0.125),
+        ];
+        let cloud = fixtures::xyz_pointcloud(&points);
+        let msg = fixtures::pointcloud2(MessageWriter::cdr_be(), &cloud);
+        assert_eq!(msg[1], 0x00);
+
+        let parsed = parse_pointcloud(McapDeserializer::new(msg)).unwrap();
+
+        assert_eq!(parsed.header.frame_id, "map");
+        assert_eq!(parsed.width, 2);
+        assert_eq!(parsed.fields, cloud.fields);
+        assert_eq!(parsed.point_step, 12);
+        assert_eq!(Vec::<pose::Point>::try_from(parsed).unwrap(), points);
+    }
 }
@@ -1,7 +1,7 @@
 //! This module provides functionality to parse point cloud data from ROS messages.
 use std::collections::HashMap;
 
-use crate::deserialization::{BufferReader, MessageDataBuffer};
+use crate::deserialization::{BufferReader, Endian, MessageDataBuffer};
 use crate::pose;
 use std::io;
 
@@ -16,6 +16,15 @@ pub trait PointCloud2Deserializer: BufferReader {
     fn read_data(&mut self) -> Result<Vec<u8>, io::Error>;
 }
 
+/// Trait for deserializing legacy sensor_msgs/PointCloud messages.
+///# Methods
+///* `read_points32` - Reads the array of geometry_msgs/Point32 from the buffer.
+///* `read_channels` - Reads the array of sensor_msgs/ChannelFloat32 from the buffer, as (name, values) pairs.
+pub trait LegacyPointCloudDeserializer: BufferReader {
+    fn read_points32(&mut self) -> Result<Vec<pose::Point>, io::Error>;
+    fn read_channels(&mut self) -> Result<Vec<(String, Vec<f32>)>, io::Error>;
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum DataType {
     INT8 = 1,
@@ -67,8 +76,8 @@ pub fn parse_pointcloud<D: PointCloud2Deserializer>(
     let header = d.read_header()?;
 
     // 2D structure of the point cloud
-    let height = d.read_u32_le()?;
-    let width = d.read_u32_le()?;
+    let height = d.read_u32(d.endian())?;
+    let width = d.read_u32(d.endian())?;
 
     // Fields
     let fields = d.read_point_fields()?;
@@ -76,9 +85,9 @@ pub fn parse_pointcloud<D: PointCloud2Deserializer>(
     // Is this data bigendian?
     let is_bigendian = d.read_byte_aligned(4)? == 1;
     // Length of a point in bytes
-    let point_step = d.read_u32_le()?;
+    let point_step = d.read_u32(d.endian())?;
     // Length of a row in bytes
-    let row_step = d.read_u32_le()?;
+    let row_step = d.read_u32(d.endian())?;
 
     // Actual pointcloud data
     let data = d.read_data()?;
@@ -99,69 +108,987 @@ pub fn parse_pointcloud<D: PointCloud2Deserializer>(
     })
 }
 
+/// Parses a legacy sensor_msgs/PointCloud message using the provided deserializer.
+///
+/// The legacy cloud is converted into an unorganized PointCloud2, with FLOAT32
+/// `x`, `y` and `z` fields followed by one FLOAT32 field per channel.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance implementing the deserialization trait.
+///
+/// # Returns
+///
+/// A PointCloud2 structure equivalent to the legacy message.
+pub fn parse_pointcloud_legacy<D: LegacyPointCloudDeserializer>(
+    mut d: D,
+) -> Result<PointCloud2, std::io::Error> {
+    // Message header
+    let header = d.read_header()?;
+
+    // Points and their channels
+    let points = d.read_points32()?;
+    let channels = d.read_channels()?;
+
+    if let Some((name, values)) = channels.iter().find(|(_, v)| v.len() != points.len()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Channel '{}' has {} values for {} points",
+                name,
+                values.len(),
+                points.len()
+            ),
+        ));
+    }
+
+    let names: Vec<&str> = ["x", "y", "z"]
+        .into_iter()
+        .chain(channels.iter().map(|(name, _)| name.as_str()))
+        .collect();
+    let fields: Vec<PointField> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| PointField {
+            name: name.to_string(),
+            offset: 4 * i as u32,
+            datatype: DataType::FLOAT32,
+            count: 1,
+        })
+        .collect();
+    let point_step = 4 * fields.len() as u32;
+
+    let data: Vec<u8> = points
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &pt)| {
+            Into::<[f32; 3]>::into(pt)
+                .into_iter()
+                .chain(channels.iter().map(move |(_, values)| values[i]))
+        })
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+
+    Ok(PointCloud2 {
+        header,
+        height: 1,
+        width: points.len() as u32,
+        fields,
+        is_bigendian: false,
+        point_step,
+        row_step: point_step * points.len() as u32,
+        data,
+        is_dense: false,
+    })
+}
+
+impl DataType {
+    /// Returns the size in bytes of a single value of this type.
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            DataType::INT8 | DataType::UINT8 => 1,
+            DataType::INT16 | DataType::UINT16 => 2,
+            DataType::INT32 | DataType::UINT32 | DataType::FLOAT32 => 4,
+            DataType::FLOAT64 => 8,
+        }
+    }
+}
+
+/// Writes values in CDR, after the 4-byte encapsulation header.
+pub(crate) struct CdrWriter {
+    buf: Vec<u8>,
+    endian: Endian,
+}
+
+impl CdrWriter {
+    /// Starts a message whose values are written in the byte order `endian`.
+    pub(crate) fn new(endian: Endian) -> Self {
+        let representation = match endian {
+            Endian::Little => 0x01,
+            Endian::Big => 0x00,
+        };
+        Self {
+            buf: vec![0x00, representation, 0x00, 0x00],
+            endian,
+        }
+    }
+
+    /// Pads the buffer so that the next value starts at a multiple of `alignment`.
+    /// CDR alignment is relative to the end of the encapsulation header.
+    fn align(&mut self, alignment: usize) {
+        let padding = (alignment - (self.buf.len() - 4) % alignment) % alignment;
+        self.buf.extend(std::iter::repeat_n(0, padding));
+    }
+
+    /// Writes a number aligned to its size, given in both byte orders.
+    fn number<const N: usize>(&mut self, le: [u8; N], be: [u8; N]) -> &mut Self {
+        self.align(N);
+        self.buf.extend(match self.endian {
+            Endian::Little => le,
+            Endian::Big => be,
+        });
+        self
+    }
+
+    pub(crate) fn u8(&mut self, v: u8) -> &mut Self {
+        self.buf.push(v);
+        self
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) -> &mut Self {
+        self.number(v.to_le_bytes(), v.to_be_bytes())
+    }
+
+    pub(crate) fn i32(&mut self, v: i32) -> &mut Self {
+        self.number(v.to_le_bytes(), v.to_be_bytes())
+    }
+
+    /// Writes a string with its length, which counts its null terminator.
+    pub(crate) fn string(&mut self, s: &str) -> &mut Self {
+        self.u32(s.len() as u32 + 1);
+        self.buf.extend(s.as_bytes());
+        self.buf.push(0);
+        self
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.u32(bytes.len() as u32);
+        self.buf.extend(bytes);
+        self
+    }
+
+    /// Returns the serialized message, leaving the writer empty.
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+}
+
+/// Writers of the values that no serialized message of the crate holds, for the test fixtures.
+#[cfg(any(test, feature = "test-support"))]
+impl CdrWriter {
+    pub(crate) fn f32(&mut self, v: f32) -> &mut Self {
+        self.number(v.to_le_bytes(), v.to_be_bytes())
+    }
+
+    pub(crate) fn f64(&mut self, v: f64) -> &mut Self {
+        self.number(v.to_le_bytes(), v.to_be_bytes())
+    }
+}
+
 impl PointCloud2 {
+    /// Serializes the cloud as a ROS 2 sensor_msgs/msg/PointCloud2 message, in little-endian
+    /// CDR with its encapsulation header, as stored in MCAP files.
+    ///
+    /// ROS 2 headers have no sequence number, so `header.seq` is not serialized.
+    pub fn to_cdr_bytes(&self) -> Vec<u8> {
+        let mut w = CdrWriter::new(Endian::Little);
+        w.i32(self.header.stamp.sec)
+            .u32(self.header.stamp.nanosec)
+            .string(&self.header.frame_id)
+            .u32(self.height)
+            .u32(self.width)
+            .u32(self.fields.len() as u32);
+        for field in &self.fields {
+            w.string(&field.name)
+                .u32(field.offset)
+                .u8(field.datatype.clone() as u8)
+                .u32(field.count);
+        }
+        w.u8(self.is_bigendian as u8)
+            .u32(self.point_step)
+            .u32(self.row_step)
+            .bytes(&self.data)
+            .u8(self.is_dense as u8)
+            .finish()
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    /// Checks that the point layout is consistent with the fields and the data.
+    ///
+    /// Every field must fit in `point_step` bytes, and the data must hold a whole number of points.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error describing the first inconsistency found.
+    pub fn validate(&self) -> Result<(), io::Error> {
+        let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+
+        let point_size = self
+            .fields
+            .iter()
+            .map(|f| f.offset as usize + f.datatype.size_in_bytes() * f.count as usize)
+            .max()
+            .unwrap_or(0);
+        if (self.point_step as usize) < point_size {
+            return invalid(format!(
+                "point_step of {} bytes is smaller than the {} bytes spanned by the fields",
+                self.point_step, point_size
+            ));
+        }
+        if self.point_step == 0 {
+            return invalid(String::from("point_step is 0"));
+        }
+        if !self.data.len().is_multiple_of(self.point_step as usize) {
+            return invalid(format!(
+                "Data length of {} bytes is not a multiple of the point_step of {} bytes",
+                self.data.len(),
+                self.point_step
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn n_points(&self) -> usize {
         self.len() / (self.point_step as usize)
     }
 
-    pub fn points(&self) -> Vec<HashMap<String, f64>> {
-        let pt_len = self.point_step as usize;
-
+    /// Decodes the points of the cloud, as maps from field name to value.
+    ///
+    /// Each field is read at its `offset` from the start of the point, so padding between
+    /// fields is skipped. A field with a `count` greater than 1 is decoded as `count` values
+    /// named `name[0]`, `name[1]`, ...
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is too short for the fields of a point.
+    pub fn points(&self) -> Result<Vec<HashMap<String, f64>>, io::Error> {
         // Use a MessageDataBuffer to deserialize data
         let mut data_buf = MessageDataBuffer::new(self.data.to_vec());
 
-        let points = (0..self.n_points())
-            .into_iter()
+        (0..self.n_points())
             .map(|i| {
+                let point_base = i * self.point_step as usize;
                 // Create point from fields
                 let mut point = HashMap::new();
                 for field in &self.fields {
-                    let value = match field.datatype {
-                        DataType::FLOAT64 => data_buf.read_f64_le().unwrap(),
-                        DataType::FLOAT32 => data_buf.read_f32_le().unwrap() as f64,
-                        DataType::UINT16 => data_buf.read_u16_le().unwrap() as f64,
-                        _ => panic!("Unsupported datatype: {:?}", field.datatype),
-                    };
-                    point.insert(field.name.clone(), value);
+                    data_buf.set_offset(point_base + field.offset as usize)?;
+                    for k in 0..field.count {
+                        let value = read_value(&mut data_buf, &field.datatype, self.is_bigendian)?;
+                        let name = match field.count {
+                            1 => field.name.clone(),
+                            _ => format!("{}[{}]", field.name, k),
+                        };
+                        point.insert(name, value);
+                    }
                 }
-                point
+                Ok(point)
             })
-            .collect();
+            .collect()
+    }
+
+    /// Unpacks the colors of the points, from a packed `rgb` or `rgba` field.
+    ///
+    /// The field holds a FLOAT32, UINT32 or INT32 value whose bits pack the color channels,
+    /// as `0xAARRGGBB`. Without alpha channel, i.e. for an `rgb` field, points are opaque.
+    ///
+    /// # Returns
+    ///
+    /// The RGBA color of each point, or `None` if the cloud has no packed color field
+    /// or if its data is too short for the field.
+    pub fn colors(&self) -> Option<Vec<[u8; 4]>> {
+        let field = self.fields.iter().find(|f| {
+            (f.name == "rgb" || f.name == "rgba")
+                && f.count == 1
+                && matches!(
+                    f.datatype,
+                    DataType::FLOAT32 | DataType::UINT32 | DataType::INT32
+                )
+        })?;
+        let has_alpha = field.name == "rgba";
+
+        (0..self.n_points())
+            .map(|i| {
+                let start = i * self.point_step as usize + field.offset as usize;
+                let bytes: [u8; 4] = self.data.get(start..start + 4)?.try_into().ok()?;
+                let packed = match self.is_bigendian {
+                    true => u32::from_be_bytes(bytes),
+                    false => u32::from_le_bytes(bytes),
+                };
+                let [a, r, g, b] = packed.to_be_bytes();
+                Some([r, g, b, if has_alpha { a } else { 255 }])
+            })
+            .collect()
+    }
+
+    /// Extracts the points of the cloud, each paired with the value of the scalar field `field`
+    /// (e.g. `intensity`, `ring` or `t`).
+    ///
+    /// Points with a non-finite coordinate, such as the NaN placeholders of invalid returns,
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidInput` error if the cloud has no field named `field`.
+    pub fn to_points_with(&self, field: &str) -> Result<Vec<(pose::Point, f64)>, io::Error> {
+        if !self.fields.iter().any(|f| f.name == field) {
+            let available: Vec<&str> = self.fields.iter().map(|f| f.name.as_str()).collect();
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Field '{}' not found in pointcloud (available: {})",
+                    field,
+                    available.join(", ")
+                ),
+            ));
+        }
+
+        Ok(self
+            .points()?
+            .iter()
+            .map(|pt| {
+                (
+                    pose::Point {
+                        x: pt["x"],
+                        y: pt["y"],
+                        z: pt["z"],
+                    },
+                    pt[field],
+                )
+            })
+            .filter(|(pt, _)| pt.is_finite())
+            .collect())
+    }
+
+    /// Keeps only the points lying within the axis-aligned box spanned by `min` and `max`,
+    /// bounds included. Points with a non-finite coordinate are dropped.
+    ///
+    /// The kept points form an unorganized cloud, i.e. a single row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error if the cloud has no `x`, `y` or `z` field, or an error
+    /// if the data is too short for them.
+    pub fn retain_within(&mut self, min: &pose::Point, max: &pose::Point) -> Result<(), io::Error> {
+        let xyz: Vec<&PointField> = ["x", "y", "z"]
+            .iter()
+            .map(|name| {
+                self.fields.iter().find(|f| f.name == *name).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Field '{}' not found in pointcloud", name),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
 
-        points
+        let point_step = self.point_step as usize;
+        let mut data_buf = MessageDataBuffer::new(self.data.to_vec());
+        let mut kept = Vec::new();
+        for i in 0..self.n_points() {
+            let point_base = i * point_step;
+            let mut coords = [0.0; 3];
+            for (coord, field) in coords.iter_mut().zip(&xyz) {
+                data_buf.set_offset(point_base + field.offset as usize)?;
+                *coord = read_value(&mut data_buf, &field.datatype, self.is_bigendian)?;
+            }
+            let [x, y, z] = coords;
+            let point = pose::Point::new(x, y, z);
+            if point.is_finite() && point.is_within(min, max) {
+                kept.extend_from_slice(&self.data[point_base..point_base + point_step]);
+            }
+        }
+
+        self.height = 1;
+        self.width = (kept.len() / point_step) as u32;
+        self.row_step = kept.len() as u32;
+        self.data = kept;
+        Ok(())
     }
 }
 
-impl Into<Vec<pose::Point>> for PointCloud2 {
-    fn into(self) -> Vec<pose::Point> {
-        let points = self.points();
-        points
+/// Extracts the points of the cloud.
+///
+/// Points with a non-finite coordinate, such as the NaN placeholders of invalid returns,
+/// are skipped.
+impl TryFrom<PointCloud2> for Vec<pose::Point> {
+    type Error = io::Error;
+
+    fn try_from(val: PointCloud2) -> Result<Self, Self::Error> {
+        let points = val.points()?;
+        Ok(points
             .iter()
             .map(|pt_hashmap| pose::Point {
                 x: pt_hashmap["x"],
                 y: pt_hashmap["y"],
                 z: pt_hashmap["z"],
             })
+            .filter(|pt| pt.is_finite())
+            .collect())
+    }
+}
+
+/// Reads a single value of type `datatype` from the buffer, converted to f64.
+pub(crate) fn read_value(
+    buf: &mut MessageDataBuffer,
+    datatype: &DataType,
+    big_endian: bool,
+) -> Result<f64, io::Error> {
+    let endian = if big_endian {
+        Endian::Big
+    } else {
+        Endian::Little
+    };
+    Ok(match datatype {
+        DataType::INT8 => buf.read_byte()? as i8 as f64,
+        DataType::UINT8 => buf.read_byte()? as f64,
+        DataType::INT16 => buf.read_i16(endian)? as f64,
+        DataType::UINT16 => buf.read_u16(endian)? as f64,
+        DataType::INT32 => buf.read_i32(endian)? as f64,
+        DataType::UINT32 => buf.read_u32(endian)? as f64,
+        DataType::FLOAT32 => buf.read_f32(endian)? as f64,
+        DataType::FLOAT64 => buf.read_f64(endian)?,
+    })
+}
+
+/// Integer coordinates of a voxel in a grid of cubic voxels aligned on the origin.
+/// Can be used as a hash key to bucket points by voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoxelKey {
+    pub i: i64,
+    pub j: i64,
+    pub k: i64,
+}
+
+impl VoxelKey {
+    /// Returns the key of the voxel of edge length `leaf_size` containing `point`.
+    pub fn of(point: pose::Point, leaf_size: f64) -> Self {
+        Self {
+            i: (point.x / leaf_size).floor() as i64,
+            j: (point.y / leaf_size).floor() as i64,
+            k: (point.z / leaf_size).floor() as i64,
+        }
+    }
+
+    /// Returns the center of the voxel of edge length `leaf_size`.
+    pub fn center(&self, leaf_size: f64) -> pose::Point {
+        pose::Point {
+            x: (self.i as f64 + 0.5) * leaf_size,
+            y: (self.j as f64 + 0.5) * leaf_size,
+            z: (self.k as f64 + 0.5) * leaf_size,
+        }
+    }
+}
+
+/// Accumulates points into a grid of cubic voxels, keeping one centroid per voxel.
+///
+/// Points can be inserted in several batches, so the memory used only depends on
+/// the number of occupied voxels and not on the number of inserted points.
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    leaf_size: f64,
+    voxels: HashMap<VoxelKey, ([f64; 3], usize)>,
+}
+
+impl VoxelGrid {
+    /// Creates an empty grid of voxels of edge length `leaf_size`.
+    ///
+    /// Returns an `InvalidInput` error if `leaf_size` is not a positive finite number.
+    pub fn new(leaf_size: f64) -> Result<Self, io::Error> {
+        if !(leaf_size > 0.0 && leaf_size.is_finite()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "The voxel leaf size must be positive and finite, got {}",
+                    leaf_size
+                ),
+            ));
+        }
+        Ok(Self {
+            leaf_size,
+            voxels: HashMap::new(),
+        })
+    }
+
+    /// Adds points to the grid. Points with non-finite coordinates are skipped.
+    pub fn insert<I: IntoIterator<Item = pose::Point>>(&mut self, points: I) {
+        for pt in points {
+            if !pt.is_finite() {
+                continue;
+            }
+            let (sum, count) = self
+                .voxels
+                .entry(VoxelKey::of(pt, self.leaf_size))
+                .or_insert(([0.0; 3], 0));
+            sum[0] += pt.x;
+            sum[1] += pt.y;
+            sum[2] += pt.z;
+            *count += 1;
+        }
+    }
+
+    /// Returns the number of occupied voxels.
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    /// Checks whether no point has been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    /// Returns the centroid of the points of each occupied voxel, ordered by voxel.
+    pub fn points(&self) -> Vec<pose::Point> {
+        let mut voxels: Vec<_> = self.voxels.iter().collect();
+        voxels.sort_unstable_by_key(|(key, _)| (key.i, key.j, key.k));
+        voxels
+            .into_iter()
+            .map(|(_, (sum, count))| {
+                let n = *count as f64;
+                pose::Point::new(sum[0] / n, sum[1] / n, sum[2] / n)
+            })
             .collect()
     }
 }
 
-impl From<u8> for DataType {
-    fn from(byte: u8) -> Self {
+/// Downsamples points by replacing the points of each voxel of edge length
+/// `leaf_size` by their centroid.
+///
+/// Returns an `InvalidInput` error if `leaf_size` is not a positive finite number.
+pub fn voxel_downsample(
+    points: &[pose::Point],
+    leaf_size: f64,
+) -> Result<Vec<pose::Point>, io::Error> {
+    let mut grid = VoxelGrid::new(leaf_size)?;
+    grid.insert(points.iter().copied());
+    Ok(grid.points())
+}
+
+impl TryFrom<u8> for DataType {
+    type Error = io::Error;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
         match byte {
-            1_u8 => Self::INT8,
-            2_u8 => Self::UINT8,
-            3_u8 => Self::INT16,
-            4_u8 => Self::UINT16,
-            5_u8 => Self::INT32,
-            6_u8 => Self::UINT32,
-            7_u8 => Self::FLOAT32,
-            8_u8 => Self::FLOAT64,
-            _ => panic!("Unknown byte value"),
+            1_u8 => Ok(Self::INT8),
+            2_u8 => Ok(Self::UINT8),
+            3_u8 => Ok(Self::INT16),
+            4_u8 => Ok(Self::UINT16),
+            5_u8 => Ok(Self::INT32),
+            6_u8 => Ok(Self::UINT32),
+            7_u8 => Ok(Self::FLOAT32),
+            8_u8 => Ok(Self::FLOAT64),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown PointField datatype: {}", byte),
+            )),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bag::BagDeserializer;
+    use crate::fixtures::MessageWriter;
+    use crate::mcap::McapDeserializer;
+
+    #[test]
+    fn test_parse_bag_legacy_pointcloud() {
+        let stamp = pose::Time {
+            sec: 10,
+            nanosec: 0,
+        };
+        let msg = MessageWriter::ros1()
+            .header(3, stamp, "velodyne")
+            // Points
+            .u32(2)
+            .f32s(&[1.0, 2.0, 3.0, -1.0, -2.0, -3.0])
+            // Channels
+            .u32(1)
+            .string("intensity")
+            .u32(2)
+            .f32s(&[10.0, 20.0])
+            .finish();
+
+        let cloud = parse_pointcloud_legacy(BagDeserializer::new(msg)).unwrap();
+        assert_eq!(cloud.header.frame_id, "velodyne");
+        assert_eq!(cloud.n_points(), 2);
+
+        let points = cloud.points().unwrap();
+        assert_eq!(points[1]["intensity"], 20.0);
+
+        let points: Vec<pose::Point> = cloud.try_into().unwrap();
+        assert_eq!(
+            points,
+            vec![
+                pose::Point::new(1.0, 2.0, 3.0),
+                pose::Point::new(-1.0, -2.0, -3.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_mcap_legacy_pointcloud() {
+        let stamp = pose::Time {
+            sec: 10,
+            nanosec: 0,
+        };
+        let msg = MessageWriter::cdr()
+            .header(0, stamp, "lidar")
+            // Points
+            .u32(1)
+            .f32s(&[4.0, 5.0, 6.0])
+            // Channels
+            .u32(1)
+            .string("ring")
+            .u32(1)
+            .f32s(&[7.0])
+            .finish();
+
+        let cloud = parse_pointcloud_legacy(McapDeserializer::new(msg)).unwrap();
+        assert_eq!(cloud.header.frame_id, "lidar");
+
+        let points = cloud.points().unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0]["x"], 4.0);
+        assert_eq!(points[0]["y"], 5.0);
+        assert_eq!(points[0]["z"], 6.0);
+        assert_eq!(points[0]["ring"], 7.0);
+    }
+
+    #[test]
+    fn test_parse_legacy_pointcloud_channel_mismatch() {
+        let msg = MessageWriter::ros1()
+            .header(0, pose::Time { sec: 0, nanosec: 0 }, "velodyne")
+            .u32(1)
+            .f32s(&[1.0, 2.0, 3.0])
+            .u32(1)
+            .string("intensity")
+            .u32(0)
+            .finish();
+
+        let err = parse_pointcloud_legacy(BagDeserializer::new(msg)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_voxel_key() {
+        let leaf_size = 0.5;
+        let key = VoxelKey::of(pose::Point::new(0.1, 0.2, 0.3), leaf_size);
+        let same = VoxelKey::of(pose::Point::new(0.4, 0.45, 0.01), leaf_size);
+        let adjacent = VoxelKey::of(pose::Point::new(0.6, 0.2, 0.3), leaf_size);
+        let negative = VoxelKey::of(pose::Point::new(-0.1, 0.2, 0.3), leaf_size);
+
+        assert_eq!(key, same);
+        assert_ne!(key, adjacent);
+        assert_eq!(adjacent, VoxelKey { i: 1, j: 0, k: 0 });
+        assert_eq!(negative, VoxelKey { i: -1, j: 0, k: 0 });
+
+        let voxels: HashMap<VoxelKey, usize> = HashMap::from([(key, 1), (adjacent, 2)]);
+        assert_eq!(voxels[&same], 1);
+        assert_eq!(key.center(leaf_size), pose::Point::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn test_voxel_grid() {
+        let mut grid = VoxelGrid::new(1.0).unwrap();
+        grid.insert([
+            pose::Point::new(0.25, 0.25, 0.25),
+            pose::Point::new(0.75, 0.25, 0.5),
+        ]);
+        grid.insert([
+            pose::Point::new(1.5, 0.5, 0.5),
+            pose::Point::new(f64::NAN, 0.5, 0.5),
+        ]);
+
+        assert_eq!(grid.len(), 2);
+        assert_eq!(
+            grid.points(),
+            vec![
+                pose::Point::new(0.5, 0.25, 0.375),
+                pose::Point::new(1.5, 0.5, 0.5)
+            ]
+        );
+        assert_eq!(
+            voxel_downsample(&[pose::Point::new(0.2, 0.2, 0.2)], 1.0).unwrap(),
+            vec![pose::Point::new(0.2, 0.2, 0.2)]
+        );
+
+        for leaf_size in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let err = VoxelGrid::new(leaf_size).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_points_big_endian() {
+        let points = [
+            pose::Point::new(1.5, -2.0, 3.25),
+            pose::Point::new(-0.5, 4.0, 0.125),
+        ];
+        let mut cloud = crate::fixtures::xyz_pointcloud(&points);
+        cloud.is_bigendian = true;
+        cloud.data = points
+            .iter()
+            .flat_map(|&pt| Into::<[f32; 3]>::into(pt))
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+
+        assert_eq!(Vec::<pose::Point>::try_from(cloud).unwrap(), points);
+    }
+
+    #[test]
+    fn test_to_points_with() {
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[]);
+        cloud.fields.push(PointField {
+            name: String::from("intensity"),
+            offset: 12,
+            datatype: DataType::FLOAT32,
+            count: 1,
+        });
+        cloud.point_step = 16;
+        cloud.width = 2;
+        cloud.row_step = 32;
+        cloud.data = MessageWriter::ros1()
+            .f32s(&[1.0, 2.0, 3.0, 10.0, -1.0, -2.0, -3.0, 20.0])
+            .finish();
+
+        assert_eq!(
+            cloud.to_points_with("intensity").unwrap(),
+            vec![
+                (pose::Point::new(1.0, 2.0, 3.0), 10.0),
+                (pose::Point::new(-1.0, -2.0, -3.0), 20.0),
+            ]
+        );
+
+        let err = cloud.to_points_with("ring").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            "Field 'ring' not found in pointcloud (available: x, y, z, intensity)"
+        );
+    }
+
+    #[test]
+    fn test_retain_within() {
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[
+            pose::Point::new(0.0, 0.0, 0.0),
+            pose::Point::new(1.0, 1.0, 1.0),
+            pose::Point::new(5.0, 0.0, 0.0),
+            pose::Point::new(f64::NAN, 0.0, 0.0),
+            pose::Point::new(-3.0, 2.0, 0.5),
+        ]);
+        cloud
+            .retain_within(
+                &pose::Point::new(-1.0, -1.0, -1.0),
+                &pose::Point::new(2.0, 2.0, 2.0),
+            )
+            .unwrap();
+
+        assert_eq!(cloud.width, 2);
+        assert_eq!(cloud.row_step, 24);
+        assert!(cloud.validate().is_ok());
+        assert_eq!(
+            Vec::<pose::Point>::try_from(cloud).unwrap(),
+            vec![
+                pose::Point::new(0.0, 0.0, 0.0),
+                pose::Point::new(1.0, 1.0, 1.0)
+            ]
+        );
+
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[pose::Point::new(0.0, 0.0, 0.0)]);
+        cloud.fields.pop();
+        let err = cloud
+            .retain_within(
+                &pose::Point::new(-1.0, -1.0, -1.0),
+                &pose::Point::new(1.0, 1.0, 1.0),
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_datatype_try_from() {
+        assert_eq!(DataType::try_from(7).unwrap(), DataType::FLOAT32);
+
+        let err = DataType::try_from(9).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A PointField with a corrupt datatype fails to parse instead of panicking
+        let msg = MessageWriter::ros1()
+            .string("x")
+            .u32(0)
+            .u8(9)
+            .u32(1)
+            .finish();
+        let err = BagDeserializer::new(msg).read_point_field().unwrap_err();
+        assert_eq!(err.to_string(), "Unknown PointField datatype: 9");
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[
+            pose::Point::new(1.0, 2.0, 3.0),
+            pose::Point::new(4.0, 5.0, 6.0),
+        ]);
+        assert!(cloud.validate().is_ok());
+
+        cloud.point_step = 8;
+        let err = cloud.validate().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "point_step of 8 bytes is smaller than the 12 bytes spanned by the fields"
+        );
+
+        cloud.point_step = 16;
+        let err = cloud.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Data length of 24 bytes is not a multiple of the point_step of 16 bytes"
+        );
+    }
+
+    #[test]
+    fn test_points_mixed_datatypes() {
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[]);
+        cloud.fields.push(PointField {
+            name: String::from("t"),
+            offset: 12,
+            datatype: DataType::UINT32,
+            count: 1,
+        });
+        cloud.point_step = 16;
+        cloud.width = 2;
+        cloud.row_step = 32;
+        let mut data = MessageWriter::ros1();
+        for (xyz, t) in [
+            ([1.0, 2.0, 3.0], 100_000_u32),
+            ([-1.0, -2.0, -3.0], u32::MAX),
+        ] {
+            data.f32s(&xyz).u32(t);
+        }
+        cloud.data = data.finish();
+
+        let points = cloud.points().unwrap();
+        assert_eq!(points[0]["x"], 1.0);
+        assert_eq!(points[0]["t"], 100_000.0);
+        assert_eq!(points[1]["z"], -3.0);
+        assert_eq!(points[1]["t"], u32::MAX as f64);
+
+        // A truncated point is reported instead of panicking
+        cloud.fields[3].datatype = DataType::FLOAT64;
+        assert_eq!(
+            cloud.points().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_points_field_offsets() {
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[]);
+        cloud.fields.push(PointField {
+            name: String::from("intensity"),
+            offset: 16,
+            datatype: DataType::FLOAT32,
+            count: 1,
+        });
+        cloud.fields.push(PointField {
+            name: String::from("normal"),
+            offset: 20,
+            datatype: DataType::FLOAT32,
+            count: 3,
+        });
+        cloud.point_step = 32;
+        cloud.width = 2;
+        cloud.row_step = 64;
+        let mut data = MessageWriter::ros1();
+        for (xyz, intensity, normal) in [
+            ([1.0, 2.0, 3.0], 10.0, [0.0, 0.0, 1.0]),
+            ([-1.0, -2.0, -3.0], 20.0, [1.0, 0.0, 0.0]),
+        ] {
+            data.f32s(&xyz)
+                // Padding between z and intensity
+                .u32(u32::MAX)
+                .f32(intensity)
+                .f32s(&normal);
+        }
+        cloud.data = data.finish();
+        assert!(cloud.validate().is_ok());
+
+        let points = cloud.points().unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0]["intensity"], 10.0);
+        assert_eq!(points[0]["normal[2]"], 1.0);
+        assert_eq!(points[1]["x"], -1.0);
+        assert_eq!(points[1]["intensity"], 20.0);
+        assert_eq!(points[1]["normal[0]"], 1.0);
+        assert_eq!(points[1]["normal[2]"], 0.0);
+    }
+
+    #[test]
+    fn test_to_cdr_bytes() {
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[
+            pose::Point::new(1.0, 2.0, 3.0),
+            pose::Point::new(-4.0, 0.5, 6.0),
+        ]);
+        // Names and frame id whose lengths need padding before the next u32
+        cloud.header.frame_id = String::from("velodyne");
+        cloud.header.stamp = pose::Time {
+            sec: 12,
+            nanosec: 345,
+        };
+        cloud.fields.push(PointField {
+            name: String::from("ring"),
+            offset: 12,
+            datatype: DataType::UINT16,
+            count: 1,
+        });
+        cloud.point_step = 14;
+        cloud.row_step = 28;
+        cloud.data = cloud
+            .data
+            .chunks_exact(12)
+            .zip([3_u16, 7])
+            .flat_map(|(xyz, ring)| xyz.iter().copied().chain(ring.to_le_bytes()))
+            .collect();
+        cloud.is_dense = false;
+
+        let msg = crate::fixtures::pointcloud2(MessageWriter::cdr(), &cloud);
+        let parsed = parse_pointcloud(McapDeserializer::new(msg.clone())).unwrap();
+        assert_eq!(parsed, cloud);
+
+        let bytes = parsed.to_cdr_bytes();
+        assert_eq!(bytes, msg);
+        let reparsed = parse_pointcloud(McapDeserializer::new(bytes)).unwrap();
+        assert_eq!(reparsed, parsed);
+        assert_eq!(reparsed.points().unwrap()[1]["ring"], 7.0);
+    }
+
+    #[test]
+    fn test_colors() {
+        let mut cloud = crate::fixtures::xyz_pointcloud(&[]);
+        assert_eq!(cloud.colors(), None);
+
+        cloud.fields.push(PointField {
+            name: String::from("rgb"),
+            offset: 12,
+            datatype: DataType::FLOAT32,
+            count: 1,
+        });
+        cloud.point_step = 16;
+        cloud.width = 2;
+        cloud.row_step = 32;
+        // PCL packs the color bits of an rgb field in a float
+        let mut data = MessageWriter::ros1();
+        for (xyz, packed) in [([1.0, 2.0, 3.0], 0x00ff8000_u32), ([0.0; 3], 0x0012abef)] {
+            data.f32s(&xyz).f32(f32::from_bits(packed));
+        }
+        cloud.data = data.finish();
+        assert_eq!(
+            cloud.colors(),
+            Some(vec![[255, 128, 0, 255], [0x12, 0xab, 0xef, 255]])
+        );
+
+        cloud.fields[3].name = String::from("rgba");
+        cloud.fields[3].datatype = DataType::UINT32;
+        cloud.data[12..16].copy_from_slice(&0x80ff8000_u32.to_le_bytes());
+        assert_eq!(cloud.colors().unwrap()[0], [255, 128, 0, 128]);
+    }
+}
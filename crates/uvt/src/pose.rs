@@ -6,21 +6,83 @@ use std::{
 };
 
 use quaternion_core as quat;
-use quaternion_core::RotationSequence::XYZ;
-use quaternion_core::RotationType::Extrinsic;
+pub use quaternion_core::{RotationSequence, RotationType};
 
 #[cfg(feature = "glam-support")]
 use glam;
 
+#[cfg(feature = "nalgebra")]
+use nalgebra as na;
+
 // HEADER
 
 /// Analog to builtin_interfaces/msg/Time in ROS
-#[derive(Debug, Clone, Copy, PartialEq)]
+///
+/// Times are compared by their total number of nanoseconds, so that a `nanosec`
+/// overflowing a second compares equal to its normalized form.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Time {
     pub sec: i32,
     pub nanosec: u32,
 }
 
+impl Time {
+    /// Returns the total number of nanoseconds of the time.
+    pub fn as_nanos(&self) -> i64 {
+        self.sec as i64 * 1_000_000_000 + self.nanosec as i64
+    }
+
+    /// Creates a normalized time from a total number of nanoseconds, possibly negative.
+    /// The `nanosec` part is always within `[0, 1e9)`.
+    pub fn from_nanos(nanos: i64) -> Self {
+        Time {
+            sec: nanos.div_euclid(1_000_000_000) as i32,
+            nanosec: nanos.rem_euclid(1_000_000_000) as u32,
+        }
+    }
+}
+
+/// Computes the duration elapsed from `rhs` to `self`.
+/// As for [`std::time::Instant`], the duration saturates to zero if `rhs` is later than `self`.
+impl std::ops::Sub for Time {
+    type Output = Duration;
+
+    fn sub(self, rhs: Time) -> Duration {
+        let nanos = (self.as_nanos() - rhs.as_nanos()).max(0) as u64;
+        Duration::from_nanos(nanos)
+    }
+}
+
+/// Offsets the time by a duration. The result is normalized.
+impl std::ops::Add<Duration> for Time {
+    type Output = Time;
+
+    fn add(self, rhs: Duration) -> Time {
+        Time::from_nanos(self.as_nanos() + rhs.as_nanos() as i64)
+    }
+}
+
+impl PartialEq for Time {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_nanos() == other.as_nanos()
+    }
+}
+
+impl Eq for Time {}
+
+impl PartialOrd for Time {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Time {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_nanos().cmp(&other.as_nanos())
+    }
+}
+
 // Convert a standard Duration into a ROS-like Time message.
 impl From<Duration> for Time {
     fn from(duration: Duration) -> Self {
@@ -40,6 +102,7 @@ impl From<Time> for Duration {
 
 /// A header struct analog to std_msgs/msg/Header in ROS
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     /// The sequence number is a uint32 that is incremented with each message in ROS stamped messages.
     pub seq: u32,
@@ -53,6 +116,7 @@ pub struct Header {
 
 /// Analog to geometry_msgs/msg/Point in ROS
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -76,26 +140,121 @@ impl Point {
     pub fn coords(self) -> (f64, f64, f64) {
         (self.x, self.y, self.z)
     }
+    /// Checks whether the point lies within the axis-aligned box spanned by `min` and `max`.
+    /// Bounds are inclusive.
+    pub fn is_within(&self, min: &Point, max: &Point) -> bool {
+        (min.x..=max.x).contains(&self.x)
+            && (min.y..=max.y).contains(&self.y)
+            && (min.z..=max.z).contains(&self.z)
+    }
+    /// Checks whether all coordinates of the point are finite, i.e. neither NaN nor infinite.
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+    /// Computes the Euclidean distance to another point.
+    pub fn distance(&self, other: &Point) -> f64 {
+        (*self - *other).norm()
+    }
+    /// Computes the dot product with another point, seen as a vector.
+    pub fn dot(&self, other: &Point) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+    /// Computes the cross product with another point, seen as a vector.
+    pub fn cross(&self, other: &Point) -> Point {
+        Point {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+    /// Returns the Euclidean norm of the point, seen as a vector.
+    pub fn norm(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    /// Adds two points component-wise.
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Sub for Point {
+    type Output = Self;
+
+    /// Subtracts two points component-wise.
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Self;
+
+    /// Scales the point by a scalar value.
+    fn mul(self, rhs: f64) -> Self {
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
 }
 
-impl Into<[f32; 3]> for Point {
-    fn into(self) -> [f32; 3] {
-        [self.x as f32, self.y as f32, self.z as f32]
+impl From<Point> for [f32; 3] {
+    fn from(val: Point) -> Self {
+        [val.x as f32, val.y as f32, val.z as f32]
     }
 }
 
 /// Conversion from Point to glam::Vec3 if the "glam-support" feature is enabled.
 /// Used to show point clouds in rerun.
 #[cfg(feature = "glam-support")]
-impl Into<glam::Vec3> for Point {
-    fn into(self) -> glam::Vec3 {
-        glam::Vec3::new(self.x as f32, self.y as f32, self.z as f32)
+impl From<Point> for glam::Vec3 {
+    fn from(val: Point) -> Self {
+        glam::Vec3::new(val.x as f32, val.y as f32, val.z as f32)
+    }
+}
+
+/// Conversion from glam::Vec3 to Point if the "glam-support" feature is enabled.
+#[cfg(feature = "glam-support")]
+impl From<glam::Vec3> for Point {
+    fn from(val: glam::Vec3) -> Self {
+        Point::new(val.x as f64, val.y as f64, val.z as f64)
+    }
+}
+
+/// Conversion from Point to nalgebra::Point3 if the "nalgebra" feature is enabled.
+#[cfg(feature = "nalgebra")]
+impl From<Point> for na::Point3<f64> {
+    fn from(val: Point) -> Self {
+        na::Point3::new(val.x, val.y, val.z)
+    }
+}
+
+/// Conversion from nalgebra::Point3 to Point if the "nalgebra" feature is enabled.
+#[cfg(feature = "nalgebra")]
+impl From<na::Point3<f64>> for Point {
+    fn from(val: na::Point3<f64>) -> Self {
+        Point::new(val.x, val.y, val.z)
     }
 }
 
 /// A quaternion struct analog to geometry_msgs/msg/Quaternion in ROS.
 /// q = w + xi + yj + zk
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Quaternion {
     pub x: f64,
     pub y: f64,
@@ -114,9 +273,42 @@ impl From<quat::Quaternion<f64>> for Quaternion {
     }
 }
 
-impl Into<quat::Quaternion<f64>> for Quaternion {
-    fn into(self) -> quat::Quaternion<f64> {
-        (self.w, [self.x, self.y, self.z])
+impl From<Quaternion> for quat::Quaternion<f64> {
+    fn from(val: Quaternion) -> Self {
+        (val.w, [val.x, val.y, val.z])
+    }
+}
+
+/// Conversion from Quaternion to glam::Quat if the "glam-support" feature is enabled.
+#[cfg(feature = "glam-support")]
+impl From<Quaternion> for glam::Quat {
+    fn from(val: Quaternion) -> Self {
+        glam::Quat::from_xyzw(val.x as f32, val.y as f32, val.z as f32, val.w as f32)
+    }
+}
+
+/// Conversion from glam::Quat to Quaternion if the "glam-support" feature is enabled.
+#[cfg(feature = "glam-support")]
+impl From<glam::Quat> for Quaternion {
+    fn from(val: glam::Quat) -> Self {
+        Quaternion::new(val.x as f64, val.y as f64, val.z as f64, val.w as f64)
+    }
+}
+
+/// Conversion from Quaternion to nalgebra::UnitQuaternion if the "nalgebra" feature is enabled.
+/// The quaternion is normalized.
+#[cfg(feature = "nalgebra")]
+impl From<Quaternion> for na::UnitQuaternion<f64> {
+    fn from(val: Quaternion) -> Self {
+        na::UnitQuaternion::from_quaternion(na::Quaternion::new(val.w, val.x, val.y, val.z))
+    }
+}
+
+/// Conversion from nalgebra::UnitQuaternion to Quaternion if the "nalgebra" feature is enabled.
+#[cfg(feature = "nalgebra")]
+impl From<na::UnitQuaternion<f64>> for Quaternion {
+    fn from(val: na::UnitQuaternion<f64>) -> Self {
+        Quaternion::new(val.i, val.j, val.k, val.w)
     }
 }
 
@@ -164,7 +356,109 @@ impl Quaternion {
         if norm == 0.0 {
             panic!("Cannot normalize a quaternion with zero norm");
         }
-        self.clone() * (1.0 / norm)
+        *self * (1.0 / norm)
+    }
+
+    /// Returns a normalized (unit length) version of the quaternion,
+    /// or `None` if its norm is zero or not finite.
+    pub fn try_normalized(&self) -> Option<Self> {
+        let norm = self.norm();
+        (norm > 0.0 && norm.is_finite()).then(|| *self * (1.0 / norm))
+    }
+
+    /// Computes the dot product of two quaternions, seen as 4D vectors.
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Spherical linear interpolation between two unit quaternions.
+    /// Returns `self` for t = 0 and `other` (up to sign) for t = 1.
+    ///
+    /// The interpolation follows the shortest path between the two rotations, and
+    /// falls back to a normalized linear interpolation when they are nearly parallel.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+        let mut other = *other;
+        let mut dot = self.dot(&other);
+        // q and -q represent the same rotation, take the shortest path
+        if dot < 0.0 {
+            other = other * -1.0;
+            dot = -dot;
+        }
+
+        if dot > 0.9995 {
+            return (*self + (other - *self) * t).normalized();
+        }
+
+        let theta = dot.clamp(-1.0, 1.0).acos();
+        let sin_theta = theta.sin();
+        *self * (((1.0 - t) * theta).sin() / sin_theta) + other * ((t * theta).sin() / sin_theta)
+    }
+
+    /// Returns the rotation matrix of the quaternion, which is normalized first.
+    /// The matrix is row-major (`m[row][col]`) and rotates column vectors: p' = m * p.
+    /// Panics if the quaternion has zero length.
+    pub fn to_rotation_matrix(&self) -> [[f64; 3]; 3] {
+        let Quaternion { x, y, z, w } = self.normalized();
+        [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ]
+    }
+
+    /// Constructs a unit quaternion from a rotation matrix, with the convention of
+    /// [`Quaternion::to_rotation_matrix`]: row-major, rotating column vectors.
+    ///
+    /// The largest of the trace and the diagonal elements is used as pivot (Shepperd's method),
+    /// which avoids dividing by a small number for any rotation.
+    pub fn from_rotation_matrix(m: [[f64; 3]; 3]) -> Quaternion {
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let q = if trace > m[0][0] && trace > m[1][1] && trace > m[2][2] {
+            let s = 2.0 * (1.0 + trace).sqrt();
+            Quaternion {
+                w: s / 4.0,
+                x: (m[2][1] - m[1][2]) / s,
+                y: (m[0][2] - m[2][0]) / s,
+                z: (m[1][0] - m[0][1]) / s,
+            }
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0 * (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt();
+            Quaternion {
+                w: (m[2][1] - m[1][2]) / s,
+                x: s / 4.0,
+                y: (m[0][1] + m[1][0]) / s,
+                z: (m[0][2] + m[2][0]) / s,
+            }
+        } else if m[1][1] > m[2][2] {
+            let s = 2.0 * (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt();
+            Quaternion {
+                w: (m[0][2] - m[2][0]) / s,
+                x: (m[0][1] + m[1][0]) / s,
+                y: s / 4.0,
+                z: (m[1][2] + m[2][1]) / s,
+            }
+        } else {
+            let s = 2.0 * (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt();
+            Quaternion {
+                w: (m[1][0] - m[0][1]) / s,
+                x: (m[0][2] + m[2][0]) / s,
+                y: (m[1][2] + m[2][1]) / s,
+                z: s / 4.0,
+            }
+        };
+        q.normalized()
     }
 }
 
@@ -236,6 +530,7 @@ impl Mul<f64> for Quaternion {
 
 /// Analog to geometry_msgs/msg/Pose in ROS
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pose {
     /// Position represented as a Point (x, y, z)
     pub position: Point,
@@ -243,39 +538,194 @@ pub struct Pose {
     pub orientation: Quaternion,
 }
 
+/// Convention of a sequence of Euler angles: the rotation type (intrinsic or extrinsic)
+/// and the order of the rotation axes.
+///
+/// The three angles of a sequence are the rotations about its axes, in order.
+/// For instance, with `RotationSequence::ZYX`, the angles are (yaw, pitch, roll).
+#[derive(Debug, Clone, Copy)]
+pub struct EulerSeq {
+    pub rotation_type: RotationType,
+    pub sequence: RotationSequence,
+}
+
+impl EulerSeq {
+    /// Extrinsic XYZ angles, i.e. (roll, pitch, yaw), as used by the UVT format.
+    pub const EXTRINSIC_XYZ: EulerSeq =
+        EulerSeq::new(RotationType::Extrinsic, RotationSequence::XYZ);
+    /// Intrinsic ZYX angles, i.e. (yaw, pitch, roll).
+    pub const INTRINSIC_ZYX: EulerSeq =
+        EulerSeq::new(RotationType::Intrinsic, RotationSequence::ZYX);
+
+    pub const fn new(rotation_type: RotationType, sequence: RotationSequence) -> Self {
+        Self {
+            rotation_type,
+            sequence,
+        }
+    }
+}
+
+impl Default for EulerSeq {
+    fn default() -> Self {
+        Self::EXTRINSIC_XYZ
+    }
+}
+
 impl Pose {
     /// Generate a Pose from a tuple of 6 DOFs:
     /// (x, y, z, roll, pitch, yaw).
-    /// Angles are in radians
+    /// Angles are in radians, as extrinsic XYZ Euler angles.
     pub fn from_6dof(dofs: (f64, f64, f64, f64, f64, f64)) -> Self {
-        let pt = Point {
-            x: dofs.0,
-            y: dofs.1,
-            z: dofs.2,
-        };
-        let angles = [dofs.3, dofs.4, dofs.5];
-        let q = quat::from_euler_angles(Extrinsic, XYZ, angles);
-        Self {
-            position: pt,
-            orientation: q.into(),
-        }
+        Self::from_euler(
+            EulerSeq::EXTRINSIC_XYZ,
+            (dofs.3, dofs.4, dofs.5),
+            Point::new(dofs.0, dofs.1, dofs.2),
+        )
     }
 
     /// Generate a tuple of 6 DOFs:
     /// (x, y, z, roll, pitch, yaw).
-    /// Angles are in radians
+    /// Angles are in radians, as extrinsic XYZ Euler angles.
     pub fn to_6dof(self) -> (f64, f64, f64, f64, f64, f64) {
         let pt = self.position;
-        let q = self.orientation;
-        let [roll, pitch, yaw] = quat::to_euler_angles::<f64>(Extrinsic, XYZ, q.into());
+        let (roll, pitch, yaw) = self.to_euler(EulerSeq::EXTRINSIC_XYZ);
 
         (pt.x, pt.y, pt.z, roll, pitch, yaw)
     }
+
+    /// Generate a Pose from a position and Euler angles.
+    ///
+    /// # Arguments
+    ///
+    /// * `seq` - The convention of the Euler angles.
+    /// * `angles` - The rotations about the axes of `seq`, in order, in radians.
+    /// * `position` - The position of the pose.
+    pub fn from_euler(seq: EulerSeq, angles: (f64, f64, f64), position: Point) -> Self {
+        let angles = [angles.0, angles.1, angles.2];
+        let q = quat::from_euler_angles(seq.rotation_type, seq.sequence, angles);
+        Self {
+            position,
+            orientation: q.into(),
+        }
+    }
+
+    /// Converts the orientation of the pose into Euler angles.
+    ///
+    /// # Arguments
+    ///
+    /// * `seq` - The convention of the Euler angles.
+    ///
+    /// # Returns
+    ///
+    /// The rotations about the axes of `seq`, in order, in radians.
+    pub fn to_euler(&self, seq: EulerSeq) -> (f64, f64, f64) {
+        let [a, b, c] =
+            quat::to_euler_angles::<f64>(seq.rotation_type, seq.sequence, self.orientation.into());
+        (a, b, c)
+    }
+
+    /// Applies this pose, seen as a rigid transform, to a point.
+    /// p' = R * p + t
+    ///
+    /// A pose of frame B expressed in frame A maps the coordinates of a point in frame B
+    /// to its coordinates in frame A, as the transforms of the ROS TF tree.
+    pub fn transform_point(&self, p: Point) -> Point {
+        let q = self.orientation;
+        let rotated = q * Quaternion::new(p.x, p.y, p.z, 0.0) * q.conjugate();
+        Point::new(rotated.x, rotated.y, rotated.z) + self.position
+    }
+
+    /// Composes two poses, seen as rigid transforms.
+    /// The resulting transform applies `other` first, then `self`:
+    /// `a.compose(&b).transform_point(p) == a.transform_point(b.transform_point(p))`.
+    ///
+    /// With the pose of frame B in frame A as `self` and the pose of frame C in frame B
+    /// as `other`, the result is the pose of frame C in frame A.
+    pub fn compose(&self, other: &Pose) -> Pose {
+        Pose {
+            position: self.transform_point(other.position),
+            orientation: self.orientation * other.orientation,
+        }
+    }
+
+    /// Interpolates between two poses.
+    /// The position is interpolated linearly, and the orientation with [`Quaternion::slerp`].
+    /// Returns `self` for t = 0 and `other` for t = 1.
+    pub fn interpolate(&self, other: &Pose, t: f64) -> Pose {
+        Pose {
+            position: self.position + (other.position - self.position) * t,
+            orientation: self.orientation.slerp(&other.orientation, t),
+        }
+    }
+
+    /// Returns the inverse rigid transform, assuming a unit orientation:
+    /// p' = R^T * (p - t).
+    ///
+    /// The inverse of the pose of frame B in frame A is the pose of frame A in frame B,
+    /// so that `p.compose(&p.inverse())` is the identity.
+    pub fn inverse(&self) -> Pose {
+        let orientation = self.orientation.conjugate();
+        let rotation = Pose {
+            position: Point::new(0.0, 0.0, 0.0),
+            orientation,
+        };
+        Pose {
+            position: rotation.transform_point(self.position) * -1.0,
+            orientation,
+        }
+    }
+}
+
+/// Conversion from Pose to glam::Affine3A if the "glam-support" feature is enabled.
+/// Used to log oriented poses in rerun.
+#[cfg(feature = "glam-support")]
+impl From<Pose> for glam::Affine3A {
+    fn from(val: Pose) -> Self {
+        glam::Affine3A::from_rotation_translation(val.orientation.into(), val.position.into())
+    }
+}
+
+/// Conversion from glam::Affine3A to Pose if the "glam-support" feature is enabled.
+/// The scale of the transform, if any, is discarded.
+#[cfg(feature = "glam-support")]
+impl From<glam::Affine3A> for Pose {
+    fn from(val: glam::Affine3A) -> Self {
+        let (_, rotation, translation) = val.to_scale_rotation_translation();
+        Pose {
+            position: translation.into(),
+            orientation: rotation.into(),
+        }
+    }
+}
+
+/// Conversion from Pose to nalgebra::Isometry3 if the "nalgebra" feature is enabled.
+#[cfg(feature = "nalgebra")]
+impl From<Pose> for na::Isometry3<f64> {
+    fn from(val: Pose) -> Self {
+        let position = val.position;
+        na::Isometry3::from_parts(
+            na::Translation3::new(position.x, position.y, position.z),
+            val.orientation.into(),
+        )
+    }
+}
+
+/// Conversion from nalgebra::Isometry3 to Pose if the "nalgebra" feature is enabled.
+#[cfg(feature = "nalgebra")]
+impl From<na::Isometry3<f64>> for Pose {
+    fn from(val: na::Isometry3<f64>) -> Self {
+        let translation = val.translation.vector;
+        Pose {
+            position: Point::new(translation.x, translation.y, translation.z),
+            orientation: val.rotation.into(),
+        }
+    }
 }
 
 /// Analog to geometry_msgs/msg/PoseStamped in ROS.
 /// Combines a Header with a Pose, similar to ROS' geometry_msgs/msg/PoseStamped.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PoseStamped {
     pub header: Header,
     pub pose: Pose,
@@ -298,9 +748,9 @@ impl PoseStamped {
     }
 }
 
-impl Into<Pose> for PoseStamped {
-    fn into(self) -> Pose {
-        self.pose
+impl From<PoseStamped> for Pose {
+    fn from(val: PoseStamped) -> Self {
+        val.pose
     }
 }
 
@@ -308,6 +758,7 @@ impl Into<Pose> for PoseStamped {
 
 /// Analog to geometry_msgs/msg/Twist in ROS
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Twist {
     pub linear: Vector3,
     pub angular: Vector3,
@@ -315,6 +766,7 @@ pub struct Twist {
 
 /// Analog to geometry_msgs/msg/Vector3 in ROS
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector3 {
     x: f64,
     y: f64,
@@ -327,29 +779,52 @@ impl Vector3 {
     }
 }
 
+/// Serializes covariance matrices as sequences, since serde only supports arrays up to 32 items.
+#[cfg(feature = "serde")]
+mod covariance_serde {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(covariance: &[f64; 36], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(covariance)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[f64; 36], D::Error> {
+        let values = Vec::<f64>::deserialize(d)?;
+        let n = values.len();
+        values
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(n, &"36 covariance values"))
+    }
+}
+
 /// Analog to geometry_msgs/msg/PoseWithCovariance in ROS
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PoseWithCovariance {
     pub pose: Pose,
     /// Row-major representation of the 6x6 covariance matrix
     /// The orientation parameters use a fixed-axis representation.
     /// In order, the parameters are:
     /// (x, y, z, rotation about X axis, rotation about Y axis, rotation about Z axis)
+    #[cfg_attr(feature = "serde", serde(with = "covariance_serde"))]
     pub covariance: [f64; 36],
 }
 
 /// Analog to geometry_msgs/msg/TwistWithCovariance in ROS
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TwistWithCovariance {
     pub twist: Twist,
     /// Row-major representation of the 6x6 covariance matrix
     /// The orientation parameters use a fixed-axis representation.
     /// In order, the parameters are:
     /// (x, y, z, rotation about X axis, rotation about Y axis, rotation about Z axis)
+    #[cfg_attr(feature = "serde", serde(with = "covariance_serde"))]
     pub covariance: [f64; 36],
 }
 /// Analog to nav_msgs/msg/Odometry in ROS
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Odometry {
     pub header: Header,
     pub child_frame_id: String,
@@ -360,6 +835,7 @@ pub struct Odometry {
 // PATH
 /// Analog to nav_msgs/msg/Path in ROS
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Path {
     pub header: Header,
     pub poses: Vec<Pose>,
@@ -369,6 +845,22 @@ impl Path {
     pub fn len(&self) -> usize {
         self.poses.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.poses.is_empty()
+    }
+
+    /// Computes the length of the path, i.e. the sum of the Euclidean distances
+    /// between consecutive positions. Returns 0 for fewer than two poses.
+    pub fn length(&self) -> f64 {
+        path_length(self.poses.iter().map(|p| p.position))
+    }
+}
+
+/// Sums the Euclidean distances between consecutive points.
+pub(crate) fn path_length<I: IntoIterator<Item = Point>>(points: I) -> f64 {
+    let points: Vec<Point> = points.into_iter().collect();
+    points.windows(2).map(|w| w[0].distance(&w[1])).sum()
 }
 
 impl From<Vec<PoseStamped>> for Path {
@@ -429,6 +921,52 @@ mod tests {
         assert_eq!(duration, converted_duration);
     }
 
+    #[test]
+    fn test_time_ordering() {
+        let before = Time {
+            sec: 4,
+            nanosec: 999_999_999,
+        };
+        let after = Time { sec: 5, nanosec: 0 };
+        let overflowing = Time {
+            sec: 4,
+            nanosec: 1_000_000_000,
+        };
+
+        assert!(before < after);
+        assert_eq!(overflowing, after);
+        assert!(
+            Time {
+                sec: -1,
+                nanosec: 500_000_000
+            } < Time { sec: 0, nanosec: 0 }
+        );
+    }
+
+    #[test]
+    fn test_time_arithmetic() {
+        let start = Time {
+            sec: 4,
+            nanosec: 750_000_000,
+        };
+        let end = Time {
+            sec: 6,
+            nanosec: 250_000_000,
+        };
+
+        assert_eq!(end - start, Duration::from_millis(1500));
+        assert_eq!(start - end, Duration::ZERO);
+        assert_eq!(start + Duration::from_millis(1500), end);
+
+        let negative = Time {
+            sec: -2,
+            nanosec: 250_000_000,
+        };
+        assert_eq!(start - negative, Duration::from_millis(6500));
+        let shifted = negative + Duration::from_millis(1000);
+        assert_eq!((shifted.sec, shifted.nanosec), (-1, 250_000_000));
+    }
+
     #[test]
     fn test_point_creation() {
         let point_constructor = Point {
@@ -449,6 +987,35 @@ mod tests {
         assert_eq!(point_constructor, point_tuple);
     }
 
+    #[test]
+    fn test_point_operations() {
+        let p1 = Point::new(1.0, 2.0, 3.0);
+        let p2 = Point::new(4.0, 6.0, 3.0);
+
+        assert_eq!(p1 + p2, Point::new(5.0, 8.0, 6.0));
+        assert_eq!(p2 - p1, Point::new(3.0, 4.0, 0.0));
+        assert_eq!(p1 * 2.0, Point::new(2.0, 4.0, 6.0));
+        assert_eq!(p1.distance(&p2), 5.0);
+        assert_eq!(p2.distance(&p1), 5.0);
+        assert_eq!(p1.dot(&p2), 25.0);
+    }
+
+    #[test]
+    fn test_point_cross() {
+        let x = Point::new(1.0, 0.0, 0.0);
+        let y = Point::new(0.0, 1.0, 0.0);
+
+        assert_eq!(x.cross(&y), Point::new(0.0, 0.0, 1.0));
+        assert_eq!(y.cross(&x), Point::new(0.0, 0.0, -1.0));
+        assert_eq!(x.cross(&x), Point::new(0.0, 0.0, 0.0));
+
+        let p1 = Point::new(1.0, 2.0, 3.0);
+        let p2 = Point::new(-2.0, 0.5, 4.0);
+        let cross = p1.cross(&p2);
+        assert_eq!(cross.dot(&p1), 0.0);
+        assert_eq!(cross.dot(&p2), 0.0);
+    }
+
     #[test]
     fn test_quaternion_creation() {
         let q_constructor = Quaternion {
@@ -539,6 +1106,117 @@ mod tests {
         assert_eq!(q2 * 2.0, q2_scaled);
     }
 
+    #[test]
+    fn test_rotation_matrix_round_trip() {
+        for q in quaternion_data() {
+            let q = q.normalized();
+            let converted = Quaternion::from_rotation_matrix(q.to_rotation_matrix());
+
+            // q and -q represent the same rotation
+            let distance = (converted - q).norm().min((converted * -1.0 - q).norm());
+            assert!(distance < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_rotation_matrix_convention() {
+        // 90° around Z maps the X axis onto the Y axis
+        let [_, _, q3, _] = quaternion_data();
+        let m = q3.to_rotation_matrix();
+        let expected = [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+        for (row, expected_row) in m.iter().zip(expected) {
+            for (v, expected_v) in row.iter().zip(expected_row) {
+                assert!((v - expected_v).abs() < 1e-12);
+            }
+        }
+
+        let pose = Pose {
+            position: Point::new(0.0, 0.0, 0.0),
+            orientation: q3,
+        };
+        let p = Point::new(1.0, 2.0, 3.0);
+        let rotated = pose.transform_point(p);
+        let expected = Point::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z,
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z,
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z,
+        );
+        assert!(rotated.distance(&expected) < 1e-12);
+    }
+
+    #[test]
+    fn test_slerp() {
+        let identity = Quaternion::new(0.0, 0.0, 0.0, 1.0);
+        let [_, _, q3, q4] = quaternion_data();
+
+        assert!((identity.slerp(&q3, 0.0) - identity).norm() < 1e-12);
+        assert!((identity.slerp(&q3, 1.0) - q3).norm() < 1e-12);
+        assert!((q3.slerp(&q4, 0.0) - q3).norm() < 1e-12);
+        assert!((q3.slerp(&q4, 1.0) - q4).norm() < 1e-12);
+
+        // Halfway between no rotation and 90° around Z is 45° around Z
+        let half = identity.slerp(&q3, 0.5);
+        let (_, _, _, roll, pitch, yaw) = Pose {
+            position: Point::new(0.0, 0.0, 0.0),
+            orientation: half,
+        }
+        .to_6dof();
+        assert!(roll.abs() < 1e-12);
+        assert!(pitch.abs() < 1e-12);
+        assert!((yaw - std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+
+        // Shortest path, with -q3 being the same rotation as q3
+        let half_flipped = identity.slerp(&(q3 * -1.0), 0.5);
+        assert!((half_flipped - half).norm() < 1e-12);
+
+        // Nearly parallel quaternions
+        let close = Quaternion::new(0.0, 0.0, 1e-6, 1.0).normalized();
+        assert!((identity.slerp(&close, 0.5).norm() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pose_interpolate() {
+        let start = Pose::from_6dof((0.0, 0.0, 0.0, 0.0, 0.0, 0.0));
+        let end = Pose::from_6dof((2.0, 4.0, -2.0, 0.0, 0.0, std::f64::consts::FRAC_PI_2));
+
+        let mid = start.interpolate(&end, 0.5);
+        let (x, y, z, _, _, yaw) = mid.to_6dof();
+        assert_eq!((x, y, z), (1.0, 2.0, -1.0));
+        assert!((yaw - std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+
+        assert_eq!(start.interpolate(&end, 0.0), start);
+        assert_eq!(start.interpolate(&end, 1.0).position, end.position);
+    }
+
+    #[test]
+    fn test_transform_point_translation() {
+        let translation = Pose::from_6dof((1.0, -2.0, 3.0, 0.0, 0.0, 0.0));
+
+        assert_eq!(
+            translation.transform_point(Point::new(0.5, 0.5, 0.5)),
+            Point::new(1.5, -1.5, 3.5)
+        );
+    }
+
+    #[test]
+    fn test_compose_inverse() {
+        let p = Pose::from_6dof((1.0, -2.0, 3.0, 0.3, -0.2, 1.2));
+        let q = Pose::from_6dof((-4.0, 0.5, 2.0, -1.0, 0.4, 0.1));
+
+        for identity in [p.compose(&p.inverse()), p.inverse().compose(&p)] {
+            let (x, y, z, roll, pitch, yaw) = identity.to_6dof();
+            for v in [x, y, z, roll, pitch, yaw] {
+                assert!(v.abs() < 1e-12);
+            }
+        }
+
+        // Composition applies the right-hand side first
+        let pt = Point::new(0.25, 4.0, -1.0);
+        let composed = p.compose(&q).transform_point(pt);
+        let chained = p.transform_point(q.transform_point(pt));
+        assert!(composed.distance(&chained) < 1e-12);
+    }
+
     #[test]
     fn test_path_len() {
         let path = Path {
@@ -552,4 +1230,119 @@ mod tests {
 
         assert_eq!(path.len(), 6);
     }
+
+    #[test]
+    fn test_path_length() {
+        let path_through = |positions: &[(f64, f64, f64)]| Path {
+            header: Header {
+                frame_id: String::from("map"),
+                seq: 0,
+                stamp: Duration::from_secs(0).into(),
+            },
+            poses: positions
+                .iter()
+                .map(|&(x, y, z)| Pose::from_6dof((x, y, z, 0.0, 0.0, 0.0)))
+                .collect(),
+        };
+
+        assert_eq!(path_through(&[]).length(), 0.0);
+        assert_eq!(path_through(&[(1.0, 2.0, 3.0)]).length(), 0.0);
+
+        // Straight line
+        let straight = path_through(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (3.0, 0.0, 0.0)]);
+        assert_eq!(straight.length(), 3.0);
+
+        // Right angle
+        let right_angle = path_through(&[(0.0, 0.0, 0.0), (3.0, 0.0, 0.0), (3.0, 4.0, 0.0)]);
+        assert_eq!(right_angle.length(), 7.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let pose = PoseStamped::new(
+            Header {
+                seq: 3,
+                stamp: Time {
+                    sec: 1_700_000_000,
+                    nanosec: 42,
+                },
+                frame_id: String::from("map"),
+            },
+            Pose::from_6dof((1.5, -2.0, 3.25, 0.1, 0.2, 0.3)),
+        );
+
+        let json = serde_json::to_string(&pose).unwrap();
+        let parsed: PoseStamped = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed, pose);
+    }
+
+    #[cfg(feature = "glam-support")]
+    #[test]
+    fn test_glam_round_trip() {
+        let pose = Pose::from_6dof((1.5, -2.0, 0.25, 0.3, -0.2, 1.2));
+
+        let quat: glam::Quat = pose.orientation.into();
+        assert!(quat.is_normalized());
+        let orientation: Quaternion = quat.into();
+        assert!(orientation.dot(&pose.orientation) > 1.0 - 1e-6);
+
+        let affine: glam::Affine3A = pose.into();
+        let moved = affine.transform_point3(glam::Vec3::new(1.0, 0.0, 0.0));
+        let expected = pose.transform_point(Point::new(1.0, 0.0, 0.0));
+        assert!(Point::from(moved).distance(&expected) < 1e-5);
+
+        let back: Pose = affine.into();
+        assert!(back.position.distance(&pose.position) < 1e-6);
+        assert!(back.orientation.dot(&pose.orientation).abs() > 1.0 - 1e-6);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_nalgebra_round_trip() {
+        let pose = Pose::from_6dof((1.5, -2.0, 0.25, 0.3, -0.2, 1.2));
+        let point = Point::new(0.5, 4.0, -1.0);
+
+        let na_point: na::Point3<f64> = point.into();
+        assert_eq!(Point::from(na_point), point);
+
+        let isometry: na::Isometry3<f64> = pose.into();
+        let moved = isometry * na::Point3::from(point);
+        assert!(Point::from(moved).distance(&pose.transform_point(point)) < 1e-12);
+
+        let back: Pose = isometry.into();
+        assert!(back.position.distance(&pose.position) < 1e-12);
+        let diff = back.orientation - pose.orientation;
+        assert!(diff.norm() < 1e-12);
+
+        // Unnormalized quaternions are normalized
+        let rotation: na::UnitQuaternion<f64> = Quaternion::new(0.0, 0.0, 2.0, 0.0).into();
+        assert_eq!(
+            Quaternion::from(rotation),
+            Quaternion::new(0.0, 0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_euler_sequences() {
+        let angles = (0.3, -0.2, 1.2);
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let xyz = Pose::from_euler(EulerSeq::EXTRINSIC_XYZ, angles, origin);
+        let zyx = Pose::from_euler(
+            EulerSeq::new(RotationType::Extrinsic, RotationSequence::ZYX),
+            angles,
+            origin,
+        );
+        assert!(xyz.orientation.dot(&zyx.orientation).abs() < 1.0 - 1e-6);
+
+        // Extrinsic XYZ (roll, pitch, yaw) is intrinsic ZYX (yaw, pitch, roll)
+        let (yaw, pitch, roll) = xyz.to_euler(EulerSeq::INTRINSIC_ZYX);
+        assert!((roll - 0.3).abs() < 1e-9);
+        assert!((pitch + 0.2).abs() < 1e-9);
+        assert!((yaw - 1.2).abs() < 1e-9);
+
+        let (_, _, _, r, p, y) = xyz.to_6dof();
+        assert!((r - 0.3).abs() < 1e-9 && (p + 0.2).abs() < 1e-9 && (y - 1.2).abs() < 1e-9);
+    }
 }
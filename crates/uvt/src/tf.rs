@@ -0,0 +1,147 @@
+//! This module provides functionality to parse TF transforms from ROS messages.
+use crate::pose;
+use crate::trajectory::TrajectoryDeserializer;
+use std::io;
+
+/// A stamped transform, as `(stamp, parent frame, child frame, transform)`.
+pub type StampedTransform = (pose::Time, String, String, pose::Pose);
+
+/// Trait for deserializing tf2_msgs/TFMessage messages.
+///# Methods
+///* `read_transform_stamped` - Reads a single geometry_msgs/TransformStamped from the buffer.
+///* `read_transforms` - Reads the whole array of transforms from the buffer.
+pub trait TransformDeserializer: TrajectoryDeserializer {
+    fn read_transform_stamped(&mut self) -> Result<StampedTransform, io::Error>;
+    fn read_transforms(&mut self) -> Result<Vec<StampedTransform>, io::Error>;
+}
+
+/// Parses a tf2_msgs/TFMessage into its parent → child stamped transforms.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance that converts the raw message into structured data.
+///
+/// # Returns
+///
+/// A vector of `(stamp, parent frame, child frame, transform)` tuples.
+pub fn parse_transforms<D: TransformDeserializer>(
+    mut d: D,
+) -> Result<Vec<StampedTransform>, io::Error> {
+    d.read_transforms()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bag::BagDeserializer;
+    use crate::fixtures::MessageWriter;
+    use crate::mcap::McapDeserializer;
+
+    fn transform(position: pose::Point, orientation: pose::Quaternion) -> pose::Pose {
+        pose::Pose {
+            position,
+            orientation,
+        }
+    }
+
+    #[test]
+    fn test_parse_bag_transforms() {
+        let msg = MessageWriter::ros1()
+            .u32(2)
+            // map -> odom
+            .header(
+                7,
+                pose::Time {
+                    sec: 12,
+                    nanosec: 500,
+                },
+                "map",
+            )
+            .string("odom")
+            .pose(&transform(
+                pose::Point::new(1.0, 2.0, 3.0),
+                pose::Quaternion::new(0.0, 0.0, 0.0, 1.0),
+            ))
+            // odom -> base_link
+            .header(
+                8,
+                pose::Time {
+                    sec: 13,
+                    nanosec: 0,
+                },
+                "odom",
+            )
+            .string("base_link")
+            .pose(&transform(
+                pose::Point::new(-1.0, 0.5, 0.0),
+                pose::Quaternion::new(0.0, 0.0, 1.0, 0.0),
+            ))
+            .finish();
+
+        let transforms = parse_transforms(BagDeserializer::new(msg)).unwrap();
+
+        assert_eq!(transforms.len(), 2);
+
+        let (stamp, parent, child, tf) = &transforms[0];
+        assert_eq!(
+            *stamp,
+            pose::Time {
+                sec: 12,
+                nanosec: 500
+            }
+        );
+        assert_eq!(parent, "map");
+        assert_eq!(child, "odom");
+        assert_eq!(tf.position, pose::Point::new(1.0, 2.0, 3.0));
+        assert_eq!(tf.orientation, pose::Quaternion::new(0.0, 0.0, 0.0, 1.0));
+
+        let (stamp, parent, child, tf) = &transforms[1];
+        assert_eq!(
+            *stamp,
+            pose::Time {
+                sec: 13,
+                nanosec: 0
+            }
+        );
+        assert_eq!(parent, "odom");
+        assert_eq!(child, "base_link");
+        assert_eq!(tf.position, pose::Point::new(-1.0, 0.5, 0.0));
+        assert_eq!(tf.orientation, pose::Quaternion::new(0.0, 0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_parse_mcap_transforms() {
+        let msg = MessageWriter::cdr()
+            .u32(1)
+            .header(
+                0,
+                pose::Time {
+                    sec: 42,
+                    nanosec: 10,
+                },
+                "map",
+            )
+            .string("odom")
+            .pose(&transform(
+                pose::Point::new(4.0, 5.0, 6.0),
+                pose::Quaternion::new(0.0, 0.0, 0.0, 1.0),
+            ))
+            .finish();
+
+        let transforms = parse_transforms(McapDeserializer::new(msg)).unwrap();
+
+        assert_eq!(transforms.len(), 1);
+        let (stamp, parent, child, tf) = &transforms[0];
+        assert_eq!(
+            *stamp,
+            pose::Time {
+                sec: 42,
+                nanosec: 10
+            }
+        );
+        assert_eq!(parent, "map");
+        assert_eq!(child, "odom");
+        assert_eq!(tf.position, pose::Point::new(4.0, 5.0, 6.0));
+        assert_eq!(tf.orientation, pose::Quaternion::new(0.0, 0.0, 0.0, 1.0));
+    }
+}
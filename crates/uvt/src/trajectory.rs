@@ -2,6 +2,10 @@
 use crate::deserialization::BufferReader;
 use crate::pose;
 use std::io;
+use std::time::Duration;
+
+/// Deviation from 1 of the norm of an orientation, under which it is kept as is
+const UNIT_NORM_TOLERANCE: f64 = 1e-6;
 
 /// Trait for deserializing Trajectory messages.
 ///# Methods
@@ -9,14 +13,66 @@ use std::io;
 ///* `read_orientation` - Reads a Quaternion representing orientation from the buffer.
 ///* `read_covariance` - Reads a vector of f64 representing covariance from the buffer.
 ///* `read_vector` - Reads a Vector3 from the buffer.
+///* `read_pose` - Reads a Pose from the buffer.
+///* `read_poses` - Reads an array of Poses from the buffer.
+///* `read_stamped_poses` - Reads an array of PoseStamped from the buffer.
 pub trait TrajectoryDeserializer: BufferReader {
     fn read_position(&mut self) -> Result<pose::Point, io::Error>;
     fn read_orientation(&mut self) -> Result<pose::Quaternion, io::Error>;
     fn read_covariance(&mut self) -> Result<Vec<f64>, io::Error>;
     fn read_vector(&mut self) -> Result<pose::Vector3, io::Error>;
+    fn read_pose(&mut self) -> Result<pose::Pose, io::Error>;
+    fn read_poses(&mut self) -> Result<Vec<pose::Pose>, io::Error>;
+    fn read_stamped_poses(&mut self) -> Result<Vec<pose::PoseStamped>, io::Error>;
+}
+
+/// Message layout of the trajectory topic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrajectoryType {
+    /// `nav_msgs/Odometry`, one stamped pose per message.
+    #[default]
+    Odometry,
+    /// `geometry_msgs/PoseArray`, several poses sharing the message stamp.
+    PoseArray,
+    /// `nav_msgs/Path`, several stamped poses per message.
+    Path,
+    /// `geometry_msgs/PoseStamped`, one stamped pose per message.
+    PoseStamped,
+    /// `geometry_msgs/PoseWithCovarianceStamped`, one stamped pose per message.
+    PoseWithCovarianceStamped,
+}
+
+/// Checks an orientation read from a trajectory, so that later conversions do not fail.
+///
+/// Orientations whose norm is within [`UNIT_NORM_TOLERANCE`] of 1 are kept as is, and other
+/// ones are normalized. A zero or non-finite quaternion, as published by some odometry sources
+/// without orientation, cannot be normalized and gives `None`.
+fn checked_orientation(q: pose::Quaternion) -> Option<pose::Quaternion> {
+    if (q.norm() - 1.0).abs() <= UNIT_NORM_TOLERANCE {
+        return Some(q);
+    }
+    q.try_normalized()
+}
+
+/// Checks the orientations of trajectory poses, see [`checked_orientation`]. The orientations
+/// that cannot be normalized are replaced by the identity.
+///
+/// # Returns
+///
+/// The number of orientations replaced by the identity.
+pub(crate) fn check_orientations(poses: &mut [pose::PoseStamped]) -> usize {
+    let mut replaced = 0;
+    for p in poses {
+        p.pose.orientation = checked_orientation(p.pose.orientation).unwrap_or_else(|| {
+            replaced += 1;
+            pose::Quaternion::new(0.0, 0.0, 0.0, 1.0)
+        });
+    }
+    replaced
 }
 
 /// Parses a trajectory message from raw data into a sequence of PoseStamped instances.
+/// The covariances and twist of the message are discarded, see [`parse_odometry`] to keep them.
 ///
 /// # Arguments
 ///
@@ -26,37 +82,513 @@ pub trait TrajectoryDeserializer: BufferReader {
 ///
 /// A vector of PoseStamped elements representing the trajectory.
 pub fn parse_trajectory<D: TrajectoryDeserializer>(
-    mut d: D,
+    d: D,
 ) -> Result<pose::PoseStamped, std::io::Error> {
+    let odometry = parse_odometry(d)?;
+
+    Ok(pose::PoseStamped {
+        header: odometry.header,
+        pose: odometry.pose.pose,
+    })
+}
+
+/// Parses a nav_msgs/Odometry message, keeping its covariances and twist.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance that converts the raw message into structured data.
+///
+/// # Returns
+///
+/// The odometry of the message.
+pub fn parse_odometry<D: TrajectoryDeserializer>(mut d: D) -> Result<pose::Odometry, io::Error> {
     // Message header
     let header = d.read_header()?;
 
-    let _child_frame = d.read_lp_string_aligned(8)?;
+    let child_frame_id = d.read_lp_string_aligned(8)?;
 
     // Message pose
     let position = d.read_position()?;
     let orientation = d.read_orientation()?;
 
-    // TODO: Implement PoseWithCovarianceStamped
-    // https://docs.ros.org/en/noetic/api/geometry_msgs/html/msg/PoseWithCovarianceStamped.html
-
     // Pose covariance
     // 6 x 6 covariance matrix = 36 covariance values
-    let _pose_covariance = d.read_covariance()?;
+    let pose_covariance = covariance_matrix(d.read_covariance()?)?;
 
     // Twist values
-    let _twist_linear = d.read_vector()?;
-    let _twist_angular = d.read_vector()?;
+    let linear = d.read_vector()?;
+    let angular = d.read_vector()?;
 
     // Twist covariance
     // 6 x 6 covariance matrix = 36 covariance values
-    let _twist_covariance = d.read_covariance()?;
+    let twist_covariance = covariance_matrix(d.read_covariance()?)?;
 
-    Ok(pose::PoseStamped {
-        header: header,
-        pose: pose::Pose {
-            position: position,
-            orientation: orientation,
+    Ok(pose::Odometry {
+        header,
+        child_frame_id,
+        pose: pose::PoseWithCovariance {
+            pose: pose::Pose {
+                position,
+                orientation,
+            },
+            covariance: pose_covariance,
         },
+        twist: pose::TwistWithCovariance {
+            twist: pose::Twist { linear, angular },
+            covariance: twist_covariance,
+        },
+    })
+}
+
+/// Converts the values read by [`TrajectoryDeserializer::read_covariance`] to a 6x6 matrix.
+fn covariance_matrix(values: Vec<f64>) -> Result<[f64; 36], io::Error> {
+    values.try_into().map_err(|values: Vec<f64>| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Expected 36 covariance values, got {}", values.len()),
+        )
+    })
+}
+
+/// Parses a geometry_msgs/PoseArray message into a sequence of PoseStamped instances.
+///
+/// PoseArray poses have no stamp of their own, so each one is given the message header.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance that converts the raw message into structured data.
+///
+/// # Returns
+///
+/// A vector of PoseStamped elements, in message order.
+pub fn parse_pose_array<D: TrajectoryDeserializer>(
+    mut d: D,
+) -> Result<Vec<pose::PoseStamped>, io::Error> {
+    let header = d.read_header()?;
+    let poses = d.read_poses()?;
+
+    Ok(poses
+        .into_iter()
+        .map(|pose| pose::PoseStamped {
+            header: header.clone(),
+            pose,
+        })
+        .collect())
+}
+
+/// Parses a geometry_msgs/PoseStamped message.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance that converts the raw message into structured data.
+///
+/// # Returns
+///
+/// The stamped pose of the message.
+pub fn parse_pose_stamped<D: TrajectoryDeserializer>(
+    mut d: D,
+) -> Result<pose::PoseStamped, io::Error> {
+    Ok(pose::PoseStamped {
+        header: d.read_header()?,
+        pose: d.read_pose()?,
+    })
+}
+
+/// Parses a geometry_msgs/PoseWithCovarianceStamped message. The covariance is discarded.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance that converts the raw message into structured data.
+///
+/// # Returns
+///
+/// The stamped pose of the message.
+pub fn parse_pose_with_covariance_stamped<D: TrajectoryDeserializer>(
+    mut d: D,
+) -> Result<pose::PoseStamped, io::Error> {
+    let header = d.read_header()?;
+    let pose = d.read_pose()?;
+
+    // Pose covariance
+    // 6 x 6 covariance matrix = 36 covariance values
+    let _pose_covariance = d.read_covariance()?;
+
+    Ok(pose::PoseStamped { header, pose })
+}
+
+/// Parses a nav_msgs/Path message into a sequence of PoseStamped instances.
+///
+/// Each pose keeps its own header. The header of the message itself is discarded.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance that converts the raw message into structured data.
+///
+/// # Returns
+///
+/// A vector of PoseStamped elements, in message order.
+pub fn parse_path<D: TrajectoryDeserializer>(
+    mut d: D,
+) -> Result<Vec<pose::PoseStamped>, io::Error> {
+    let _header = d.read_header()?;
+    d.read_stamped_poses()
+}
+
+/// Parses a trajectory message of type `trajectory_type` into its stamped poses.
+/// Orientations are kept as read, without normalization.
+///
+/// # Arguments
+///
+/// * `deserializer` - An instance that converts the raw message into structured data.
+/// * `trajectory_type` - The message layout of the trajectory topic.
+///
+/// # Returns
+///
+/// A vector of PoseStamped elements, in message order.
+pub fn parse_poses<D: TrajectoryDeserializer>(
+    d: D,
+    trajectory_type: TrajectoryType,
+) -> Result<Vec<pose::PoseStamped>, io::Error> {
+    Ok(match trajectory_type {
+        TrajectoryType::Odometry => vec![parse_trajectory(d)?],
+        TrajectoryType::PoseArray => parse_pose_array(d)?,
+        TrajectoryType::Path => parse_path(d)?,
+        TrajectoryType::PoseStamped => vec![parse_pose_stamped(d)?],
+        TrajectoryType::PoseWithCovarianceStamped => vec![parse_pose_with_covariance_stamped(d)?],
     })
 }
+
+/// How [`remove_jumps`] handles the poses detected as jumps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JumpHandling {
+    /// Drop the jumping poses from the trajectory.
+    #[default]
+    Remove,
+    /// Replace the position of the jumping poses by the one interpolated in time between
+    /// their neighbors. Their orientation and header are kept.
+    Interpolate,
+}
+
+/// Handles isolated jumps (or teleports) of a trajectory, as produced by GPS outliers.
+///
+/// A pose is a jump when it is farther than `max_step` from both its previous and next poses.
+/// The first and last poses only have one neighbor, so they are never considered as jumps.
+///
+/// # Arguments
+///
+/// * `poses` - The trajectory, sorted by time.
+/// * `max_step` - The largest distance expected between consecutive poses.
+/// * `handling` - Whether the jumps are removed or interpolated.
+///
+/// # Returns
+///
+/// The number of poses that were detected as jumps.
+pub fn remove_jumps(
+    poses: &mut Vec<pose::PoseStamped>,
+    max_step: f64,
+    handling: JumpHandling,
+) -> usize {
+    let jumps: Vec<usize> = (1..poses.len().saturating_sub(1))
+        .filter(|&i| {
+            let position = &poses[i].pose.position;
+            position.distance(&poses[i - 1].pose.position) > max_step
+                && position.distance(&poses[i + 1].pose.position) > max_step
+        })
+        .collect();
+
+    match handling {
+        JumpHandling::Remove => {
+            let mut i = 0;
+            poses.retain(|_| {
+                i += 1;
+                jumps.binary_search(&(i - 1)).is_err()
+            });
+        }
+        JumpHandling::Interpolate => {
+            for &i in &jumps {
+                let (prev, next) = (&poses[i - 1], &poses[i + 1]);
+                let t_prev = Duration::from(prev.header.stamp).as_secs_f64();
+                let t_next = Duration::from(next.header.stamp).as_secs_f64();
+                let t = Duration::from(poses[i].header.stamp).as_secs_f64();
+                let ratio = if t_next > t_prev {
+                    ((t - t_prev) / (t_next - t_prev)).clamp(0.0, 1.0)
+                } else {
+                    0.5
+                };
+                let (a, b) = (prev.pose.position, next.pose.position);
+                poses[i].pose.position = a + (b - a) * ratio;
+            }
+        }
+    }
+
+    jumps.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bag::BagDeserializer;
+    use crate::fixtures::{self, MessageWriter};
+    use crate::mcap::McapDeserializer;
+
+    fn pose_array(mut w: MessageWriter, poses: &[pose::Pose]) -> Vec<u8> {
+        w.header(3, pose::Time { sec: 7, nanosec: 9 }, "map")
+            .u32(poses.len() as u32);
+        poses.iter().for_each(|pose| {
+            w.pose(pose);
+        });
+        w.finish()
+    }
+
+    fn check_pose_array(parsed: Vec<pose::PoseStamped>, poses: &[pose::Pose]) {
+        assert_eq!(parsed.len(), poses.len());
+        for (stamped, pose) in parsed.iter().zip(poses) {
+            assert_eq!(stamped.header.stamp, pose::Time { sec: 7, nanosec: 9 });
+            assert_eq!(stamped.header.frame_id, "map");
+            assert_eq!(stamped.pose.position, pose.position);
+            assert_eq!(stamped.pose.orientation, pose.orientation);
+        }
+    }
+
+    #[test]
+    fn test_parse_pose_array() {
+        let poses = [
+            pose::Pose::from_6dof((1.0, 2.0, 3.0, 0.0, 0.0, 0.0)),
+            pose::Pose::from_6dof((-4.0, 5.0, 0.5, 0.0, 0.0, 1.0)),
+        ];
+
+        let msg = pose_array(MessageWriter::ros1(), &poses);
+        check_pose_array(parse_pose_array(BagDeserializer::new(msg)).unwrap(), &poses);
+
+        let msg = pose_array(MessageWriter::cdr(), &poses);
+        check_pose_array(
+            parse_pose_array(McapDeserializer::new(msg)).unwrap(),
+            &poses,
+        );
+    }
+
+    fn path(mut w: MessageWriter, poses: &[pose::PoseStamped]) -> Vec<u8> {
+        w.header(0, pose::Time { sec: 7, nanosec: 9 }, "map")
+            .u32(poses.len() as u32);
+        poses.iter().for_each(|p| {
+            let h = &p.header;
+            w.header(h.seq, h.stamp, &h.frame_id).pose(&p.pose);
+        });
+        w.finish()
+    }
+
+    fn check_pose_stamped(parsed: pose::PoseStamped, expected: &pose::PoseStamped) {
+        assert_eq!(parsed.header.stamp, expected.header.stamp);
+        assert_eq!(parsed.header.frame_id, expected.header.frame_id);
+        assert_eq!(parsed.pose, expected.pose);
+    }
+
+    #[test]
+    fn test_parse_pose_stamped() {
+        let expected = &straight_line()[3];
+        let msg = |mut w: MessageWriter| {
+            let h = &expected.header;
+            w.header(h.seq, h.stamp, &h.frame_id).pose(&expected.pose);
+            w.finish()
+        };
+
+        let parsed = parse_pose_stamped(BagDeserializer::new(msg(MessageWriter::ros1())));
+        check_pose_stamped(parsed.unwrap(), expected);
+        let parsed = parse_pose_stamped(McapDeserializer::new(msg(MessageWriter::cdr())));
+        check_pose_stamped(parsed.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_pose_with_covariance_stamped() {
+        let expected = &straight_line()[3];
+        let msg = |mut w: MessageWriter| {
+            let h = &expected.header;
+            w.header(h.seq, h.stamp, &h.frame_id).pose(&expected.pose);
+            (0..36).for_each(|i| {
+                w.f64(i as f64);
+            });
+            w.finish()
+        };
+
+        let d = BagDeserializer::new(msg(MessageWriter::ros1()));
+        check_pose_stamped(parse_pose_with_covariance_stamped(d).unwrap(), expected);
+        let d = McapDeserializer::new(msg(MessageWriter::cdr()));
+        check_pose_stamped(parse_pose_with_covariance_stamped(d).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_odometry() {
+        let expected = &straight_line()[3];
+        let msg = |mut w: MessageWriter| {
+            let h = &expected.header;
+            w.header(h.seq, h.stamp, &h.frame_id)
+                .string("base_link")
+                .pose(&expected.pose);
+            (0..36 + 6 + 36).for_each(|i| {
+                w.f64(i as f64);
+            });
+            w.finish()
+        };
+
+        let pose_covariance: [f64; 36] = std::array::from_fn(|i| i as f64);
+        let twist_covariance: [f64; 36] = std::array::from_fn(|i| (42 + i) as f64);
+        for odometry in [
+            parse_odometry(BagDeserializer::new(msg(MessageWriter::ros1()))).unwrap(),
+            parse_odometry(McapDeserializer::new(msg(MessageWriter::cdr()))).unwrap(),
+        ] {
+            assert_eq!(odometry.header.stamp, expected.header.stamp);
+            assert_eq!(odometry.child_frame_id, "base_link");
+            assert_eq!(odometry.pose.pose, expected.pose);
+            assert_eq!(odometry.pose.covariance, pose_covariance);
+            assert_eq!(
+                odometry.twist.twist.linear,
+                pose::Vector3::new(36.0, 37.0, 38.0)
+            );
+            assert_eq!(
+                odometry.twist.twist.angular,
+                pose::Vector3::new(39.0, 40.0, 41.0)
+            );
+            assert_eq!(odometry.twist.covariance, twist_covariance);
+        }
+    }
+
+    #[test]
+    fn test_check_orientations() {
+        let expected = &straight_line()[2];
+        let h = &expected.header;
+        let with_orientation = |orientation| pose::Pose {
+            orientation,
+            ..expected.pose
+        };
+        let msg = |w, input: &pose::Pose| {
+            fixtures::odometry(w, h.seq, h.stamp, &h.frame_id, "base_link", input)
+        };
+
+        let zero = with_orientation(pose::Quaternion::new(0.0, 0.0, 0.0, 0.0));
+        let unnormalized = with_orientation(pose::Quaternion::new(0.0, 0.0, 2.0, 0.0));
+        let mut poses = vec![
+            parse_trajectory(BagDeserializer::new(msg(MessageWriter::ros1(), &zero))).unwrap(),
+            parse_trajectory(McapDeserializer::new(msg(
+                MessageWriter::cdr(),
+                &unnormalized,
+            )))
+            .unwrap(),
+            expected.clone(),
+        ];
+
+        assert_eq!(check_orientations(&mut poses), 1);
+        assert_eq!(poses[0].pose.position, expected.pose.position);
+        assert_eq!(
+            poses[0].pose.orientation,
+            pose::Quaternion::new(0.0, 0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            poses[1].pose.orientation,
+            pose::Quaternion::new(0.0, 0.0, 1.0, 0.0)
+        );
+        assert_eq!(poses[2], *expected);
+
+        // The identity can be converted, without panicking
+        assert_eq!(poses[0].pose.to_6dof().3, 0.0);
+    }
+
+    #[test]
+    fn test_parse_odometry_child_frame() {
+        let expected = &straight_line()[1];
+        let h = &expected.header;
+        // Lengths needing different paddings before the CDR pose
+        for child_frame_id in ["base_link", "imu", "velodyne_top"] {
+            let msg = |w| {
+                fixtures::odometry(
+                    w,
+                    h.seq,
+                    h.stamp,
+                    &h.frame_id,
+                    child_frame_id,
+                    &expected.pose,
+                )
+            };
+
+            let d = BagDeserializer::new(msg(MessageWriter::ros1()));
+            let odometry = parse_odometry(d).unwrap();
+            assert_eq!(odometry.child_frame_id, child_frame_id);
+            assert_eq!(odometry.pose.pose, expected.pose);
+
+            let d = McapDeserializer::new(msg(MessageWriter::cdr()));
+            let odometry = parse_odometry(d).unwrap();
+            assert_eq!(odometry.child_frame_id, child_frame_id);
+            assert_eq!(odometry.pose.pose, expected.pose);
+        }
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let poses = &straight_line()[..2];
+
+        let msg = path(MessageWriter::ros1(), poses);
+        assert_eq!(parse_path(BagDeserializer::new(msg)).unwrap(), poses);
+
+        // CDR headers have no sequence number
+        let msg = path(MessageWriter::cdr(), poses);
+        let parsed = parse_path(McapDeserializer::new(msg)).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (parsed, pose) in parsed.iter().zip(poses) {
+            assert_eq!(parsed.header.stamp, pose.header.stamp);
+            assert_eq!(parsed.header.frame_id, pose.header.frame_id);
+            assert_eq!(parsed.pose, pose.pose);
+        }
+    }
+
+    fn straight_line() -> Vec<pose::PoseStamped> {
+        (0..5)
+            .map(|i| {
+                pose::PoseStamped::new(
+                    pose::Header {
+                        seq: i,
+                        stamp: pose::Time {
+                            sec: i as i32,
+                            nanosec: 0,
+                        },
+                        frame_id: String::from("map"),
+                    },
+                    pose::Pose::from_6dof((i as f64, 0.0, 0.0, 0.0, 0.0, 0.0)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_remove_jumps() {
+        let mut poses = straight_line();
+        poses[2].pose.position = pose::Point::new(2.0, 50.0, 0.0);
+
+        assert_eq!(remove_jumps(&mut poses, 1.5, JumpHandling::Remove), 1);
+        let seqs: Vec<u32> = poses.iter().map(|p| p.header.seq).collect();
+        assert_eq!(seqs, vec![0, 1, 3, 4]);
+
+        // Nothing left to remove
+        assert_eq!(remove_jumps(&mut poses, 2.5, JumpHandling::Remove), 0);
+        assert_eq!(poses.len(), 4);
+    }
+
+    #[test]
+    fn test_interpolate_jumps() {
+        let mut poses = straight_line();
+        poses[2].pose.position = pose::Point::new(2.0, 50.0, 0.0);
+
+        assert_eq!(remove_jumps(&mut poses, 1.5, JumpHandling::Interpolate), 1);
+        assert_eq!(poses.len(), 5);
+        assert_eq!(poses[2].pose.position, pose::Point::new(2.0, 0.0, 0.0));
+        assert_eq!(poses, straight_line());
+    }
+
+    #[test]
+    fn test_parse_empty_pose_array() {
+        let msg = pose_array(MessageWriter::cdr(), &[]);
+        assert!(
+            parse_pose_array(McapDeserializer::new(msg))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}